@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+
+/// Passes the revision of `ruffle_render` that was built against on to the tessellation cache,
+/// since a different tessellator can produce different meshes for the same shape.
+fn main() {
+    let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let lockfiles = [
+        manifest_dir.join("Cargo.lock"),
+        manifest_dir.join("../Cargo.lock"),
+    ];
+    let mut revision = None;
+    for lockfile in &lockfiles {
+        println!("cargo:rerun-if-changed={}", lockfile.display());
+        if revision.is_none() {
+            revision = std::fs::read_to_string(lockfile)
+                .ok()
+                .and_then(|lock| ruffle_render_source(&lock));
+        }
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+    println!(
+        "cargo:rustc-env=RUFFLE_RENDER_REVISION={}",
+        revision.as_deref().unwrap_or("unknown")
+    );
+}
+
+/// Finds the `source` of the `ruffle_render` package in a Cargo.lock, which ends in the commit
+/// hash for git dependencies.
+fn ruffle_render_source(lock: &str) -> Option<String> {
+    let package = lock
+        .split("[[package]]")
+        .find(|package| package.lines().any(|line| line == "name = \"ruffle_render\""))?;
+    let source = package
+        .lines()
+        .find_map(|line| line.strip_prefix("source = "))?;
+    Some(source.trim_matches('"').to_string())
+}
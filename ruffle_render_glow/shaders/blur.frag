@@ -0,0 +1,25 @@
+#version 100
+
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+    precision highp float;
+#else
+    precision mediump float;
+#endif
+
+uniform vec4 mult_color;
+
+uniform sampler2D u_texture;
+uniform vec2 u_blur_step;
+
+varying vec2 frag_uv;
+
+void main() {
+    // 5x5 box blur, spread out by `u_blur_step` and smoothed by linear filtering.
+    vec3 sum = vec3(0.0);
+    for (int x = -2; x <= 2; x++) {
+        for (int y = -2; y <= 2; y++) {
+            sum += texture2D(u_texture, frag_uv + vec2(float(x), float(y)) * u_blur_step).rgb;
+        }
+    }
+    gl_FragColor = vec4(sum / 25.0 * mult_color.rgb, 1.0);
+}
@@ -0,0 +1,29 @@
+#version 300 es
+
+precision mediump float;
+
+layout(std140) uniform DrawBlock {
+    mat4 world_matrix;
+    vec4 mult_color;
+    vec4 add_color;
+};
+
+uniform sampler2D u_texture;
+
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+void main() {
+    vec4 color = texture(u_texture, frag_uv);
+
+    // Unmultiply alpha before apply color transform.
+    if (color.a > 0.0) {
+        color.rgb /= color.a;
+        color = clamp(mult_color * color + add_color, 0.0, 1.0);
+        float alpha = clamp(color.a, 0.0, 1.0);
+        color = vec4(color.rgb * alpha, alpha);
+    }
+
+    out_color = color;
+}
@@ -0,0 +1,25 @@
+#version 300 es
+
+precision mediump float;
+
+uniform sampler2D u_texture;
+
+in vec2 frag_uv;
+flat in vec4 frag_mult_color;
+flat in vec4 frag_add_color;
+
+out vec4 out_color;
+
+void main() {
+    vec4 color = texture(u_texture, frag_uv);
+
+    // Unmultiply alpha before apply color transform.
+    if (color.a > 0.0) {
+        color.rgb /= color.a;
+        color = clamp(frag_mult_color * color + frag_add_color, 0.0, 1.0);
+        float alpha = clamp(color.a, 0.0, 1.0);
+        color = vec4(color.rgb * alpha, alpha);
+    }
+
+    out_color = color;
+}
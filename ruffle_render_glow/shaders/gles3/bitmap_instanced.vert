@@ -0,0 +1,32 @@
+#version 300 es
+
+precision highp float;
+
+layout(std140) uniform ViewBlock {
+    mat4 view_matrix;
+};
+
+layout(location = 0) in vec2 position;
+
+// Per-instance attributes: the 2x2 part of the world matrix, its translation and the color transform.
+layout(location = 2) in vec4 instance_matrix;
+layout(location = 3) in vec2 instance_translation;
+layout(location = 4) in vec4 instance_mult_color;
+layout(location = 5) in vec4 instance_add_color;
+
+out vec2 frag_uv;
+flat out vec4 frag_mult_color;
+flat out vec4 frag_add_color;
+
+void main() {
+    mat4 world_matrix = mat4(
+        instance_matrix.x, instance_matrix.y, 0.0, 0.0,
+        instance_matrix.z, instance_matrix.w, 0.0, 0.0,
+        0.0, 0.0, 1.0, 0.0,
+        instance_translation.x, instance_translation.y, 0.0, 1.0
+    );
+    frag_uv = position;
+    frag_mult_color = instance_mult_color;
+    frag_add_color = instance_add_color;
+    gl_Position = view_matrix * (world_matrix * vec4(position, 0.0, 1.0));
+}
@@ -0,0 +1,27 @@
+#version 300 es
+
+precision mediump float;
+
+layout(std140) uniform DrawBlock {
+    mat4 world_matrix;
+    vec4 mult_color;
+    vec4 add_color;
+};
+
+uniform sampler2D u_texture;
+uniform vec2 u_blur_step;
+
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+void main() {
+    // 5x5 box blur, spread out by `u_blur_step` and smoothed by linear filtering.
+    vec3 sum = vec3(0.0);
+    for (int x = -2; x <= 2; x++) {
+        for (int y = -2; y <= 2; y++) {
+            sum += texture(u_texture, frag_uv + vec2(float(x), float(y)) * u_blur_step).rgb;
+        }
+    }
+    out_color = vec4(sum / 25.0 * mult_color.rgb, 1.0);
+}
@@ -0,0 +1,11 @@
+#version 300 es
+
+precision mediump float;
+
+in vec4 frag_color;
+
+out vec4 out_color;
+
+void main() {
+    out_color = frag_color;
+}
@@ -0,0 +1,25 @@
+#version 300 es
+
+precision highp float;
+
+layout(std140) uniform ViewBlock {
+    mat4 view_matrix;
+};
+
+layout(std140) uniform DrawBlock {
+    mat4 world_matrix;
+    vec4 mult_color;
+    vec4 add_color;
+};
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec4 color;
+
+out vec4 frag_color;
+
+void main() {
+    frag_color = clamp(color * mult_color + add_color, 0.0, 1.0);
+    float alpha = clamp(frag_color.a, 0.0, 1.0);
+    frag_color = vec4(frag_color.rgb * alpha, alpha);
+    gl_Position = view_matrix * (world_matrix * vec4(position, 0.0, 1.0));
+}
@@ -0,0 +1,85 @@
+#version 300 es
+
+precision highp float;
+
+layout(std140) uniform DrawBlock {
+    mat4 world_matrix;
+    vec4 mult_color;
+    vec4 add_color;
+};
+
+uniform int u_gradient_type;
+uniform float u_ratios[16];
+uniform vec4 u_colors[16];
+uniform int u_repeat_mode;
+uniform float u_focal_point;
+uniform int u_interpolation;
+
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+vec4 transform_color(vec4 color) {
+    return clamp(mult_color * color + add_color, 0.0, 1.0);
+}
+
+vec3 linear_to_srgb(vec3 linear) {
+    vec3 a = 12.92 * linear;
+    vec3 b = 1.055 * pow(linear, vec3(1.0 / 2.4)) - 0.055;
+    vec3 c = step(vec3(0.0031308), linear);
+    return mix(a, b, c);
+}
+
+void main() {
+    float t;
+    if (u_gradient_type == 0) {
+        t = frag_uv.x;
+    } else if (u_gradient_type == 1) {
+        t = length(frag_uv * 2.0 - 1.0);
+    } else {
+        vec2 uv = frag_uv * 2.0 - 1.0;
+        vec2 d = vec2(u_focal_point, 0.0) - uv;
+        float l = length(d);
+        d /= l;
+        t = l / (sqrt(1.0 -  u_focal_point*u_focal_point*d.y*d.y) + u_focal_point*d.x);
+    }
+    if (u_repeat_mode == 0) {
+        // Clamp
+        t = clamp(t, 0.0, 1.0);
+    } else if (u_repeat_mode == 1) {
+        // Repeat
+        t = fract(t);
+    } else {
+        // Mirror
+        if (t < 0.0) {
+            t = -t;
+        }
+
+        if (int(mod(t, 2.0)) == 0) {
+            t = fract(t);
+        } else {
+            t = 1.0 - fract(t);
+        }
+    }
+
+    // GLSL ES 3.00 allows dynamic indexing of uniform arrays, so we can simply walk the ratios.
+    vec4 color = transform_color(u_colors[14]);
+    if (t <= u_ratios[0]) {
+        color = transform_color(u_colors[0]);
+    } else {
+        for (int i = 1; i < 15; i++) {
+            if (t <= u_ratios[i]) {
+                float a = (t - u_ratios[i - 1]) / (u_ratios[i] - u_ratios[i - 1]);
+                color = mix(transform_color(u_colors[i - 1]), transform_color(u_colors[i]), a);
+                break;
+            }
+        }
+    }
+
+    if (u_interpolation != 0) {
+        color = vec4(linear_to_srgb(vec3(color)), color.a);
+    }
+
+    float alpha = clamp(color.a, 0.0, 1.0);
+    out_color = vec4(color.rgb * alpha, alpha);
+}
@@ -0,0 +1,19 @@
+#version 300 es
+
+precision mediump float;
+
+uniform mediump float u_antialias;
+
+in vec4 frag_color;
+in float frag_distance;
+flat in float frag_half_width;
+
+out vec4 out_color;
+
+void main() {
+    float coverage = 1.0;
+    if (u_antialias > 0.0) {
+        coverage = clamp(frag_half_width + 0.5 - abs(frag_distance), 0.0, 1.0);
+    }
+    out_color = frag_color * coverage;
+}
@@ -0,0 +1,49 @@
+#version 300 es
+
+precision highp float;
+
+layout(std140) uniform ViewBlock {
+    mat4 view_matrix;
+};
+
+layout(std140) uniform DrawBlock {
+    mat4 world_matrix;
+    vec4 mult_color;
+    vec4 add_color;
+};
+
+uniform mediump float u_antialias;
+
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec4 color;
+layout(location = 2) in vec2 tangent;
+layout(location = 3) in vec4 stroke;
+
+out vec4 frag_color;
+out float frag_distance;
+flat out float frag_half_width;
+
+void main() {
+    frag_color = clamp(color * mult_color + add_color, 0.0, 1.0);
+    float alpha = clamp(frag_color.a, 0.0, 1.0);
+    frag_color = vec4(frag_color.rgb * alpha, alpha);
+
+    // The thickness only follows the axes that the line scale mode allows.
+    float scale = 1.0;
+    if (stroke.w > 1.5) {
+        scale = length(world_matrix[1].xy);
+    } else if (stroke.w > 0.5) {
+        scale = length(world_matrix[0].xy);
+    }
+    frag_half_width = stroke.z * scale;
+
+    // Extrude across and along the transformed segment, in pixels. Only the distance across
+    // fades out, so leave room for a one pixel fade-out there when antialiasing.
+    vec2 direction = normalize((world_matrix * vec4(tangent, 0.0, 0.0)).xy);
+    vec2 normal = vec2(-direction.y, direction.x);
+    frag_distance = stroke.x * (frag_half_width + u_antialias);
+
+    vec4 center = world_matrix * vec4(position, 0.0, 1.0);
+    vec2 offset = normal * frag_distance + direction * (stroke.y * frag_half_width);
+    gl_Position = view_matrix * (center + vec4(offset, 0.0, 0.0));
+}
@@ -0,0 +1,24 @@
+#version 300 es
+
+precision highp float;
+
+layout(std140) uniform ViewBlock {
+    mat4 view_matrix;
+};
+
+layout(std140) uniform DrawBlock {
+    mat4 world_matrix;
+    vec4 mult_color;
+    vec4 add_color;
+};
+
+uniform mat3 u_matrix;
+
+layout(location = 0) in vec2 position;
+
+out vec2 frag_uv;
+
+void main() {
+    frag_uv = vec2(u_matrix * vec3(position, 1.0));
+    gl_Position = view_matrix * (world_matrix * vec4(position, 0.0, 1.0));
+}
@@ -0,0 +1,32 @@
+#version 300 es
+
+precision mediump float;
+
+layout(std140) uniform DrawBlock {
+    mat4 world_matrix;
+    vec4 mult_color;
+    vec4 add_color;
+};
+
+// The Y plane, and the U, V and optional alpha planes of a YUV 4:2:0 frame.
+uniform sampler2D u_texture;
+uniform sampler2D u_texture_u;
+uniform sampler2D u_texture_v;
+uniform sampler2D u_texture_a;
+uniform float u_alpha_plane;
+
+in vec2 frag_uv;
+
+out vec4 out_color;
+
+void main() {
+    // BT.601, limited range.
+    float y = 1.164 * (texture(u_texture, frag_uv).r - 0.0625);
+    float u = texture(u_texture_u, frag_uv).r - 0.5;
+    float v = texture(u_texture_v, frag_uv).r - 0.5;
+    vec3 rgb = clamp(vec3(y + 1.596 * v, y - 0.391 * u - 0.813 * v, y + 2.018 * u), 0.0, 1.0);
+    float a = mix(1.0, texture(u_texture_a, frag_uv).r, u_alpha_plane);
+
+    vec4 color = clamp(mult_color * vec4(rgb, a) + add_color, 0.0, 1.0);
+    out_color = vec4(color.rgb * color.a, color.a);
+}
@@ -0,0 +1,21 @@
+#version 100
+
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+    precision highp float;
+#else
+    precision mediump float;
+#endif
+
+uniform mediump float u_antialias;
+
+varying vec4 frag_color;
+varying float frag_distance;
+varying float frag_half_width;
+
+void main() {
+    float coverage = 1.0;
+    if (u_antialias > 0.0) {
+        coverage = clamp(frag_half_width + 0.5 - abs(frag_distance), 0.0, 1.0);
+    }
+    gl_FragColor = frag_color * coverage;
+}
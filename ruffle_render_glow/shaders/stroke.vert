@@ -0,0 +1,47 @@
+#version 100
+
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+    precision highp float;
+#else
+    precision mediump float;
+#endif
+
+uniform mat4 view_matrix;
+uniform mat4 world_matrix;
+uniform vec4 mult_color;
+uniform vec4 add_color;
+uniform mediump float u_antialias;
+
+attribute vec2 position;
+attribute vec4 color;
+attribute vec2 tangent;
+attribute vec4 stroke;
+
+varying vec4 frag_color;
+varying float frag_distance;
+varying float frag_half_width;
+
+void main() {
+    frag_color = clamp(color * mult_color + add_color, 0.0, 1.0);
+    float alpha = clamp(frag_color.a, 0.0, 1.0);
+    frag_color = vec4(frag_color.rgb * alpha, alpha);
+
+    // The thickness only follows the axes that the line scale mode allows.
+    float scale = 1.0;
+    if (stroke.w > 1.5) {
+        scale = length(world_matrix[1].xy);
+    } else if (stroke.w > 0.5) {
+        scale = length(world_matrix[0].xy);
+    }
+    frag_half_width = stroke.z * scale;
+
+    // Extrude across and along the transformed segment, in pixels. Only the distance across
+    // fades out, so leave room for a one pixel fade-out there when antialiasing.
+    vec2 direction = normalize((world_matrix * vec4(tangent, 0.0, 0.0)).xy);
+    vec2 normal = vec2(-direction.y, direction.x);
+    frag_distance = stroke.x * (frag_half_width + u_antialias);
+
+    vec4 center = world_matrix * vec4(position, 0.0, 1.0);
+    vec2 offset = normal * frag_distance + direction * (stroke.y * frag_half_width);
+    gl_Position = view_matrix * (center + vec4(offset, 0.0, 0.0));
+}
@@ -0,0 +1,34 @@
+#version 100
+
+#ifdef GL_FRAGMENT_PRECISION_HIGH
+    precision highp float;
+#else
+    precision mediump float;
+#endif
+
+uniform mat4 view_matrix;
+uniform mat4 world_matrix;
+uniform vec4 mult_color;
+uniform vec4 add_color;
+uniform mat3 u_matrix;
+
+// The Y plane, and the U, V and optional alpha planes of a YUV 4:2:0 frame.
+uniform sampler2D u_texture;
+uniform sampler2D u_texture_u;
+uniform sampler2D u_texture_v;
+uniform sampler2D u_texture_a;
+uniform float u_alpha_plane;
+
+varying vec2 frag_uv;
+
+void main() {
+    // BT.601, limited range.
+    float y = 1.164 * (texture2D(u_texture, frag_uv).r - 0.0625);
+    float u = texture2D(u_texture_u, frag_uv).r - 0.5;
+    float v = texture2D(u_texture_v, frag_uv).r - 0.5;
+    vec3 rgb = clamp(vec3(y + 1.596 * v, y - 0.391 * u - 0.813 * v, y + 2.018 * u), 0.0, 1.0);
+    float a = mix(1.0, texture2D(u_texture_a, frag_uv).r, u_alpha_plane);
+
+    vec4 color = clamp(mult_color * vec4(rgb, a) + add_color, 0.0, 1.0);
+    gl_FragColor = vec4(color.rgb * color.a, color.a);
+}
@@ -3,6 +3,10 @@
 #![allow(clippy::arc_with_non_send_sync)]
 
 //mod context3d;
+mod shape_cache;
+mod stroke;
+mod tessellation;
+mod worker;
 
 use bytemuck::{Pod, Zeroable};
 use glow::*;
@@ -19,15 +23,22 @@ use ruffle_render::error::Error as BitmapError;
 use ruffle_render::matrix::Matrix;
 use ruffle_render::quality::StageQuality;
 use ruffle_render::shape_utils::{DistilledShape, GradientType};
-use ruffle_render::tessellator::{
-    Gradient as TessGradient, ShapeTessellator, Vertex as TessVertex,
-};
+use ruffle_render::tessellator::{Gradient as TessGradient, Vertex as TessVertex};
 use ruffle_render::transform::Transform;
+use shape_cache::{MeshDraw, MeshDrawType};
 use std::any::Any;
 use std::borrow::Cow;
-use std::sync::Arc;
-use swf::{BlendMode, Color, Twips};
+use std::cell::{OnceCell, RefCell};
+use std::collections::HashMap;
+use std::sync::mpsc::TryRecvError;
+use std::sync::{Arc, Mutex};
+use stroke::{StrokeMesh, StrokeVertex};
+use swf::{BlendMode, CharacterId, Color, Twips};
+use tessellation::{OwnedShape, ShapeRun};
 use thiserror::Error;
+use worker::{Pending, WorkerPool};
+
+pub use shape_cache::ShapeCache;
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -66,6 +77,9 @@ pub enum Error {
 
     #[error("GL Error in {0}: {1}")]
     GLError(&'static str, u32),
+
+    #[error("Worker thread failed")]
+    WorkerFailed,
 }
 
 const COLOR_VERTEX_GLSL: &str = include_str!("../shaders/color.vert");
@@ -73,7 +87,202 @@ const COLOR_FRAGMENT_GLSL: &str = include_str!("../shaders/color.frag");
 const TEXTURE_VERTEX_GLSL: &str = include_str!("../shaders/texture.vert");
 const GRADIENT_FRAGMENT_GLSL: &str = include_str!("../shaders/gradient.frag");
 const BITMAP_FRAGMENT_GLSL: &str = include_str!("../shaders/bitmap.frag");
-const NUM_VERTEX_ATTRIBUTES: u32 = 2;
+const STROKE_VERTEX_GLSL: &str = include_str!("../shaders/stroke.vert");
+const STROKE_FRAGMENT_GLSL: &str = include_str!("../shaders/stroke.frag");
+const BLUR_FRAGMENT_GLSL: &str = include_str!("../shaders/blur.frag");
+const YUV_FRAGMENT_GLSL: &str = include_str!("../shaders/yuv.frag");
+
+const COLOR_VERTEX_GLSL3: &str = include_str!("../shaders/gles3/color.vert");
+const COLOR_FRAGMENT_GLSL3: &str = include_str!("../shaders/gles3/color.frag");
+const TEXTURE_VERTEX_GLSL3: &str = include_str!("../shaders/gles3/texture.vert");
+const GRADIENT_FRAGMENT_GLSL3: &str = include_str!("../shaders/gles3/gradient.frag");
+const BITMAP_FRAGMENT_GLSL3: &str = include_str!("../shaders/gles3/bitmap.frag");
+const STROKE_VERTEX_GLSL3: &str = include_str!("../shaders/gles3/stroke.vert");
+const STROKE_FRAGMENT_GLSL3: &str = include_str!("../shaders/gles3/stroke.frag");
+const BLUR_FRAGMENT_GLSL3: &str = include_str!("../shaders/gles3/blur.frag");
+const YUV_FRAGMENT_GLSL3: &str = include_str!("../shaders/gles3/yuv.frag");
+const BITMAP_INSTANCED_VERTEX_GLSL3: &str = include_str!("../shaders/gles3/bitmap_instanced.vert");
+const BITMAP_INSTANCED_FRAGMENT_GLSL3: &str =
+    include_str!("../shaders/gles3/bitmap_instanced.frag");
+
+const NUM_VERTEX_ATTRIBUTES: u32 = 4;
+
+// Uniform block binding points used by the GLES3 shaders.
+const VIEW_BLOCK_BINDING: u32 = 0;
+const DRAW_BLOCK_BINDING: u32 = 1;
+
+// Attribute locations of the per-instance data in `bitmap_instanced.vert`.
+const INSTANCE_MATRIX_LOCATION: u32 = 2;
+const INSTANCE_TRANSLATION_LOCATION: u32 = 3;
+const INSTANCE_MULT_COLOR_LOCATION: u32 = 4;
+const INSTANCE_ADD_COLOR_LOCATION: u32 = 5;
+
+/// The feature level the backend renders with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RenderPath {
+    /// `#version 100` shaders with per-draw uniform uploads and no MSAA.
+    /// This is what vitaGL supports.
+    Gles2,
+
+    /// `#version 300 es` shaders with uniform buffers, instanced bitmap quads
+    /// and multisampled renderbuffers.
+    Gles3,
+}
+
+impl RenderPath {
+    /// Picks the best path the current context supports.
+    pub fn detect(gl: &glow::Context) -> Self {
+        if cfg!(target_os = "vita") {
+            return RenderPath::Gles2;
+        }
+        let version = gl.version();
+        let is_gles3 = if version.is_embedded {
+            version.major >= 3
+        } else {
+            // `#version 300 es` shaders need ARB_ES3_compatibility, which is core in 4.3.
+            (version.major, version.minor) >= (4, 3)
+        };
+        if is_gles3 {
+            RenderPath::Gles3
+        } else {
+            RenderPath::Gles2
+        }
+    }
+}
+
+/// Clockwise rotation of the stage on the screen, so that portrait games can be played with
+/// the handheld held sideways.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScreenRotation {
+    #[default]
+    None,
+    Clockwise90,
+    Clockwise180,
+    Clockwise270,
+}
+
+impl ScreenRotation {
+    pub fn from_degrees(degrees: u32) -> Option<Self> {
+        match degrees {
+            0 => Some(ScreenRotation::None),
+            90 => Some(ScreenRotation::Clockwise90),
+            180 => Some(ScreenRotation::Clockwise180),
+            270 => Some(ScreenRotation::Clockwise270),
+            _ => None,
+        }
+    }
+
+    /// Whether the stage's width runs along the screen's height.
+    pub fn is_sideways(self) -> bool {
+        matches!(
+            self,
+            ScreenRotation::Clockwise90 | ScreenRotation::Clockwise270
+        )
+    }
+
+    /// Returns the size of the stage shown on a `width`x`height` screen.
+    pub fn stage_size(self, width: u32, height: u32) -> (u32, u32) {
+        if self.is_sideways() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Maps a point on a `width`x`height` screen to the stage.
+    pub fn screen_to_stage(self, x: f64, y: f64, width: f64, height: f64) -> (f64, f64) {
+        match self {
+            ScreenRotation::None => (x, y),
+            ScreenRotation::Clockwise90 => (y, width - x),
+            ScreenRotation::Clockwise180 => (width - x, height - y),
+            ScreenRotation::Clockwise270 => (height - y, x),
+        }
+    }
+
+    /// Maps a point on the stage to a `width`x`height` screen.
+    fn stage_to_screen(self, x: f64, y: f64, width: f64, height: f64) -> (f64, f64) {
+        match self {
+            ScreenRotation::None => (x, y),
+            ScreenRotation::Clockwise90 => (width - y, x),
+            ScreenRotation::Clockwise180 => (width - x, height - y),
+            ScreenRotation::Clockwise270 => (y, height - x),
+        }
+    }
+
+    /// Rotates a matrix that outputs clip space coordinates.
+    fn rotate(self, mut matrix: [[f32; 4]; 4]) -> [[f32; 4]; 4] {
+        for column in &mut matrix {
+            let [x, y, ..] = *column;
+            [column[0], column[1]] = match self {
+                ScreenRotation::None => [x, y],
+                ScreenRotation::Clockwise90 => [y, -x],
+                ScreenRotation::Clockwise180 => [-x, -y],
+                ScreenRotation::Clockwise270 => [-y, x],
+            };
+        }
+        matrix
+    }
+}
+
+/// Where the stage is shown on the screen, when it isn't stretched over all of it.
+/// Coordinates are in screen pixels, in the stage's orientation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StageOutput {
+    pub screen_width: u32,
+    pub screen_height: u32,
+    /// The area the stage is stretched over. It may extend past the screen.
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// Upscale the stage with nearest neighbour instead of bilinear filtering.
+    pub nearest: bool,
+}
+
+/// A rectangle drawn over the stage and the letterbox border, such as a cursor or on-screen
+/// controls. Coordinates are in screen pixels, in the stage's orientation.
+#[derive(Debug, Clone)]
+pub struct OverlayQuad {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// The color of the quad, or the tint of its bitmap, with straight alpha.
+    pub color: [f32; 4],
+    pub bitmap: Option<BitmapHandle>,
+}
+
+/// What fills the screen around a letterboxed stage.
+pub enum LetterboxBorder {
+    Color(Color),
+    /// Stretched over the whole screen, of which only the borders are visible.
+    Image(Bitmap<'static>),
+    /// A blurred and dimmed copy of the stage, stretched over the whole screen.
+    Blur,
+}
+
+enum LetterboxFill {
+    Color([f32; 4]),
+    Image(BitmapHandle),
+    Blur {
+        program: ShaderProgram,
+        quad: Vec<Draw>,
+        // Holds a copy of the stage area of the screen.
+        texture: BitmapHandle,
+    },
+}
+
+struct Letterbox {
+    fill: LetterboxFill,
+    stage_width: f64,
+    stage_height: f64,
+}
+
+// Distance between the taps of the blur shader, as a fraction of the stage.
+const BLUR_STEP: f32 = 1.0 / 48.0;
+
+// How much the blurred border is darkened, so that it doesn't distract from the stage.
+const BLUR_BRIGHTNESS: f32 = 0.6;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum MaskState {
@@ -104,6 +313,25 @@ impl From<TessVertex> for Vertex {
     }
 }
 
+/// Contents of the `DrawBlock` uniform block, laid out as std140.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct DrawUniforms {
+    world_matrix: [[f32; 4]; 4],
+    mult_color: [f32; 4],
+    add_color: [f32; 4],
+}
+
+/// Per-instance data of an instanced bitmap quad.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct BitmapInstance {
+    matrix: [f32; 4],
+    translation: [f32; 2],
+    mult_color: [f32; 4],
+    add_color: [f32; 4],
+}
+
 #[derive(Debug)]
 pub struct QueueSyncHandle {
     texture: BitmapHandle,
@@ -118,8 +346,12 @@ pub struct GlowRenderBackend {
 
     // The frame buffers used for resolving MSAA.
     msaa_buffers: Option<MsaaBuffers>,
+    // The render target used for nearest neighbour upscaling without MSAA.
+    stage_buffers: Option<StageBuffers>,
+    output: Option<StageOutput>,
     #[cfg(not(target_os = "vita"))]
     msaa_sample_count: u32,
+    rotation: ScreenRotation,
 
     max_texture_size: u32,
 
@@ -128,11 +360,37 @@ pub struct GlowRenderBackend {
     color_program: ShaderProgram,
     bitmap_program: ShaderProgram,
     gradient_program: ShaderProgram,
+    stroke_program: ShaderProgram,
+    yuv_program: ShaderProgram,
 
-    shape_tessellator: ShapeTessellator,
+    render_path: RenderPath,
+
+    // The uniform buffers backing `ViewBlock` and `DrawBlock` (GLES3 only).
+    uniform_buffers: Option<UniformBuffers>,
+
+    // Batches consecutive `render_bitmap` calls into instanced draws (GLES3 only).
+    bitmap_batch: Option<BitmapBatch>,
+
+    workers: WorkerPool,
+    shape_cache: Option<Arc<Mutex<ShapeCache>>>,
+    // Whether `render_offscreen` is running, which waits for shapes still being tessellated.
+    rendering_offscreen: bool,
 
     color_quad_draws: Vec<Draw>,
     bitmap_quad_draws: Vec<Draw>,
+    yuv_quad_draws: Vec<Draw>,
+
+    // The hairline meshes used by `draw_line` and `draw_line_rect`.
+    line_draws: Vec<Draw>,
+
+    // Whether hairlines and non-scaling strokes fade out over an extra pixel.
+    stroke_antialiasing: bool,
+
+    // Drawn over the letterbox bars at the end of each frame.
+    letterbox: Option<Letterbox>,
+
+    // Drawn over everything else at the end of each frame.
+    overlay: Vec<OverlayQuad>,
 
     mask_state: MaskState,
     num_masks: u32,
@@ -158,13 +416,141 @@ struct RegistryData {
     gl: Arc<glow::Context>,
     width: u32,
     height: u32,
-    texture: glow::Texture,
+    /// Holds the bitmap once `upload_pending` has run.
+    raw_texture: glow::Texture,
+    /// Pixels still being converted by a worker, uploaded on first use.
+    pending: RefCell<Option<Pending<(u32, Bitmap<'static>)>>>,
+    /// The chroma and alpha planes of a YUV bitmap, whose luma plane is in `raw_texture`.
+    yuv: Option<YuvPlanes>,
+}
+
+#[derive(Debug)]
+struct YuvPlanes {
+    u: glow::Texture,
+    v: glow::Texture,
+    a: Option<glow::Texture>,
+}
+
+impl YuvPlanes {
+    /// Allocates the planes for the size of `bitmap` and uploads its pixels.
+    unsafe fn allocate(&self, gl: &glow::Context, y: glow::Texture, bitmap: &Bitmap) {
+        for (texture, width, height, plane) in self.planes(y, bitmap) {
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                glow::LUMINANCE as i32,
+                width,
+                height,
+                0,
+                glow::LUMINANCE,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(Some(plane)),
+            );
+        }
+    }
+
+    /// Replaces the pixels of the planes with those of `bitmap`, which must have the same size
+    /// and format as the bitmap the planes were allocated for.
+    unsafe fn update(&self, gl: &glow::Context, y: glow::Texture, bitmap: &Bitmap) {
+        for (texture, width, height, plane) in self.planes(y, bitmap) {
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            gl.tex_sub_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                0,
+                0,
+                width,
+                height,
+                glow::LUMINANCE,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(Some(plane)),
+            );
+        }
+    }
+
+    /// Splits `bitmap` into its planes, each with its texture and size.
+    fn planes<'a>(
+        &self,
+        y: glow::Texture,
+        bitmap: &'a Bitmap,
+    ) -> Vec<(glow::Texture, i32, i32, &'a [u8])> {
+        let (width, height) = (bitmap.width() as i32, bitmap.height() as i32);
+        let (chroma_width, chroma_height) = ((width + 1) / 2, (height + 1) / 2);
+        let data = bitmap.data();
+        let (y_plane, rest) = data.split_at((width * height) as usize);
+        let (u_plane, rest) = rest.split_at((chroma_width * chroma_height) as usize);
+        let (v_plane, a_plane) = rest.split_at((chroma_width * chroma_height) as usize);
+
+        let mut planes = vec![
+            (y, width, height, y_plane),
+            (self.u, chroma_width, chroma_height, u_plane),
+            (self.v, chroma_width, chroma_height, v_plane),
+        ];
+        if let Some(a) = self.a {
+            planes.push((a, width, height, a_plane));
+        }
+        planes
+    }
+}
+
+impl RegistryData {
+    /// Uploads the pixels converted by a worker, if they're ready. Call this before binding
+    /// `raw_texture`, since the upload binds it to `TEXTURE_2D` of the active unit.
+    ///
+    /// Returns `false` if the bitmap is still being converted, in which case it should be left
+    /// out of the frame rather than stalling it. With `wait`, this blocks until it's done instead,
+    /// for offscreen rendering and pixel updates, where the result is kept.
+    fn upload_pending(&self, wait: bool) -> bool {
+        let Some(pending) = self.pending.take() else {
+            return true;
+        };
+        let converted = if wait {
+            pending.wait()
+        } else {
+            match pending.try_wait() {
+                Ok(converted) => Some(converted),
+                Err(TryRecvError::Empty) => {
+                    self.pending.replace(Some(pending));
+                    return false;
+                }
+                Err(TryRecvError::Disconnected) => None,
+            }
+        };
+        let Some((format, bitmap)) = converted else {
+            log::error!("Couldn't decode bitmap: {:?}", Error::WorkerFailed);
+            return true;
+        };
+        unsafe {
+            self.gl
+                .bind_texture(glow::TEXTURE_2D, Some(self.raw_texture));
+            self.gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                format as i32,
+                bitmap.width() as i32,
+                bitmap.height() as i32,
+                0,
+                format,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(Some(bitmap.data())),
+            );
+        }
+        true
+    }
 }
 
 impl Drop for RegistryData {
     fn drop(&mut self) {
         unsafe {
-            self.gl.delete_texture(self.texture);
+            self.gl.delete_texture(self.raw_texture);
+            if let Some(yuv) = &self.yuv {
+                self.gl.delete_texture(yuv.u);
+                self.gl.delete_texture(yuv.v);
+                if let Some(a) = yuv.a {
+                    self.gl.delete_texture(a);
+                }
+            }
         }
     }
 }
@@ -177,6 +563,10 @@ fn as_registry_data(handle: &BitmapHandle) -> &RegistryData {
 
 const MAX_GRADIENT_COLORS: usize = 15;
 
+// Indices into `GlowRenderBackend::line_draws`.
+const LINE_DRAW: usize = 0;
+const LINE_RECT_DRAW: usize = 1;
+
 impl GlowRenderBackend {
     pub fn new(
         glow_context: Arc<glow::Context>,
@@ -188,34 +578,76 @@ impl GlowRenderBackend {
         unsafe {
             let gl = glow_context;
 
-            // Determine MSAA sample count.
+            let render_path = RenderPath::detect(&gl);
+            log::info!("Using the {render_path:?} render path");
+
+            // Determine MSAA sample count. Multisampled renderbuffers need GLES3.
             #[cfg(not(target_os = "vita"))]
-            let mut msaa_sample_count = quality.sample_count().min(4);
+            let mut msaa_sample_count = match render_path {
+                RenderPath::Gles2 => 1,
+                RenderPath::Gles3 => quality.sample_count().min(4),
+            };
 
             //// Ensure that we don't exceed the max MSAA of this device.
             #[cfg(not(target_os = "vita"))]
-            let max_samples = gl.get_parameter_i32(glow::MAX_SAMPLES) as u32;
-            #[cfg(not(target_os = "vita"))]
-            if max_samples > 0 && max_samples < msaa_sample_count {
-                log::info!("Device only supports {max_samples}xMSAA");
-                msaa_sample_count = max_samples;
+            if render_path == RenderPath::Gles3 {
+                let max_samples = gl.get_parameter_i32(glow::MAX_SAMPLES) as u32;
+                if max_samples > 0 && max_samples < msaa_sample_count {
+                    log::info!("Device only supports {max_samples}xMSAA");
+                    msaa_sample_count = max_samples;
+                }
             }
 
             let max_texture_size = gl.get_parameter_i32(glow::MAX_TEXTURE_SIZE) as u32;
 
-            let color_vertex = Self::compile_shader(&gl, glow::VERTEX_SHADER, COLOR_VERTEX_GLSL)?;
+            let (color_vertex_glsl, texture_vertex_glsl, color_fragment_glsl) = match render_path {
+                RenderPath::Gles2 => (COLOR_VERTEX_GLSL, TEXTURE_VERTEX_GLSL, COLOR_FRAGMENT_GLSL),
+                RenderPath::Gles3 => (
+                    COLOR_VERTEX_GLSL3,
+                    TEXTURE_VERTEX_GLSL3,
+                    COLOR_FRAGMENT_GLSL3,
+                ),
+            };
+            let (bitmap_fragment_glsl, gradient_fragment_glsl) = match render_path {
+                RenderPath::Gles2 => (BITMAP_FRAGMENT_GLSL, GRADIENT_FRAGMENT_GLSL),
+                RenderPath::Gles3 => (BITMAP_FRAGMENT_GLSL3, GRADIENT_FRAGMENT_GLSL3),
+            };
+            let (stroke_vertex_glsl, stroke_fragment_glsl) = match render_path {
+                RenderPath::Gles2 => (STROKE_VERTEX_GLSL, STROKE_FRAGMENT_GLSL),
+                RenderPath::Gles3 => (STROKE_VERTEX_GLSL3, STROKE_FRAGMENT_GLSL3),
+            };
+            let yuv_fragment_glsl = match render_path {
+                RenderPath::Gles2 => YUV_FRAGMENT_GLSL,
+                RenderPath::Gles3 => YUV_FRAGMENT_GLSL3,
+            };
+
+            let color_vertex = Self::compile_shader(&gl, glow::VERTEX_SHADER, color_vertex_glsl)?;
             let texture_vertex =
-                Self::compile_shader(&gl, glow::VERTEX_SHADER, TEXTURE_VERTEX_GLSL)?;
+                Self::compile_shader(&gl, glow::VERTEX_SHADER, texture_vertex_glsl)?;
             let color_fragment =
-                Self::compile_shader(&gl, glow::FRAGMENT_SHADER, COLOR_FRAGMENT_GLSL)?;
+                Self::compile_shader(&gl, glow::FRAGMENT_SHADER, color_fragment_glsl)?;
             let bitmap_fragment =
-                Self::compile_shader(&gl, glow::FRAGMENT_SHADER, BITMAP_FRAGMENT_GLSL)?;
+                Self::compile_shader(&gl, glow::FRAGMENT_SHADER, bitmap_fragment_glsl)?;
             let gradient_fragment =
-                Self::compile_shader(&gl, glow::FRAGMENT_SHADER, GRADIENT_FRAGMENT_GLSL)?;
-
-            let color_program = ShaderProgram::new(&gl, color_vertex, color_fragment)?;
-            let bitmap_program = ShaderProgram::new(&gl, texture_vertex, bitmap_fragment)?;
-            let gradient_program = ShaderProgram::new(&gl, texture_vertex, gradient_fragment)?;
+                Self::compile_shader(&gl, glow::FRAGMENT_SHADER, gradient_fragment_glsl)?;
+            let stroke_vertex = Self::compile_shader(&gl, glow::VERTEX_SHADER, stroke_vertex_glsl)?;
+            let stroke_fragment =
+                Self::compile_shader(&gl, glow::FRAGMENT_SHADER, stroke_fragment_glsl)?;
+            let yuv_fragment = Self::compile_shader(&gl, glow::FRAGMENT_SHADER, yuv_fragment_glsl)?;
+
+            let color_program = ShaderProgram::new(&gl, color_vertex, color_fragment, render_path)?;
+            let bitmap_program =
+                ShaderProgram::new(&gl, texture_vertex, bitmap_fragment, render_path)?;
+            let gradient_program =
+                ShaderProgram::new(&gl, texture_vertex, gradient_fragment, render_path)?;
+            let stroke_program =
+                ShaderProgram::new(&gl, stroke_vertex, stroke_fragment, render_path)?;
+            let yuv_program = ShaderProgram::new(&gl, texture_vertex, yuv_fragment, render_path)?;
+
+            let uniform_buffers = match render_path {
+                RenderPath::Gles2 => None,
+                RenderPath::Gles3 => Some(UniformBuffers::new(&gl)?),
+            };
 
             gl.enable(glow::BLEND);
 
@@ -230,8 +662,11 @@ impl GlowRenderBackend {
                 gl,
 
                 msaa_buffers: None,
+                stage_buffers: None,
+                output: None,
                 #[cfg(not(target_os = "vita"))]
                 msaa_sample_count,
+                rotation: ScreenRotation::None,
 
                 max_texture_size,
 
@@ -240,11 +675,24 @@ impl GlowRenderBackend {
                 color_program,
                 gradient_program,
                 bitmap_program,
+                stroke_program,
+                yuv_program,
 
-                shape_tessellator: ShapeTessellator::new(),
+                render_path,
+                uniform_buffers,
+                bitmap_batch: None,
+
+                workers: WorkerPool::new(),
+                shape_cache: None,
+                rendering_offscreen: false,
 
                 color_quad_draws: vec![],
                 bitmap_quad_draws: vec![],
+                yuv_quad_draws: vec![],
+                line_draws: vec![],
+                stroke_antialiasing: true,
+                letterbox: None,
+                overlay: vec![],
                 renderbuffer_width: 1,
                 renderbuffer_height: 1,
                 view_matrix: [[0.0; 4]; 4],
@@ -266,8 +714,19 @@ impl GlowRenderBackend {
 
             let mut color_quad_mesh = renderer.build_quad_mesh(&renderer.color_program)?;
             let mut bitmap_quad_mesh = renderer.build_quad_mesh(&renderer.bitmap_program)?;
+            let mut yuv_quad_mesh = renderer.build_quad_mesh(&renderer.yuv_program)?;
             renderer.color_quad_draws.append(&mut color_quad_mesh);
             renderer.bitmap_quad_draws.append(&mut bitmap_quad_mesh);
+            renderer.yuv_quad_draws.append(&mut yuv_quad_mesh);
+
+            let line_draw = renderer.build_stroke_draw(&StrokeMesh::line())?;
+            let line_rect_draw = renderer.build_stroke_draw(&StrokeMesh::line_rect())?;
+            renderer.line_draws.push(line_draw);
+            renderer.line_draws.push(line_rect_draw);
+
+            if render_path == RenderPath::Gles3 {
+                renderer.bitmap_batch = Some(renderer.build_bitmap_batch()?);
+            }
 
             renderer.set_viewport_dimensions(ViewportDimensions {
                 width: 1,
@@ -279,6 +738,178 @@ impl GlowRenderBackend {
         }
     }
 
+    /// Rotates the output. The viewport dimensions passed to the backend are those of the stage,
+    /// which are swapped relative to the screen when `rotation` is sideways.
+    pub fn set_rotation(&mut self, rotation: ScreenRotation) {
+        self.rotation = rotation;
+        self.set_view_matrix(self.stage_view_matrix());
+    }
+
+    /// The size of the default framebuffer.
+    fn screen_size(&self) -> (i32, i32) {
+        let (width, height) = match &self.output {
+            Some(output) => (output.screen_width as i32, output.screen_height as i32),
+            None => (self.renderbuffer_width, self.renderbuffer_height),
+        };
+        if self.rotation.is_sideways() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// The view matrix used when drawing the stage. Without MSAA the stage is drawn straight to
+    /// the screen, so it includes the rotation. Otherwise it's applied when resolving.
+    fn stage_view_matrix(&self) -> [[f32; 4]; 4] {
+        let view_matrix = ortho_matrix(
+            self.renderbuffer_width as f32,
+            self.renderbuffer_height as f32,
+        );
+        if self.is_buffered() {
+            view_matrix
+        } else {
+            self.rotation.rotate(view_matrix)
+        }
+    }
+
+    /// Whether the stage is drawn into a render target before it's shown on the screen.
+    fn is_buffered(&self) -> bool {
+        self.msaa_buffers.is_some() || self.stage_buffers.is_some()
+    }
+
+    /// Places the stage on the screen, instead of stretching it over the whole screen.
+    pub fn set_stage_output(&mut self, output: Option<StageOutput>) {
+        if self.output != output {
+            self.output = output;
+            self.set_viewport_dimensions(self.viewport_dimensions());
+        }
+    }
+
+    /// The area of the screen the stage is shown in, as `(x, y, width, height)` in GL window
+    /// coordinates.
+    fn output_viewport(&self) -> (i32, i32, i32, i32) {
+        let (screen_width, screen_height) = self.screen_size();
+        let Some(output) = &self.output else {
+            return (0, 0, screen_width, screen_height);
+        };
+
+        let (width, height) = (screen_width as f64, screen_height as f64);
+        let (ax, ay) =
+            self.rotation
+                .stage_to_screen(output.x as f64, output.y as f64, width, height);
+        let (bx, by) = self.rotation.stage_to_screen(
+            output.x as f64 + output.width as f64,
+            output.y as f64 + output.height as f64,
+            width,
+            height,
+        );
+        let left = ax.min(bx) as i32;
+        let right = ax.max(bx) as i32;
+        let top = ay.min(by) as i32;
+        let bottom = ay.max(by) as i32;
+        // Window coordinates start at the bottom.
+        (left, screen_height - bottom, right - left, bottom - top)
+    }
+
+    /// Enables or disables the one pixel fade-out on hairlines and non-scaling strokes.
+    pub fn set_stroke_antialiasing(&mut self, enabled: bool) {
+        self.stroke_antialiasing = enabled;
+    }
+
+    /// Replaces the quads drawn over the stage. They show up from the next rendered frame.
+    pub fn set_overlay(&mut self, overlay: Vec<OverlayQuad>) {
+        self.overlay = overlay;
+    }
+
+    /// Fills the letterbox bars around a `stage_width`x`stage_height` movie with `border`.
+    /// The movie is assumed to be centered, as with the default stage alignment.
+    pub fn set_letterbox_border(
+        &mut self,
+        border: LetterboxBorder,
+        stage_width: f64,
+        stage_height: f64,
+    ) -> Result<(), Error> {
+        let fill = match border {
+            LetterboxBorder::Color(color) => LetterboxFill::Color([
+                color.r as f32 / 255.0,
+                color.g as f32 / 255.0,
+                color.b as f32 / 255.0,
+                color.a as f32 / 255.0,
+            ]),
+            LetterboxBorder::Image(bitmap) => LetterboxFill::Image(
+                self.register_bitmap(bitmap)
+                    .map_err(|_| Error::UnableToCreateTexture)?,
+            ),
+            LetterboxBorder::Blur => {
+                let (vertex_glsl, fragment_glsl) = match self.render_path {
+                    RenderPath::Gles2 => (TEXTURE_VERTEX_GLSL, BLUR_FRAGMENT_GLSL),
+                    RenderPath::Gles3 => (TEXTURE_VERTEX_GLSL3, BLUR_FRAGMENT_GLSL3),
+                };
+                let vertex = Self::compile_shader(&self.gl, glow::VERTEX_SHADER, vertex_glsl)?;
+                let fragment =
+                    Self::compile_shader(&self.gl, glow::FRAGMENT_SHADER, fragment_glsl)?;
+                let program = ShaderProgram::new(&self.gl, vertex, fragment, self.render_path)?;
+                let quad = self.build_quad_mesh(&program)?;
+                let texture = self
+                    .create_empty_texture(1, 1)
+                    .map_err(|_| Error::UnableToCreateTexture)?;
+                LetterboxFill::Blur {
+                    program,
+                    quad,
+                    texture,
+                }
+            }
+        };
+        self.letterbox = Some(Letterbox {
+            fill,
+            stage_width,
+            stage_height,
+        });
+        Ok(())
+    }
+
+    /// Reuses tessellated shapes from `shape_cache`, and adds newly tessellated ones to it.
+    pub fn set_shape_cache(&mut self, shape_cache: ShapeCache) {
+        self.shape_cache = Some(Arc::new(Mutex::new(shape_cache)));
+    }
+
+    /// Creates one luminance texture per plane of a YUV bitmap and uploads them right away.
+    fn register_yuv_bitmap(&self, bitmap: &Bitmap, has_alpha_plane: bool) -> BitmapHandle {
+        let create_texture = || unsafe {
+            let texture = self.gl.create_texture().expect("Unable to create texture");
+            self.gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            for (parameter, value) in [
+                (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+                (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+                (glow::TEXTURE_MIN_FILTER, glow::LINEAR),
+                (glow::TEXTURE_MAG_FILTER, glow::LINEAR),
+            ] {
+                self.gl
+                    .tex_parameter_i32(glow::TEXTURE_2D, parameter, value as i32);
+            }
+            texture
+        };
+
+        let y = create_texture();
+        let yuv = YuvPlanes {
+            u: create_texture(),
+            v: create_texture(),
+            a: has_alpha_plane.then(create_texture),
+        };
+        unsafe {
+            yuv.allocate(&self.gl, y, bitmap);
+        }
+
+        BitmapHandle(Arc::new(RegistryData {
+            gl: self.gl.clone(),
+            width: bitmap.width(),
+            height: bitmap.height(),
+            raw_texture: y,
+            pending: RefCell::new(None),
+            yuv: Some(yuv),
+        }))
+    }
+
     fn build_quad_mesh(&self, program: &ShaderProgram) -> Result<Vec<Draw>, Error> {
         let vao = self.create_vertex_array()?;
 
@@ -375,6 +1006,103 @@ impl GlowRenderBackend {
         }
     }
 
+    /// Builds the instanced quad used to batch `render_bitmap` calls on the GLES3 path.
+    fn build_bitmap_batch(&self) -> Result<BitmapBatch, Error> {
+        unsafe {
+            let vertex_shader =
+                Self::compile_shader(&self.gl, glow::VERTEX_SHADER, BITMAP_INSTANCED_VERTEX_GLSL3)?;
+            let fragment_shader = Self::compile_shader(
+                &self.gl,
+                glow::FRAGMENT_SHADER,
+                BITMAP_INSTANCED_FRAGMENT_GLSL3,
+            )?;
+            let program =
+                ShaderProgram::new(&self.gl, vertex_shader, fragment_shader, self.render_path)?;
+
+            let vao = self.create_vertex_array()?;
+
+            let vertex_buffer = self
+                .gl
+                .create_buffer()
+                .map_err(|_| Error::UnableToCreateBuffer)?;
+            self.gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertex_buffer));
+            self.gl.buffer_data_u8_slice(
+                glow::ARRAY_BUFFER,
+                bytemuck::cast_slice(&[[0.0f32, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]]),
+                glow::STATIC_DRAW,
+            );
+            self.gl.vertex_attrib_pointer_f32(
+                program.vertex_position_location,
+                2,
+                glow::FLOAT,
+                false,
+                8,
+                0,
+            );
+            self.gl
+                .enable_vertex_attrib_array(program.vertex_position_location);
+
+            let index_buffer = self
+                .gl
+                .create_buffer()
+                .map_err(|_| Error::UnableToCreateBuffer)?;
+            self.gl
+                .bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(index_buffer));
+            self.gl.buffer_data_u8_slice(
+                glow::ELEMENT_ARRAY_BUFFER,
+                bytemuck::cast_slice(&[0u32, 1, 2, 3]),
+                glow::STATIC_DRAW,
+            );
+
+            let instance_buffer = self
+                .gl
+                .create_buffer()
+                .map_err(|_| Error::UnableToCreateBuffer)?;
+            self.gl
+                .bind_buffer(glow::ARRAY_BUFFER, Some(instance_buffer));
+            let stride = std::mem::size_of::<BitmapInstance>() as i32;
+            for (location, size, offset) in [
+                (INSTANCE_MATRIX_LOCATION, 4, 0),
+                (INSTANCE_TRANSLATION_LOCATION, 2, 16),
+                (INSTANCE_MULT_COLOR_LOCATION, 4, 24),
+                (INSTANCE_ADD_COLOR_LOCATION, 4, 40),
+            ] {
+                self.gl.vertex_attrib_pointer_f32(
+                    location,
+                    size,
+                    glow::FLOAT,
+                    false,
+                    stride,
+                    offset,
+                );
+                self.gl.vertex_attrib_divisor(location, 1);
+                self.gl.enable_vertex_attrib_array(location);
+            }
+
+            self.bind_vertex_array(None);
+
+            Ok(BitmapBatch {
+                program,
+                vao,
+                vertex_buffer: Buffer {
+                    gl: self.gl.clone(),
+                    buffer: vertex_buffer,
+                },
+                index_buffer: Buffer {
+                    gl: self.gl.clone(),
+                    buffer: index_buffer,
+                },
+                instance_buffer: Buffer {
+                    gl: self.gl.clone(),
+                    buffer: instance_buffer,
+                },
+                handle: None,
+                smoothing: true,
+                instances: Vec::new(),
+            })
+        }
+    }
+
     fn compile_shader(
         gl: &glow::Context,
         shader_type: u32,
@@ -416,6 +1144,13 @@ impl GlowRenderBackend {
                 gl.delete_texture(msaa_buffers.framebuffer_texture);
             }
 
+            // Without multisampled renderbuffers we draw straight to the window.
+            if self.msaa_sample_count <= 1 {
+                gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+                gl.bind_renderbuffer(glow::RENDERBUFFER, None);
+                return Ok(());
+            }
+
             // Create frame and render buffers.
             let render_framebuffer = gl
                 .create_framebuffer()
@@ -527,192 +1262,351 @@ impl GlowRenderBackend {
         }
     }
 
-    fn register_shape_internal(
-        &mut self,
-        shape: DistilledShape,
-        bitmap_source: &dyn BitmapSource,
-    ) -> Result<Vec<Draw>, Error> {
+    fn build_stage_buffers(&mut self) -> Result<(), Error> {
         unsafe {
-            use ruffle_render::tessellator::DrawType as TessDrawType;
-
-            let lyon_mesh = self
-                .shape_tessellator
-                .tessellate_shape(shape, bitmap_source);
-
-            let mut draws = Vec::with_capacity(lyon_mesh.draws.len());
-            for draw in lyon_mesh.draws {
-                let num_indices = draw.indices.len() as i32;
-                let num_mask_indices = draw.mask_index_count as i32;
-
-                let vao = self.create_vertex_array()?;
-                let vertex_buffer = self.gl.create_buffer().unwrap();
-                self.gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertex_buffer));
-
-                let vertices: Vec<_> = draw.vertices.into_iter().map(Vertex::from).collect();
-                self.gl.buffer_data_u8_slice(
-                    glow::ARRAY_BUFFER,
-                    bytemuck::cast_slice(&vertices),
-                    glow::STATIC_DRAW,
-                );
-
-                let index_buffer = self.gl.create_buffer().unwrap();
-                self.gl
-                    .bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(index_buffer));
-                self.gl.buffer_data_u8_slice(
-                    glow::ELEMENT_ARRAY_BUFFER,
-                    bytemuck::cast_slice(&draw.indices),
-                    glow::STATIC_DRAW,
-                );
-
-                let program = match draw.draw_type {
-                    TessDrawType::Color => &self.color_program,
-                    TessDrawType::Gradient { .. } => &self.gradient_program,
-                    TessDrawType::Bitmap(_) => &self.bitmap_program,
-                };
-
-                // Unfortunately it doesn't seem to be possible to ensure that vertex attributes will be in
-                // a guaranteed position between shaders in WebGL1 (no layout qualifiers in GLSL in OpenGL ES 1.0).
-                // Attributes can change between shaders, even if the vertex layout is otherwise "the same".
-                // This varies between platforms based on what the GLSL compiler decides to do.
-                if program.vertex_position_location != 0xffff_ffff {
-                    self.gl.vertex_attrib_pointer_f32(
-                        program.vertex_position_location,
-                        2,
-                        glow::FLOAT,
-                        false,
-                        12,
-                        0,
-                    );
-                    self.gl
-                        .enable_vertex_attrib_array(program.vertex_position_location);
-                }
+            let gl = self.gl.as_ref();
 
-                if program.vertex_color_location != 0xffff_ffff {
-                    self.gl.vertex_attrib_pointer_f32(
-                        program.vertex_color_location,
-                        4,
-                        glow::UNSIGNED_BYTE,
-                        true,
-                        12,
-                        8,
-                    );
-                    self.gl
-                        .enable_vertex_attrib_array(program.vertex_color_location);
-                }
+            // Delete previous buffers, if they exist.
+            if let Some(stage_buffers) = self.stage_buffers.take() {
+                gl.delete_framebuffer(stage_buffers.framebuffer);
+                gl.delete_renderbuffer(stage_buffers.stencil_renderbuffer);
+                gl.delete_texture(stage_buffers.texture);
+            }
 
-                let num_vertex_attributes = program.num_vertex_attributes;
+            // The MSAA buffers already end in a texture, and bilinear upscaling can be done by
+            // the viewport transform.
+            let nearest = self.output.is_some_and(|output| output.nearest);
+            if self.msaa_buffers.is_some() || !nearest {
+                return Ok(());
+            }
 
-                draws.push(match draw.draw_type {
-                    TessDrawType::Color => Draw {
-                        draw_type: DrawType::Color,
-                        vao,
-                        vertex_buffer: Buffer {
-                            gl: self.gl.clone(),
-                            buffer: vertex_buffer,
-                        },
-                        index_buffer: Buffer {
-                            gl: self.gl.clone(),
-                            buffer: index_buffer,
-                        },
-                        num_indices,
-                        num_mask_indices,
-                    },
-                    TessDrawType::Gradient { matrix, gradient } => Draw {
-                        draw_type: DrawType::Gradient(Box::new(Gradient::new(
-                            lyon_mesh.gradients[gradient].clone(), // TODO: Gradient deduplication
-                            matrix,
-                        ))),
-                        vao,
-                        vertex_buffer: Buffer {
-                            gl: self.gl.clone(),
-                            buffer: vertex_buffer,
-                        },
-                        index_buffer: Buffer {
-                            gl: self.gl.clone(),
-                            buffer: index_buffer,
-                        },
-                        num_indices,
-                        num_mask_indices,
-                    },
-                    TessDrawType::Bitmap(bitmap) => Draw {
-                        draw_type: DrawType::Bitmap(BitmapDraw {
-                            matrix: bitmap.matrix,
-                            handle: bitmap_source.bitmap_handle(bitmap.bitmap_id, self),
-                            is_smoothed: bitmap.is_smoothed,
-                            is_repeating: bitmap.is_repeating,
-                        }),
-                        vao,
-                        vertex_buffer: Buffer {
-                            gl: self.gl.clone(),
-                            buffer: vertex_buffer,
-                        },
-                        index_buffer: Buffer {
-                            gl: self.gl.clone(),
-                            buffer: index_buffer,
-                        },
-                        num_indices,
-                        num_mask_indices,
-                    },
-                });
+            let framebuffer = gl
+                .create_framebuffer()
+                .map_err(|_| Error::UnableToCreateFrameBuffer)?;
+            let stencil_renderbuffer = gl
+                .create_renderbuffer()
+                .map_err(|_| Error::UnableToCreateRenderBuffer)?;
+            let texture = gl
+                .create_texture()
+                .map_err(|_| Error::UnableToCreateTexture)?;
+
+            gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            for (parameter, value) in [
+                (glow::TEXTURE_MAG_FILTER, glow::NEAREST),
+                (glow::TEXTURE_MIN_FILTER, glow::NEAREST),
+                (glow::TEXTURE_WRAP_S, glow::CLAMP_TO_EDGE),
+                (glow::TEXTURE_WRAP_T, glow::CLAMP_TO_EDGE),
+            ] {
+                gl.tex_parameter_i32(glow::TEXTURE_2D, parameter, value as i32);
+            }
+            gl.tex_image_2d(
+                glow::TEXTURE_2D,
+                0,
+                glow::RGBA as i32,
+                self.renderbuffer_width,
+                self.renderbuffer_height,
+                0,
+                glow::RGBA,
+                glow::UNSIGNED_BYTE,
+                glow::PixelUnpackData::Slice(None),
+            );
+            gl.bind_texture(glow::TEXTURE_2D, None);
+
+            gl.bind_renderbuffer(glow::RENDERBUFFER, Some(stencil_renderbuffer));
+            gl.renderbuffer_storage(
+                glow::RENDERBUFFER,
+                glow::STENCIL_INDEX8,
+                self.renderbuffer_width,
+                self.renderbuffer_height,
+            );
+            gl.bind_renderbuffer(glow::RENDERBUFFER, None);
 
-                self.bind_vertex_array(None);
+            gl.bind_framebuffer(glow::FRAMEBUFFER, Some(framebuffer));
+            gl.framebuffer_texture_2d(
+                glow::FRAMEBUFFER,
+                glow::COLOR_ATTACHMENT0,
+                glow::TEXTURE_2D,
+                Some(texture),
+                0,
+            );
+            gl.framebuffer_renderbuffer(
+                glow::FRAMEBUFFER,
+                glow::STENCIL_ATTACHMENT,
+                glow::RENDERBUFFER,
+                Some(stencil_renderbuffer),
+            );
+            let status = gl.check_framebuffer_status(glow::FRAMEBUFFER);
+            gl.bind_framebuffer(glow::FRAMEBUFFER, None);
 
-                // Don't use 'program' here in order to satisfy the borrow checker
-                for i in num_vertex_attributes..NUM_VERTEX_ATTRIBUTES {
-                    self.gl.disable_vertex_attrib_array(i);
-                }
+            let stage_buffers = StageBuffers {
+                framebuffer,
+                stencil_renderbuffer,
+                texture,
+            };
+            if status != glow::FRAMEBUFFER_COMPLETE {
+                // Falls back to bilinear upscaling.
+                gl.delete_framebuffer(stage_buffers.framebuffer);
+                gl.delete_renderbuffer(stage_buffers.stencil_renderbuffer);
+                gl.delete_texture(stage_buffers.texture);
+                return Err(Error::UnableToCreateFrameBuffer);
             }
+            self.stage_buffers = Some(stage_buffers);
 
-            Ok(draws)
+            Ok(())
         }
     }
 
-    fn clamp_bitmap(&mut self, bitmap: &mut Bitmap, format: u32) -> bool {
+    /// Converts `bitmap` to RGB or RGBA on a worker, shrinking it to fit the maximum texture size.
+    fn convert_on_worker(&self, bitmap: &Bitmap) -> Pending<(u32, Bitmap<'static>)> {
         let max_size = self.max_texture_size;
-        if bitmap.width() > max_size || bitmap.height() > max_size {
-            let ratio = bitmap.width() as f32 / bitmap.height() as f32;
-            let mut width = bitmap.width();
-            let mut height = bitmap.height();
-            if width > max_size {
-                width = max_size;
-                height = (max_size as f32 / ratio) as u32;
+        let bitmap = Bitmap::new(
+            bitmap.width(),
+            bitmap.height(),
+            bitmap.format(),
+            bitmap.data().to_vec(),
+        );
+        self.workers.spawn(move || {
+            let (format, mut bitmap) = match bitmap.format() {
+                BitmapFormat::Rgb | BitmapFormat::Yuv420p => (glow::RGB, bitmap.to_rgb()),
+                BitmapFormat::Rgba | BitmapFormat::Yuva420p => (glow::RGBA, bitmap.to_rgba()),
+            };
+            clamp_bitmap(&mut bitmap, format, max_size);
+            (format, bitmap)
+        })
+    }
+
+    /// Returns the draws of `mesh`, uploading them first if this is the first time it's drawn.
+    ///
+    /// A shape still being tessellated is left out of the frame, rather than stalling it,
+    /// unless it's drawn offscreen, where the result is kept.
+    fn mesh_draws<'a>(&mut self, mesh: &'a Mesh) -> &'a [Draw] {
+        if let Some(draws) = mesh.draws.get() {
+            return draws;
+        }
+        let Some(pending) = mesh.pending.take() else {
+            return &[];
+        };
+        let runs = if self.rendering_offscreen {
+            pending.runs.wait()
+        } else {
+            match pending.runs.try_wait() {
+                Ok(runs) => Some(runs),
+                Err(TryRecvError::Empty) => {
+                    mesh.pending.replace(Some(pending));
+                    return &[];
+                }
+                Err(TryRecvError::Disconnected) => None,
             }
-            if height > max_size {
-                height = max_size;
-                width = (max_size as f32 * ratio) as u32;
+        };
+        let draws = match runs
+            .ok_or(Error::WorkerFailed)
+            .and_then(|runs| self.upload_shape(runs, &pending.bitmaps))
+        {
+            Ok(draws) => draws,
+            Err(e) => {
+                log::error!("Couldn't register shape: {e:?}");
+                vec![]
             }
-            if format == glow::RGBA {
-                let image = image::RgbaImage::from_raw(
-                    bitmap.width(),
-                    bitmap.height(),
-                    bitmap.data().to_vec(),
-                )
-                .expect("Width and height of bitmap must match bitmap data");
-                let resized = image::imageops::resize(
-                    &image,
-                    width,
-                    height,
-                    image::imageops::FilterType::CatmullRom,
+        };
+        mesh.draws.get_or_init(|| draws)
+    }
+
+    fn upload_shape(
+        &mut self,
+        runs: Vec<ShapeRun>,
+        bitmaps: &HashMap<CharacterId, Option<BitmapHandle>>,
+    ) -> Result<Vec<Draw>, Error> {
+        let mut draws = vec![];
+        for run in runs {
+            match run {
+                ShapeRun::Tessellated(mesh) => {
+                    draws.reserve(mesh.len());
+                    for draw in mesh {
+                        draws.push(self.upload_mesh_draw(draw, bitmaps)?);
+                    }
+                }
+                ShapeRun::Strokes(strokes) => draws.push(self.build_stroke_draw(&strokes)?),
+            }
+        }
+        Ok(draws)
+    }
+
+    fn upload_mesh_draw(
+        &mut self,
+        draw: MeshDraw,
+        bitmaps: &HashMap<CharacterId, Option<BitmapHandle>>,
+    ) -> Result<Draw, Error> {
+        unsafe {
+            let num_indices = draw.indices.len() as i32;
+            let num_mask_indices = draw.num_mask_indices as i32;
+
+            let vao = self.create_vertex_array()?;
+            let vertex_buffer = self.gl.create_buffer().unwrap();
+            self.gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertex_buffer));
+
+            self.gl.buffer_data_u8_slice(
+                glow::ARRAY_BUFFER,
+                bytemuck::cast_slice(&draw.vertices),
+                glow::STATIC_DRAW,
+            );
+
+            let index_buffer = self.gl.create_buffer().unwrap();
+            self.gl
+                .bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(index_buffer));
+            self.gl.buffer_data_u8_slice(
+                glow::ELEMENT_ARRAY_BUFFER,
+                bytemuck::cast_slice(&draw.indices),
+                glow::STATIC_DRAW,
+            );
+
+            let program = match draw.draw_type {
+                MeshDrawType::Color => &self.color_program,
+                MeshDrawType::Gradient(_) => &self.gradient_program,
+                MeshDrawType::Bitmap { .. } => &self.bitmap_program,
+            };
+
+            // Unfortunately it doesn't seem to be possible to ensure that vertex attributes will be in
+            // a guaranteed position between shaders in WebGL1 (no layout qualifiers in GLSL in OpenGL ES 1.0).
+            // Attributes can change between shaders, even if the vertex layout is otherwise "the same".
+            // This varies between platforms based on what the GLSL compiler decides to do.
+            if program.vertex_position_location != 0xffff_ffff {
+                self.gl.vertex_attrib_pointer_f32(
+                    program.vertex_position_location,
+                    2,
+                    glow::FLOAT,
+                    false,
+                    12,
+                    0,
                 );
-                *bitmap = Bitmap::new(width, height, BitmapFormat::Rgba, resized.into_raw());
-            } else {
-                let image = image::RgbImage::from_raw(
-                    bitmap.width(),
-                    bitmap.height(),
-                    bitmap.data().to_vec(),
-                )
-                .expect("Width and height of bitmap must match bitmap data");
-                let resized = image::imageops::resize(
-                    &image,
-                    width,
-                    height,
-                    image::imageops::FilterType::CatmullRom,
+                self.gl
+                    .enable_vertex_attrib_array(program.vertex_position_location);
+            }
+
+            if program.vertex_color_location != 0xffff_ffff {
+                self.gl.vertex_attrib_pointer_f32(
+                    program.vertex_color_location,
+                    4,
+                    glow::UNSIGNED_BYTE,
+                    true,
+                    12,
+                    8,
                 );
-                *bitmap = Bitmap::new(width, height, BitmapFormat::Rgb, resized.into_raw());
+                self.gl
+                    .enable_vertex_attrib_array(program.vertex_color_location);
             }
-            true
-        } else {
-            false
+
+            let num_vertex_attributes = program.num_vertex_attributes;
+
+            let draw = Draw {
+                draw_type: match draw.draw_type {
+                    MeshDrawType::Color => DrawType::Color,
+                    MeshDrawType::Gradient(gradient) => DrawType::Gradient(gradient),
+                    MeshDrawType::Bitmap {
+                        matrix,
+                        bitmap_id,
+                        is_smoothed,
+                        is_repeating,
+                    } => DrawType::Bitmap(BitmapDraw {
+                        matrix,
+                        handle: bitmaps.get(&bitmap_id).cloned().flatten(),
+                        is_smoothed,
+                        is_repeating,
+                    }),
+                },
+                vao,
+                vertex_buffer: Buffer {
+                    gl: self.gl.clone(),
+                    buffer: vertex_buffer,
+                },
+                index_buffer: Buffer {
+                    gl: self.gl.clone(),
+                    buffer: index_buffer,
+                },
+                num_indices,
+                num_mask_indices,
+            };
+
+            self.bind_vertex_array(None);
+
+            // Don't use 'program' here in order to satisfy the borrow checker
+            for i in num_vertex_attributes..NUM_VERTEX_ATTRIBUTES {
+                self.gl.disable_vertex_attrib_array(i);
+            }
+
+            Ok(draw)
+        }
+    }
+
+    /// Uploads a stroke mesh for the stroke shader.
+    fn build_stroke_draw(&self, mesh: &StrokeMesh) -> Result<Draw, Error> {
+        let program = &self.stroke_program;
+        let vao = self.create_vertex_array()?;
+
+        unsafe {
+            let vertex_buffer = self
+                .gl
+                .create_buffer()
+                .map_err(|_| Error::UnableToCreateBuffer)?;
+            self.gl.bind_buffer(glow::ARRAY_BUFFER, Some(vertex_buffer));
+            self.gl.buffer_data_u8_slice(
+                glow::ARRAY_BUFFER,
+                bytemuck::cast_slice(&mesh.vertices),
+                glow::STATIC_DRAW,
+            );
+
+            let index_buffer = self
+                .gl
+                .create_buffer()
+                .map_err(|_| Error::UnableToCreateBuffer)?;
+            self.gl
+                .bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(index_buffer));
+            self.gl.buffer_data_u8_slice(
+                glow::ELEMENT_ARRAY_BUFFER,
+                bytemuck::cast_slice(&mesh.indices),
+                glow::STATIC_DRAW,
+            );
+
+            let stride = std::mem::size_of::<StrokeVertex>() as i32;
+            for (location, size, data_type, normalized, offset) in [
+                (program.vertex_position_location, 2, glow::FLOAT, false, 0),
+                (
+                    program.vertex_color_location,
+                    4,
+                    glow::UNSIGNED_BYTE,
+                    true,
+                    8,
+                ),
+                (program.vertex_tangent_location, 2, glow::FLOAT, false, 12),
+                (program.vertex_stroke_location, 4, glow::FLOAT, false, 20),
+            ] {
+                if location != 0xffff_ffff {
+                    self.gl.vertex_attrib_pointer_f32(
+                        location, size, data_type, normalized, stride, offset,
+                    );
+                    self.gl.enable_vertex_attrib_array(location);
+                }
+            }
+
+            self.bind_vertex_array(None);
+            for i in program.num_vertex_attributes..NUM_VERTEX_ATTRIBUTES {
+                self.gl.disable_vertex_attrib_array(i);
+            }
+
+            Ok(Draw {
+                draw_type: DrawType::Stroke,
+                vao,
+                vertex_buffer: Buffer {
+                    gl: self.gl.clone(),
+                    buffer: vertex_buffer,
+                },
+                index_buffer: Buffer {
+                    gl: self.gl.clone(),
+                    buffer: index_buffer,
+                },
+                num_indices: mesh.indices.len() as i32,
+                // Strokes never contribute to masks.
+                num_mask_indices: 0,
+            })
         }
     }
 
@@ -804,13 +1698,30 @@ impl GlowRenderBackend {
             self.add_color = None;
 
             // Bind to MSAA render buffer if using MSAA.
+            let mut scissor = false;
             if let Some(msaa_buffers) = &self.msaa_buffers {
                 let gl = &self.gl;
                 gl.bind_framebuffer(glow::FRAMEBUFFER, Some(msaa_buffers.render_framebuffer));
+                self.gl
+                    .viewport(0, 0, self.renderbuffer_width, self.renderbuffer_height);
+            } else if let Some(stage_buffers) = &self.stage_buffers {
+                self.gl
+                    .bind_framebuffer(glow::FRAMEBUFFER, Some(stage_buffers.framebuffer));
+                self.gl
+                    .viewport(0, 0, self.renderbuffer_width, self.renderbuffer_height);
+            } else {
+                let (x, y, width, height) = self.output_viewport();
+                self.gl.viewport(x, y, width, height);
+
+                // Only the stage's area gets the background color.
+                if self.output.is_some() {
+                    self.gl.clear_color(0.0, 0.0, 0.0, 1.0);
+                    self.gl.clear(glow::COLOR_BUFFER_BIT);
+                    self.gl.enable(glow::SCISSOR_TEST);
+                    self.gl.scissor(x, y, width, height);
+                    scissor = true;
+                }
             }
-
-            self.gl
-                .viewport(0, 0, self.renderbuffer_width, self.renderbuffer_height);
             self.gl.disable(glow::DEPTH_TEST);
 
             self.set_stencil_state();
@@ -827,17 +1738,18 @@ impl GlowRenderBackend {
             self.gl.stencil_mask(0xff);
             self.gl
                 .clear(glow::COLOR_BUFFER_BIT | glow::STENCIL_BUFFER_BIT);
+            if scissor {
+                self.gl.disable(glow::SCISSOR_TEST);
+            }
         }
     }
 
     fn end_frame(&mut self) {
+        self.flush_bitmap_batch();
         unsafe {
             // Resolve MSAA, if we're using it (WebGL2).
-            if let (gl, Some(ref msaa_buffers)) = (&self.gl, &self.msaa_buffers) {
-                // Disable any remaining masking state.
-                self.gl.disable(glow::STENCIL_TEST);
-                self.gl.color_mask(true, true, true, true);
-
+            let stage_texture = if let (gl, Some(ref msaa_buffers)) = (&self.gl, &self.msaa_buffers)
+            {
                 // Resolve the MSAA in the render buffer.
                 gl.bind_framebuffer(
                     glow::READ_FRAMEBUFFER,
@@ -856,43 +1768,58 @@ impl GlowRenderBackend {
                     glow::COLOR_BUFFER_BIT,
                     glow::NEAREST,
                 );
+                Some(msaa_buffers.framebuffer_texture)
+            } else {
+                self.stage_buffers
+                    .as_ref()
+                    .map(|stage_buffers| stage_buffers.texture)
+            };
 
-                // Render the resolved framebuffer texture to a quad on the screen.
-                gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            if let Some(stage_texture) = stage_texture {
+                // Disable any remaining masking state.
+                self.gl.disable(glow::STENCIL_TEST);
+                self.gl.color_mask(true, true, true, true);
 
-                self.gl.viewport(
-                    0,
-                    0,
-                    self.renderbuffer_width as i32,
-                    self.renderbuffer_height,
-                );
+                // Render the stage texture to a quad on the screen.
+                self.gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+                self.gl.clear_color(0.0, 0.0, 0.0, 1.0);
+                self.gl.clear(glow::COLOR_BUFFER_BIT);
+
+                let (x, y, width, height) = self.output_viewport();
+                self.gl.viewport(x, y, width, height);
 
                 let program = &self.bitmap_program;
                 self.gl.use_program(Some(program.program));
 
                 // Scale to fill screen.
-                program.uniform_matrix4fv(
-                    &self.gl,
-                    ShaderUniform::WorldMatrix,
-                    &[
-                        [2.0, 0.0, 0.0, 0.0],
-                        [0.0, 2.0, 0.0, 0.0],
-                        [0.0, 0.0, 1.0, 0.0],
-                        [-1.0, -1.0, 0.0, 1.0],
-                    ],
-                );
-                program.uniform_matrix4fv(
-                    &self.gl,
-                    ShaderUniform::ViewMatrix,
-                    &[
-                        [1.0, 0.0, 0.0, 0.0],
-                        [0.0, 1.0, 0.0, 0.0],
-                        [0.0, 0.0, 1.0, 0.0],
-                        [0.0, 0.0, 0.0, 1.0],
-                    ],
-                );
-                program.uniform4fv(&self.gl, ShaderUniform::MultColor, &[1.0, 1.0, 1.0, 1.0]);
-                program.uniform4fv(&self.gl, ShaderUniform::AddColor, &[0.0, 0.0, 0.0, 0.0]);
+                let world_matrix = [
+                    [2.0, 0.0, 0.0, 0.0],
+                    [0.0, 2.0, 0.0, 0.0],
+                    [0.0, 0.0, 1.0, 0.0],
+                    [-1.0, -1.0, 0.0, 1.0],
+                ];
+                let view_matrix = self.rotation.rotate([
+                    [1.0, 0.0, 0.0, 0.0],
+                    [0.0, 1.0, 0.0, 0.0],
+                    [0.0, 0.0, 1.0, 0.0],
+                    [0.0, 0.0, 0.0, 1.0],
+                ]);
+                if let Some(uniform_buffers) = &self.uniform_buffers {
+                    uniform_buffers.upload_view(&self.gl, &view_matrix);
+                    uniform_buffers.upload_draw(
+                        &self.gl,
+                        &DrawUniforms {
+                            world_matrix,
+                            mult_color: [1.0, 1.0, 1.0, 1.0],
+                            add_color: [0.0, 0.0, 0.0, 0.0],
+                        },
+                    );
+                } else {
+                    program.uniform_matrix4fv(&self.gl, ShaderUniform::WorldMatrix, &world_matrix);
+                    program.uniform_matrix4fv(&self.gl, ShaderUniform::ViewMatrix, &view_matrix);
+                    program.uniform4fv(&self.gl, ShaderUniform::MultColor, &[1.0, 1.0, 1.0, 1.0]);
+                    program.uniform4fv(&self.gl, ShaderUniform::AddColor, &[0.0, 0.0, 0.0, 0.0]);
+                }
 
                 program.uniform_matrix3fv(
                     &self.gl,
@@ -900,10 +1827,17 @@ impl GlowRenderBackend {
                     &[[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
                 );
 
-                // Bind the framebuffer texture.
+                // Bind the framebuffer texture. It's only filtered when it's being scaled.
                 self.gl.active_texture(glow::TEXTURE0);
+                self.gl.bind_texture(glow::TEXTURE_2D, Some(stage_texture));
+                let filter = match self.output {
+                    Some(output) if !output.nearest => glow::LINEAR,
+                    _ => glow::NEAREST,
+                } as i32;
                 self.gl
-                    .bind_texture(glow::TEXTURE_2D, Some(msaa_buffers.framebuffer_texture));
+                    .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, filter);
+                self.gl
+                    .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, filter);
                 program.uniform1i(&self.gl, ShaderUniform::BitmapTexture, 0);
 
                 // Render the quad.
@@ -915,8 +1849,456 @@ impl GlowRenderBackend {
                     glow::UNSIGNED_INT,
                     0,
                 );
+
+                if let Some(uniform_buffers) = &self.uniform_buffers {
+                    uniform_buffers.upload_view(&self.gl, &self.view_matrix);
+                }
+            }
+        }
+        self.draw_letterbox_border();
+        self.draw_overlay();
+    }
+
+    fn set_view_matrix(&mut self, view_matrix: [[f32; 4]; 4]) {
+        self.view_matrix = view_matrix;
+        if let Some(uniform_buffers) = &self.uniform_buffers {
+            uniform_buffers.upload_view(&self.gl, &view_matrix);
+        }
+        // Force the next draw to re-upload the view matrix on the GLES2 path.
+        self.active_program = std::ptr::null();
+    }
+
+    /// Fills the bars around the stage with the letterbox border, drawing straight to the screen.
+    fn draw_letterbox_border(&mut self) {
+        let Some(letterbox) = &self.letterbox else {
+            return;
+        };
+        if letterbox.stage_width <= 0.0 || letterbox.stage_height <= 0.0 {
+            return;
+        }
+
+        // The movie's area in stage pixels, and then on the screen in the stage's orientation.
+        let render_width = self.renderbuffer_width as f64;
+        let render_height = self.renderbuffer_height as f64;
+        let scale =
+            (render_width / letterbox.stage_width).min(render_height / letterbox.stage_height);
+        let mut width = letterbox.stage_width * scale;
+        let mut height = letterbox.stage_height * scale;
+        let mut x = (render_width - width) / 2.0;
+        let mut y = (render_height - height) / 2.0;
+        let (viewport_width, viewport_height) = match &self.output {
+            Some(output) => {
+                let scale_x = output.width as f64 / render_width;
+                let scale_y = output.height as f64 / render_height;
+                x = output.x as f64 + x * scale_x;
+                y = output.y as f64 + y * scale_y;
+                width *= scale_x;
+                height *= scale_y;
+                (output.screen_width as f64, output.screen_height as f64)
+            }
+            None => (render_width, render_height),
+        };
+
+        let bars: Vec<[f64; 4]> = [
+            [0.0, 0.0, x, viewport_height],
+            [x + width, 0.0, viewport_width - x - width, viewport_height],
+            [0.0, 0.0, viewport_width, y],
+            [
+                0.0,
+                y + height,
+                viewport_width,
+                viewport_height - y - height,
+            ],
+        ]
+        .into_iter()
+        .filter(|bar| bar[2] >= 1.0 && bar[3] >= 1.0)
+        .collect();
+        if bars.is_empty() {
+            return;
+        }
+
+        let (screen_width, screen_height) = self.screen_size();
+        let view_matrix = self
+            .rotation
+            .rotate(ortho_matrix(viewport_width as f32, viewport_height as f32));
+        unsafe {
+            self.gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            self.gl.viewport(0, 0, screen_width, screen_height);
+            self.gl.disable(glow::STENCIL_TEST);
+            self.gl.color_mask(true, true, true, true);
+            self.gl.active_texture(glow::TEXTURE0);
+        }
+
+        match &letterbox.fill {
+            LetterboxFill::Color(color) => {
+                for bar in bars {
+                    self.draw_screen_quad(
+                        &self.color_program,
+                        &self.color_quad_draws[0],
+                        bar,
+                        &view_matrix,
+                        *color,
+                        None,
+                    );
+                }
+            }
+            LetterboxFill::Image(bitmap)
+                if !as_registry_data(bitmap).upload_pending(self.rendering_offscreen) =>
+            {
+                // The bars are left empty until the image has been decoded.
+            }
+            LetterboxFill::Image(bitmap) => {
+                let texture = as_registry_data(bitmap).raw_texture;
+                unsafe {
+                    self.gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+                    let filter = glow::LINEAR as i32;
+                    self.gl
+                        .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, filter);
+                    self.gl
+                        .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, filter);
+                }
+                for bar in bars {
+                    // The image covers the whole screen, so each bar shows its own part of it.
+                    let texture_matrix = [
+                        [(bar[2] / viewport_width) as f32, 0.0, 0.0],
+                        [0.0, (bar[3] / viewport_height) as f32, 0.0],
+                        [
+                            (bar[0] / viewport_width) as f32,
+                            (bar[1] / viewport_height) as f32,
+                            1.0,
+                        ],
+                    ];
+                    self.draw_screen_quad(
+                        &self.bitmap_program,
+                        &self.bitmap_quad_draws[0],
+                        bar,
+                        &view_matrix,
+                        [1.0; 4],
+                        Some(&texture_matrix),
+                    );
+                }
+            }
+            LetterboxFill::Blur {
+                program,
+                quad,
+                texture,
+            } => {
+                // Copy the stage's area of the screen, which is rotated along with the stage.
+                let (screen_width_f, screen_height_f) = (screen_width as f64, screen_height as f64);
+                let (ax, ay) = self
+                    .rotation
+                    .stage_to_screen(x, y, screen_width_f, screen_height_f);
+                let (bx, by) = self.rotation.stage_to_screen(
+                    x + width,
+                    y + height,
+                    screen_width_f,
+                    screen_height_f,
+                );
+                let left = (ax.min(bx).round() as i32).clamp(0, screen_width);
+                let top = (ay.min(by).round() as i32).clamp(0, screen_height);
+                let right = (ax.max(bx).round() as i32).clamp(0, screen_width);
+                let bottom = (ay.max(by).round() as i32).clamp(0, screen_height);
+                if right > left && bottom > top {
+                    let (copy_width, copy_height) = (right - left, bottom - top);
+                    unsafe {
+                        self.gl.bind_texture(
+                            glow::TEXTURE_2D,
+                            Some(as_registry_data(texture).raw_texture),
+                        );
+                        // Framebuffer rows start at the bottom.
+                        self.gl.copy_tex_image_2d(
+                            glow::TEXTURE_2D,
+                            0,
+                            glow::RGB,
+                            left,
+                            screen_height - bottom,
+                            copy_width,
+                            copy_height,
+                            0,
+                        );
+                        self.gl.use_program(Some(program.program));
+                    }
+                    program.uniform2f(&self.gl, ShaderUniform::BlurStep, BLUR_STEP, BLUR_STEP);
+
+                    // Each bar shows the stage stretched over the whole screen.
+                    let to_uv = |px: f64, py: f64| {
+                        let (qx, qy) = (
+                            x + px / viewport_width * width,
+                            y + py / viewport_height * height,
+                        );
+                        let (sx, sy) =
+                            self.rotation
+                                .stage_to_screen(qx, qy, screen_width_f, screen_height_f);
+                        [
+                            ((sx - left as f64) / copy_width as f64) as f32,
+                            (1.0 - (sy - top as f64) / copy_height as f64) as f32,
+                        ]
+                    };
+                    for bar in bars {
+                        let origin = to_uv(bar[0], bar[1]);
+                        let along_x = to_uv(bar[0] + bar[2], bar[1]);
+                        let along_y = to_uv(bar[0], bar[1] + bar[3]);
+                        let texture_matrix = [
+                            [along_x[0] - origin[0], along_x[1] - origin[1], 0.0],
+                            [along_y[0] - origin[0], along_y[1] - origin[1], 0.0],
+                            [origin[0], origin[1], 1.0],
+                        ];
+                        self.draw_screen_quad(
+                            program,
+                            &quad[0],
+                            bar,
+                            &view_matrix,
+                            [BLUR_BRIGHTNESS, BLUR_BRIGHTNESS, BLUR_BRIGHTNESS, 1.0],
+                            Some(&texture_matrix),
+                        );
+                    }
+                }
+            }
+        }
+
+        self.set_view_matrix(self.view_matrix);
+        self.mult_color = None;
+        self.add_color = None;
+    }
+
+    /// Draws the overlay quads straight to the screen, over everything else.
+    fn draw_overlay(&mut self) {
+        if self.overlay.is_empty() {
+            return;
+        }
+
+        let (width, height) = match &self.output {
+            Some(output) => (output.screen_width as f32, output.screen_height as f32),
+            None => (
+                self.renderbuffer_width as f32,
+                self.renderbuffer_height as f32,
+            ),
+        };
+        let (screen_width, screen_height) = self.screen_size();
+        let view_matrix = self.rotation.rotate(ortho_matrix(width, height));
+        let texture_matrix = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+        unsafe {
+            self.gl.bind_framebuffer(glow::FRAMEBUFFER, None);
+            self.gl.viewport(0, 0, screen_width, screen_height);
+            self.gl.disable(glow::STENCIL_TEST);
+            self.gl.color_mask(true, true, true, true);
+            self.gl.active_texture(glow::TEXTURE0);
+        }
+
+        for quad in &self.overlay {
+            let rect = [
+                quad.x as f64,
+                quad.y as f64,
+                quad.width as f64,
+                quad.height as f64,
+            ];
+            match &quad.bitmap {
+                Some(bitmap)
+                    if !as_registry_data(bitmap).upload_pending(self.rendering_offscreen) =>
+                {
+                    // Left out until the bitmap has been decoded.
+                }
+                Some(bitmap) => {
+                    let entry = as_registry_data(bitmap);
+                    unsafe {
+                        self.gl
+                            .bind_texture(glow::TEXTURE_2D, Some(entry.raw_texture));
+                        let filter = glow::LINEAR as i32;
+                        self.gl.tex_parameter_i32(
+                            glow::TEXTURE_2D,
+                            glow::TEXTURE_MAG_FILTER,
+                            filter,
+                        );
+                        self.gl.tex_parameter_i32(
+                            glow::TEXTURE_2D,
+                            glow::TEXTURE_MIN_FILTER,
+                            filter,
+                        );
+                    }
+                    self.draw_screen_quad(
+                        &self.bitmap_program,
+                        &self.bitmap_quad_draws[0],
+                        rect,
+                        &view_matrix,
+                        quad.color,
+                        Some(&texture_matrix),
+                    );
+                }
+                None => self.draw_screen_quad(
+                    &self.color_program,
+                    &self.color_quad_draws[0],
+                    rect,
+                    &view_matrix,
+                    quad.color,
+                    None,
+                ),
             }
         }
+
+        self.set_view_matrix(self.view_matrix);
+        self.mult_color = None;
+        self.add_color = None;
+    }
+
+    /// Draws `quad` over `rect` (x, y, width and height in screen pixels, in the stage's
+    /// orientation), setting every uniform instead of going through the cached render state.
+    fn draw_screen_quad(
+        &self,
+        program: &ShaderProgram,
+        quad: &Draw,
+        rect: [f64; 4],
+        view_matrix: &[[f32; 4]; 4],
+        mult_color: [f32; 4],
+        texture_matrix: Option<&[[f32; 3]; 3]>,
+    ) {
+        let world_matrix = [
+            [rect[2] as f32, 0.0, 0.0, 0.0],
+            [0.0, rect[3] as f32, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [rect[0] as f32, rect[1] as f32, 0.0, 1.0],
+        ];
+        let add_color = [0.0; 4];
+
+        unsafe {
+            self.gl.use_program(Some(program.program));
+        }
+        if let Some(uniform_buffers) = &self.uniform_buffers {
+            uniform_buffers.upload_view(&self.gl, view_matrix);
+            uniform_buffers.upload_draw(
+                &self.gl,
+                &DrawUniforms {
+                    world_matrix,
+                    mult_color,
+                    add_color,
+                },
+            );
+        } else {
+            program.uniform_matrix4fv(&self.gl, ShaderUniform::WorldMatrix, &world_matrix);
+            program.uniform_matrix4fv(&self.gl, ShaderUniform::ViewMatrix, view_matrix);
+            program.uniform4fv(&self.gl, ShaderUniform::MultColor, &mult_color);
+            program.uniform4fv(&self.gl, ShaderUniform::AddColor, &add_color);
+        }
+        if let Some(texture_matrix) = texture_matrix {
+            program.uniform_matrix3fv(&self.gl, ShaderUniform::TextureMatrix, texture_matrix);
+            program.uniform1i(&self.gl, ShaderUniform::BitmapTexture, 0);
+        }
+
+        self.bind_vertex_array(Some(quad.vao));
+        unsafe {
+            self.gl
+                .draw_elements(glow::TRIANGLE_FAN, quad.num_indices, glow::UNSIGNED_INT, 0);
+        }
+    }
+
+    /// Queues a bitmap into the current instanced batch, flushing the batch first
+    /// if the texture or filtering changes.
+    fn queue_bitmap_instance(
+        &mut self,
+        bitmap: BitmapHandle,
+        transform: Transform,
+        smoothing: bool,
+        pixel_snapping: PixelSnapping,
+    ) {
+        let entry = as_registry_data(&bitmap);
+        if !entry.upload_pending(self.rendering_offscreen) {
+            return;
+        }
+        let mut matrix = transform.matrix;
+        pixel_snapping.apply(&mut matrix);
+        matrix *= Matrix::scale(entry.width as f32, entry.height as f32);
+
+        let instance = BitmapInstance {
+            matrix: [matrix.a, matrix.b, matrix.c, matrix.d],
+            translation: [matrix.tx.to_pixels() as f32, matrix.ty.to_pixels() as f32],
+            mult_color: transform.color_transform.mult_rgba_normalized(),
+            add_color: transform.color_transform.add_rgba_normalized(),
+        };
+
+        let needs_flush = match &self.bitmap_batch {
+            Some(batch) => match &batch.handle {
+                Some(handle) => {
+                    as_registry_data(handle).raw_texture != entry.raw_texture
+                        || batch.smoothing != smoothing
+                }
+                None => false,
+            },
+            None => return,
+        };
+        if needs_flush {
+            self.flush_bitmap_batch();
+        }
+
+        if let Some(batch) = &mut self.bitmap_batch {
+            if batch.handle.is_none() {
+                batch.handle = Some(bitmap);
+                batch.smoothing = smoothing;
+            }
+            batch.instances.push(instance);
+        }
+    }
+
+    /// Draws every queued bitmap instance in a single instanced call.
+    fn flush_bitmap_batch(&mut self) {
+        let (handle, smoothing) = match &mut self.bitmap_batch {
+            Some(batch) if !batch.instances.is_empty() => match batch.handle.take() {
+                Some(handle) => (handle, batch.smoothing),
+                None => return,
+            },
+            _ => return,
+        };
+
+        self.set_stencil_state();
+
+        let Some(batch) = &mut self.bitmap_batch else {
+            return;
+        };
+        let entry = as_registry_data(&handle);
+        let texture = entry.raw_texture;
+        unsafe {
+            let program = &batch.program;
+            if !std::ptr::eq(program, self.active_program) {
+                self.gl.use_program(Some(program.program));
+                self.active_program = program as *const ShaderProgram;
+            }
+
+            self.gl
+                .bind_buffer(glow::ARRAY_BUFFER, Some(batch.instance_buffer.buffer));
+            self.gl.buffer_data_u8_slice(
+                glow::ARRAY_BUFFER,
+                bytemuck::cast_slice(&batch.instances),
+                glow::STREAM_DRAW,
+            );
+
+            self.gl.active_texture(glow::TEXTURE0);
+            self.gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+            program.uniform1i(&self.gl, ShaderUniform::BitmapTexture, 0);
+
+            let filter = if smoothing {
+                glow::LINEAR as i32
+            } else {
+                glow::NEAREST as i32
+            };
+            self.gl
+                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, filter);
+            self.gl
+                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, filter);
+            let wrap = glow::CLAMP_TO_EDGE as i32;
+            self.gl
+                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, wrap);
+            self.gl
+                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, wrap);
+
+            self.gl.bind_vertex_array(Some(batch.vao));
+            self.gl.draw_elements_instanced(
+                glow::TRIANGLE_FAN,
+                4,
+                glow::UNSIGNED_INT,
+                0,
+                batch.instances.len() as i32,
+            );
+            self.gl.bind_vertex_array(None);
+        }
+        batch.instances.clear();
     }
 
     fn push_blend_mode(&mut self, blend: RenderBlendMode) {
@@ -937,7 +2319,87 @@ impl GlowRenderBackend {
         }
     }
 
-    fn draw_quad<const MODE: u32, const COUNT: i32>(&mut self, color: Color, matrix: Matrix) {
+    fn draw_quad<const MODE: u32, const COUNT: i32>(&mut self, color: Color, matrix: Matrix) {
+        let world_matrix = [
+            [matrix.a, matrix.b, 0.0, 0.0],
+            [matrix.c, matrix.d, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [
+                matrix.tx.to_pixels() as f32,
+                matrix.ty.to_pixels() as f32,
+                0.0,
+                1.0,
+            ],
+        ];
+
+        let mult_color = [
+            color.r as f32 * 255.0,
+            color.g as f32 * 255.0,
+            color.b as f32 * 255.0,
+            color.a as f32 * 255.0,
+        ];
+        let add_color = [0.0; 4];
+
+        self.set_stencil_state();
+
+        let program = &self.color_program;
+
+        // Set common render state, while minimizing unnecessary state changes.
+        // TODO: Using designated layout specifiers in WebGL2/OpenGL ES 3, we could guarantee that uniforms
+        // are in the same location between shaders, and avoid changing them unless necessary.
+        if !std::ptr::eq(program, self.active_program) {
+            unsafe {
+                self.gl.use_program(Some(program.program));
+            }
+            self.active_program = program as *const ShaderProgram;
+
+            program.uniform_matrix4fv(&self.gl, ShaderUniform::ViewMatrix, &self.view_matrix);
+
+            self.mult_color = None;
+            self.add_color = None;
+        };
+
+        if let Some(uniform_buffers) = &self.uniform_buffers {
+            uniform_buffers.upload_draw(
+                &self.gl,
+                &DrawUniforms {
+                    world_matrix,
+                    mult_color,
+                    add_color,
+                },
+            );
+        } else {
+            self.color_program.uniform_matrix4fv(
+                &self.gl,
+                ShaderUniform::WorldMatrix,
+                &world_matrix,
+            );
+            if Some(mult_color) != self.mult_color {
+                self.color_program
+                    .uniform4fv(&self.gl, ShaderUniform::MultColor, &mult_color);
+                self.mult_color = Some(mult_color);
+            }
+            if Some(add_color) != self.add_color {
+                self.color_program
+                    .uniform4fv(&self.gl, ShaderUniform::AddColor, &add_color);
+                self.add_color = Some(add_color);
+            }
+        }
+
+        let quad = &self.color_quad_draws;
+        self.bind_vertex_array(Some(quad[0].vao));
+        unsafe {
+            let count = if COUNT < 0 {
+                quad[0].num_indices
+            } else {
+                COUNT
+            };
+            self.gl.draw_elements(MODE, count, glow::UNSIGNED_INT, 0);
+        }
+    }
+
+    /// Draws one of the `line_draws` as a 1px hairline with the stroke shader.
+    fn draw_hairline(&mut self, index: usize, color: Color, matrix: Matrix) {
         let world_matrix = [
             [matrix.a, matrix.b, 0.0, 0.0],
             [matrix.c, matrix.d, 0.0, 0.0],
@@ -951,20 +2413,16 @@ impl GlowRenderBackend {
         ];
 
         let mult_color = [
-            color.r as f32 * 255.0,
-            color.g as f32 * 255.0,
-            color.b as f32 * 255.0,
-            color.a as f32 * 255.0,
+            color.r as f32 / 255.0,
+            color.g as f32 / 255.0,
+            color.b as f32 / 255.0,
+            color.a as f32 / 255.0,
         ];
         let add_color = [0.0; 4];
 
         self.set_stencil_state();
 
-        let program = &self.color_program;
-
-        // Set common render state, while minimizing unnecessary state changes.
-        // TODO: Using designated layout specifiers in WebGL2/OpenGL ES 3, we could guarantee that uniforms
-        // are in the same location between shaders, and avoid changing them unless necessary.
+        let program = &self.stroke_program;
         if !std::ptr::eq(program, self.active_program) {
             unsafe {
                 self.gl.use_program(Some(program.program));
@@ -975,30 +2433,39 @@ impl GlowRenderBackend {
 
             self.mult_color = None;
             self.add_color = None;
-        };
-
-        self.color_program
-            .uniform_matrix4fv(&self.gl, ShaderUniform::WorldMatrix, &world_matrix);
-        if Some(mult_color) != self.mult_color {
-            self.color_program
-                .uniform4fv(&self.gl, ShaderUniform::MultColor, &mult_color);
-            self.mult_color = Some(mult_color);
         }
-        if Some(add_color) != self.add_color {
-            self.color_program
-                .uniform4fv(&self.gl, ShaderUniform::AddColor, &add_color);
-            self.add_color = Some(add_color);
+        program.uniform1f(
+            &self.gl,
+            ShaderUniform::Antialias,
+            if self.stroke_antialiasing { 1.0 } else { 0.0 },
+        );
+
+        if let Some(uniform_buffers) = &self.uniform_buffers {
+            uniform_buffers.upload_draw(
+                &self.gl,
+                &DrawUniforms {
+                    world_matrix,
+                    mult_color,
+                    add_color,
+                },
+            );
+        } else {
+            program.uniform_matrix4fv(&self.gl, ShaderUniform::WorldMatrix, &world_matrix);
+            if Some(mult_color) != self.mult_color {
+                program.uniform4fv(&self.gl, ShaderUniform::MultColor, &mult_color);
+                self.mult_color = Some(mult_color);
+            }
+            if Some(add_color) != self.add_color {
+                program.uniform4fv(&self.gl, ShaderUniform::AddColor, &add_color);
+                self.add_color = Some(add_color);
+            }
         }
 
-        let quad = &self.color_quad_draws;
-        self.bind_vertex_array(Some(quad[0].vao));
+        let draw = &self.line_draws[index];
+        self.bind_vertex_array(Some(draw.vao));
         unsafe {
-            let count = if COUNT < 0 {
-                quad[0].num_indices
-            } else {
-                COUNT
-            };
-            self.gl.draw_elements(MODE, count, glow::UNSIGNED_INT, 0);
+            self.gl
+                .draw_elements(glow::TRIANGLES, draw.num_indices, glow::UNSIGNED_INT, 0);
         }
     }
 }
@@ -1010,6 +2477,63 @@ fn same_blend_mode(first: Option<&RenderBlendMode>, second: &RenderBlendMode) ->
     }
 }
 
+/// Maps a `width`x`height` area with a top left origin to clip space.
+fn ortho_matrix(width: f32, height: f32) -> [[f32; 4]; 4] {
+    [
+        [1.0 / (width / 2.0), 0.0, 0.0, 0.0],
+        [0.0, -1.0 / (height / 2.0), 0.0, 0.0],
+        [0.0, 0.0, 1.0, 0.0],
+        [-1.0, 1.0, 0.0, 1.0],
+    ]
+}
+
+/// Returns the size of a `width`x`height` bitmap once it's been scaled down to fit in a texture.
+fn clamped_size(width: u32, height: u32, max_size: u32) -> (u32, u32) {
+    let ratio = width as f32 / height as f32;
+    let (mut clamped_width, mut clamped_height) = (width, height);
+    if clamped_width > max_size {
+        clamped_width = max_size;
+        clamped_height = (max_size as f32 / ratio) as u32;
+    }
+    if clamped_height > max_size {
+        clamped_height = max_size;
+        clamped_width = (max_size as f32 * ratio) as u32;
+    }
+    (clamped_width, clamped_height)
+}
+
+fn clamp_bitmap(bitmap: &mut Bitmap, format: u32, max_size: u32) -> bool {
+    if bitmap.width() > max_size || bitmap.height() > max_size {
+        let (width, height) = clamped_size(bitmap.width(), bitmap.height(), max_size);
+        if format == glow::RGBA {
+            let image =
+                image::RgbaImage::from_raw(bitmap.width(), bitmap.height(), bitmap.data().to_vec())
+                    .expect("Width and height of bitmap must match bitmap data");
+            let resized = image::imageops::resize(
+                &image,
+                width,
+                height,
+                image::imageops::FilterType::CatmullRom,
+            );
+            *bitmap = Bitmap::new(width, height, BitmapFormat::Rgba, resized.into_raw());
+        } else {
+            let image =
+                image::RgbImage::from_raw(bitmap.width(), bitmap.height(), bitmap.data().to_vec())
+                    .expect("Width and height of bitmap must match bitmap data");
+            let resized = image::imageops::resize(
+                &image,
+                width,
+                height,
+                image::imageops::FilterType::CatmullRom,
+            );
+            *bitmap = Bitmap::new(width, height, BitmapFormat::Rgb, resized.into_raw());
+        }
+        true
+    } else {
+        false
+    }
+}
+
 impl RenderBackend for GlowRenderBackend {
     fn render_offscreen(
         &mut self,
@@ -1018,7 +2542,10 @@ impl RenderBackend for GlowRenderBackend {
         _quality: StageQuality,
         bounds: PixelRegion,
     ) -> Option<Box<dyn SyncHandle>> {
+        self.flush_bitmap_batch();
+
         let entry = &as_registry_data(&handle);
+        entry.upload_pending(true);
 
         self.active_program = std::ptr::null();
         self.mask_state = MaskState::NoMask;
@@ -1035,7 +2562,7 @@ impl RenderBackend for GlowRenderBackend {
                 glow::FRAMEBUFFER,
                 glow::COLOR_ATTACHMENT0,
                 glow::TEXTURE_2D,
-                Some(entry.texture),
+                Some(entry.raw_texture),
                 0,
             );
 
@@ -1047,34 +2574,27 @@ impl RenderBackend for GlowRenderBackend {
                 .viewport(0, 0, entry.width as i32, entry.height as i32);
 
             //self.set_viewport_dimensions(self.offscreen_width as u32, self.offscreen_height as u32);
-            self.view_matrix = [
+            self.set_view_matrix([
                 // note: un-flipped Y
                 [1.0 / (entry.width as f32 / 2.0), 0.0, 0.0, 0.0],
                 [0.0, 1.0 / (entry.height as f32 / 2.0), 0.0, 0.0],
                 [0.0, 0.0, 1.0, 0.0],
                 [-1.0, -1.0, 0.0, 1.0],
-            ];
+            ]);
 
             self.set_stencil_state();
             // TODO: clearColor() based on alpha/transparency
             self.gl.stencil_mask(0xff);
             self.gl.clear(glow::STENCIL_BUFFER_BIT); // is this needed?
 
+            self.rendering_offscreen = true;
             commands.execute(self);
+            self.rendering_offscreen = false;
+            self.flush_bitmap_batch();
 
             // HACK: restore viewport here
             //self.set_viewport_dimensions(self.renderbuffer_width as u32, self.renderbuffer_height as u32);
-            self.view_matrix = [
-                [1.0 / (self.renderbuffer_width as f32 / 2.0), 0.0, 0.0, 0.0],
-                [
-                    0.0,
-                    -1.0 / (self.renderbuffer_height as f32 / 2.0),
-                    0.0,
-                    0.0,
-                ],
-                [0.0, 0.0, 1.0, 0.0],
-                [-1.0, 1.0, 0.0, 1.0],
-            ];
+            self.set_view_matrix(self.stage_view_matrix());
 
             self.gl.framebuffer_texture_2d(
                 glow::FRAMEBUFFER,
@@ -1100,14 +2620,6 @@ impl RenderBackend for GlowRenderBackend {
     }
 
     fn set_viewport_dimensions(&mut self, dimensions: ViewportDimensions) {
-        // Build view matrix based on canvas size.
-        self.view_matrix = [
-            [1.0 / (dimensions.width as f32 / 2.0), 0.0, 0.0, 0.0],
-            [0.0, -1.0 / (dimensions.height as f32 / 2.0), 0.0, 0.0],
-            [0.0, 0.0, 1.0, 0.0],
-            [-1.0, 1.0, 0.0, 1.0],
-        ];
-
         // Setup GL viewport and renderbuffers clamped to reasonable sizes.
         // We don't use `.clamp()` here because `self.gl.drawing_buffer_width()` and
         // `self.gl.drawing_buffer_height()` return zero when the WebGL context is lost,
@@ -1117,9 +2629,15 @@ impl RenderBackend for GlowRenderBackend {
 
         // Recreate framebuffers with the new size.
         let _ = self.build_msaa_buffers();
+        if let Err(e) = self.build_stage_buffers() {
+            log::warn!("Couldn't create the upscaling render target: {e}");
+        }
+
+        // Build view matrix based on canvas size.
+        self.set_view_matrix(self.stage_view_matrix());
         unsafe {
-            self.gl
-                .viewport(0, 0, self.renderbuffer_width, self.renderbuffer_height);
+            let (x, y, width, height) = self.output_viewport();
+            self.gl.viewport(x, y, width, height);
         }
         self.viewport_scale_factor = dimensions.scale_factor
     }
@@ -1129,20 +2647,22 @@ impl RenderBackend for GlowRenderBackend {
         shape: DistilledShape,
         bitmap_source: &dyn BitmapSource,
     ) -> ShapeHandle {
-        let mesh = match self.register_shape_internal(shape, bitmap_source) {
-            Ok(draws) => Mesh {
-                draws,
-                gl2: self.gl.clone(),
-            },
-            Err(e) => {
-                log::error!("Couldn't register shape: {e:?}");
-                Mesh {
-                    draws: vec![],
-                    gl2: self.gl.clone(),
-                }
-            }
-        };
-        ShapeHandle(Arc::new(mesh))
+        // Only the lookups that need the movie happen here, the rest is done by a worker.
+        let shape = OwnedShape::new(shape, bitmap_source);
+        let mut bitmaps = HashMap::new();
+        for id in shape.bitmap_ids() {
+            bitmaps.insert(id, bitmap_source.bitmap_handle(id, self));
+        }
+        let shape_cache = self.shape_cache.clone();
+        let runs = self
+            .workers
+            .spawn(move || tessellation::tessellate(shape, shape_cache.as_deref()));
+
+        ShapeHandle(Arc::new(Mesh {
+            gl2: self.gl.clone(),
+            draws: OnceCell::new(),
+            pending: RefCell::new(Some(PendingShape { runs, bitmaps })),
+        }))
     }
 
     fn submit_frame(
@@ -1160,25 +2680,27 @@ impl RenderBackend for GlowRenderBackend {
     }
 
     fn register_bitmap(&mut self, bitmap: Bitmap<'_>) -> Result<BitmapHandle, BitmapError> {
+        let max_size = self.max_texture_size;
+        let (width, height) = clamped_size(bitmap.width(), bitmap.height(), max_size);
+
+        // Video frames are converted to RGB by the YUV shader, so their planes go straight up.
+        let has_alpha_plane = match bitmap.format() {
+            BitmapFormat::Yuv420p => Some(false),
+            BitmapFormat::Yuva420p => Some(true),
+            BitmapFormat::Rgb | BitmapFormat::Rgba => None,
+        };
+        if let Some(has_alpha_plane) = has_alpha_plane {
+            if (width, height) == (bitmap.width(), bitmap.height()) {
+                return Ok(self.register_yuv_bitmap(&bitmap, has_alpha_plane));
+            }
+        }
+
+        // Format conversion and resizing happen on a worker, the pixels are uploaded on first use.
+        let pixels = self.convert_on_worker(&bitmap);
+
         unsafe {
-            let (format, mut bitmap) = match bitmap.format() {
-                BitmapFormat::Rgb | BitmapFormat::Yuv420p => (glow::RGB, bitmap.to_rgb()),
-                BitmapFormat::Rgba | BitmapFormat::Yuva420p => (glow::RGBA, bitmap.to_rgba()),
-            };
-            self.clamp_bitmap(&mut bitmap, format);
             let texture = self.gl.create_texture().expect("Unable to create texture");
             self.gl.bind_texture(glow::TEXTURE_2D, Some(texture));
-            self.gl.tex_image_2d(
-                glow::TEXTURE_2D,
-                0,
-                format as i32,
-                bitmap.width() as i32,
-                bitmap.height() as i32,
-                0,
-                format,
-                glow::UNSIGNED_BYTE,
-                glow::PixelUnpackData::Slice(Some(bitmap.data())),
-            );
 
             // You must set the texture parameters for non-power-of-2 textures to function in WebGL1.
             self.gl.tex_parameter_i32(
@@ -1204,9 +2726,11 @@ impl RenderBackend for GlowRenderBackend {
 
             Ok(BitmapHandle(Arc::new(RegistryData {
                 gl: self.gl.clone(),
-                width: bitmap.width(),
-                height: bitmap.height(),
-                texture,
+                width,
+                height,
+                raw_texture: texture,
+                pending: RefCell::new(Some(pixels)),
+                yuv: None,
             })))
         }
     }
@@ -1215,24 +2739,51 @@ impl RenderBackend for GlowRenderBackend {
         &mut self,
         handle: &BitmapHandle,
         bitmap: Bitmap<'_>,
-        mut region: PixelRegion,
+        region: PixelRegion,
     ) -> Result<(), BitmapError> {
-        unsafe {
-            let texture = as_registry_data(handle).texture;
+        let entry = as_registry_data(handle);
+        if let Some(yuv) = &entry.yuv {
+            let has_alpha_plane = match bitmap.format() {
+                BitmapFormat::Yuv420p => false,
+                BitmapFormat::Yuva420p => true,
+                BitmapFormat::Rgb | BitmapFormat::Rgba => {
+                    return Err(BitmapError::Unimplemented(
+                        "update_texture with RGB pixels on a YUV texture".into(),
+                    ))
+                }
+            };
+            if (bitmap.width(), bitmap.height()) != (entry.width, entry.height)
+                || has_alpha_plane != yuv.a.is_some()
+            {
+                return Err(BitmapError::Unimplemented(
+                    "update_texture with a differently shaped YUV frame".into(),
+                ));
+            }
+            // Video frames are always replaced as a whole.
+            unsafe {
+                yuv.update(&self.gl, entry.raw_texture, &bitmap);
+            }
+            return Ok(());
+        }
 
-            self.gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+        if (bitmap.width(), bitmap.height()) != (entry.width, entry.height) {
+            // A resized texture is redone as a whole, since pixel regions don't map onto the
+            // filtered result. Like a new bitmap, it's resized on a worker.
+            entry.pending.replace(Some(self.convert_on_worker(&bitmap)));
+            return Ok(());
+        }
 
-            let (format, mut bitmap) = match bitmap.format() {
+        // Pixels still pending would overwrite these.
+        entry.upload_pending(true);
+        unsafe {
+            self.gl
+                .bind_texture(glow::TEXTURE_2D, Some(entry.raw_texture));
+
+            let (format, bitmap) = match bitmap.format() {
                 BitmapFormat::Rgb | BitmapFormat::Yuv420p => (glow::RGB, bitmap.to_rgb()),
                 BitmapFormat::Rgba | BitmapFormat::Yuva420p => (glow::RGBA, bitmap.to_rgba()),
             };
 
-            if self.clamp_bitmap(&mut bitmap, format) {
-                // If we're updating a resized texture, just redo the whole thing.
-                // We can't trivially map pixel regions as we use a filter to resize.
-                region = PixelRegion::for_whole_size(bitmap.width(), bitmap.height());
-            }
-
             self.gl.tex_image_2d(
                 glow::TEXTURE_2D,
                 0,
@@ -1291,7 +2842,7 @@ impl RenderBackend for GlowRenderBackend {
                 glow::FRAMEBUFFER,
                 glow::COLOR_ATTACHMENT0,
                 glow::TEXTURE_2D,
-                Some(entry.texture),
+                Some(entry.raw_texture),
                 0,
             );
 
@@ -1366,7 +2917,9 @@ impl RenderBackend for GlowRenderBackend {
                 gl: self.gl.clone(),
                 width,
                 height,
-                texture,
+                raw_texture: texture,
+                pending: RefCell::new(None),
+                yuv: None,
             })))
         }
     }
@@ -1380,11 +2933,24 @@ impl CommandHandler for GlowRenderBackend {
         smoothing: bool,
         pixel_snapping: PixelSnapping,
     ) {
+        let is_yuv = as_registry_data(&bitmap).yuv.is_some();
+        if !is_yuv && self.bitmap_batch.is_some() {
+            self.queue_bitmap_instance(bitmap, transform, smoothing, pixel_snapping);
+            return;
+        }
+        self.flush_bitmap_batch();
+
         unsafe {
             self.set_stencil_state();
             let entry = as_registry_data(&bitmap);
+            if !entry.upload_pending(self.rendering_offscreen) {
+                return;
+            }
             // Adjust the quad draw to use the target bitmap.
-            let quad = &self.bitmap_quad_draws;
+            let (quad, program) = match &entry.yuv {
+                Some(_) => (&self.yuv_quad_draws, &self.yuv_program),
+                None => (&self.bitmap_quad_draws, &self.bitmap_program),
+            };
             let draw = &quad[0];
             let bitmap_matrix = if let DrawType::Bitmap(BitmapDraw { matrix, .. }) = &draw.draw_type
             {
@@ -1415,8 +2981,6 @@ impl CommandHandler for GlowRenderBackend {
 
             self.bind_vertex_array(Some(draw.vao));
 
-            let program = &self.bitmap_program;
-
             // Set common render state, while minimizing unnecessary state changes.
             // TODO: Using designated layout specifiers in WebGL2/OpenGL ES 3, we could guarantee that uniforms
             // are in the same location between shaders, and avoid changing them unless necessary.
@@ -1430,39 +2994,65 @@ impl CommandHandler for GlowRenderBackend {
                 self.add_color = None;
             }
 
-            program.uniform_matrix4fv(&self.gl, ShaderUniform::WorldMatrix, &world_matrix);
-            if Some(mult_color) != self.mult_color {
-                program.uniform4fv(&self.gl, ShaderUniform::MultColor, &mult_color);
-                self.mult_color = Some(mult_color);
-            }
-            if Some(add_color) != self.add_color {
-                program.uniform4fv(&self.gl, ShaderUniform::AddColor, &add_color);
-                self.add_color = Some(add_color);
+            if let Some(uniform_buffers) = &self.uniform_buffers {
+                uniform_buffers.upload_draw(
+                    &self.gl,
+                    &DrawUniforms {
+                        world_matrix,
+                        mult_color,
+                        add_color,
+                    },
+                );
+            } else {
+                program.uniform_matrix4fv(&self.gl, ShaderUniform::WorldMatrix, &world_matrix);
+                if Some(mult_color) != self.mult_color {
+                    program.uniform4fv(&self.gl, ShaderUniform::MultColor, &mult_color);
+                    self.mult_color = Some(mult_color);
+                }
+                if Some(add_color) != self.add_color {
+                    program.uniform4fv(&self.gl, ShaderUniform::AddColor, &add_color);
+                    self.add_color = Some(add_color);
+                }
             }
 
             program.uniform_matrix3fv(&self.gl, ShaderUniform::TextureMatrix, bitmap_matrix);
 
-            // Bind texture.
-            self.gl.active_texture(glow::TEXTURE0);
-            self.gl.bind_texture(glow::TEXTURE_2D, Some(entry.texture));
-            program.uniform1i(&self.gl, ShaderUniform::BitmapTexture, 0);
+            // Bind textures. YUV bitmaps have one per plane; without an alpha plane, the luma
+            // plane stands in to keep the sampler valid.
+            let mut textures = vec![(ShaderUniform::BitmapTexture, entry.raw_texture)];
+            if let Some(yuv) = &entry.yuv {
+                textures.push((ShaderUniform::YuvTextureU, yuv.u));
+                textures.push((ShaderUniform::YuvTextureV, yuv.v));
+                textures.push((
+                    ShaderUniform::YuvTextureA,
+                    yuv.a.unwrap_or(entry.raw_texture),
+                ));
+                let alpha_plane = if yuv.a.is_some() { 1.0 } else { 0.0 };
+                program.uniform1f(&self.gl, ShaderUniform::YuvAlphaPlane, alpha_plane);
+            }
 
-            // Set texture parameters.
             let filter = if smoothing {
                 glow::LINEAR as i32
             } else {
                 glow::NEAREST as i32
             };
-            self.gl
-                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, filter);
-            self.gl
-                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, filter);
-
             let wrap = glow::CLAMP_TO_EDGE as i32;
-            self.gl
-                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, wrap);
-            self.gl
-                .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, wrap);
+            for (unit, (uniform, texture)) in textures.into_iter().enumerate() {
+                self.gl.active_texture(glow::TEXTURE0 + unit as u32);
+                self.gl.bind_texture(glow::TEXTURE_2D, Some(texture));
+                program.uniform1i(&self.gl, uniform, unit as i32);
+
+                // Set texture parameters.
+                self.gl
+                    .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MAG_FILTER, filter);
+                self.gl
+                    .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_MIN_FILTER, filter);
+                self.gl
+                    .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_S, wrap);
+                self.gl
+                    .tex_parameter_i32(glow::TEXTURE_2D, glow::TEXTURE_WRAP_T, wrap);
+            }
+            self.gl.active_texture(glow::TEXTURE0);
 
             // Draw the triangles.
             self.gl
@@ -1471,6 +3061,7 @@ impl CommandHandler for GlowRenderBackend {
     }
 
     fn render_shape(&mut self, shape: ShapeHandle, transform: Transform) {
+        self.flush_bitmap_batch();
         unsafe {
             let world_matrix = [
                 [transform.matrix.a, transform.matrix.b, 0.0, 0.0],
@@ -1489,8 +3080,9 @@ impl CommandHandler for GlowRenderBackend {
 
             self.set_stencil_state();
 
-            let mesh = as_mesh(&shape);
-            for draw in &mesh.draws {
+            let draws = self.mesh_draws(as_mesh(&shape));
+            let mut uploaded_draw_uniforms = false;
+            for draw in draws {
                 // Ignore strokes when drawing a mask stencil.
                 let num_indices = if self.mask_state != MaskState::DrawMaskStencil
                     && self.mask_state != MaskState::ClearMaskStencil
@@ -1509,6 +3101,7 @@ impl CommandHandler for GlowRenderBackend {
                     DrawType::Color => &self.color_program,
                     DrawType::Gradient(_) => &self.gradient_program,
                     DrawType::Bitmap { .. } => &self.bitmap_program,
+                    DrawType::Stroke => &self.stroke_program,
                 };
 
                 // Set common render state, while minimizing unnecessary state changes.
@@ -1528,19 +3121,41 @@ impl CommandHandler for GlowRenderBackend {
                     self.add_color = None;
                 }
 
-                program.uniform_matrix4fv(&self.gl, ShaderUniform::WorldMatrix, &world_matrix);
-                if Some(mult_color) != self.mult_color {
-                    program.uniform4fv(&self.gl, ShaderUniform::MultColor, &mult_color);
-                    self.mult_color = Some(mult_color);
-                }
-                if Some(add_color) != self.add_color {
-                    program.uniform4fv(&self.gl, ShaderUniform::AddColor, &add_color);
-                    self.add_color = Some(add_color);
+                // On the GLES3 path the transform only changes per shape, not per draw.
+                if let Some(uniform_buffers) = &self.uniform_buffers {
+                    if !uploaded_draw_uniforms {
+                        uniform_buffers.upload_draw(
+                            &self.gl,
+                            &DrawUniforms {
+                                world_matrix,
+                                mult_color,
+                                add_color,
+                            },
+                        );
+                        uploaded_draw_uniforms = true;
+                    }
+                } else {
+                    program.uniform_matrix4fv(&self.gl, ShaderUniform::WorldMatrix, &world_matrix);
+                    if Some(mult_color) != self.mult_color {
+                        program.uniform4fv(&self.gl, ShaderUniform::MultColor, &mult_color);
+                        self.mult_color = Some(mult_color);
+                    }
+                    if Some(add_color) != self.add_color {
+                        program.uniform4fv(&self.gl, ShaderUniform::AddColor, &add_color);
+                        self.add_color = Some(add_color);
+                    }
                 }
 
                 // Set shader specific uniforms.
                 match &draw.draw_type {
                     DrawType::Color => (),
+                    DrawType::Stroke => {
+                        program.uniform1f(
+                            &self.gl,
+                            ShaderUniform::Antialias,
+                            if self.stroke_antialiasing { 1.0 } else { 0.0 },
+                        );
+                    }
                     DrawType::Gradient(gradient) => {
                         program.uniform_matrix3fv(
                             &self.gl,
@@ -1581,7 +3196,13 @@ impl CommandHandler for GlowRenderBackend {
                     }
                     DrawType::Bitmap(bitmap) => {
                         let texture = match &bitmap.handle {
-                            Some(handle) => &as_registry_data(handle).texture,
+                            Some(handle) => {
+                                let entry = as_registry_data(handle);
+                                if !entry.upload_pending(self.rendering_offscreen) {
+                                    continue;
+                                }
+                                entry.raw_texture
+                            }
                             None => {
                                 log::warn!("Tried to render a handleless bitmap");
                                 continue;
@@ -1596,7 +3217,7 @@ impl CommandHandler for GlowRenderBackend {
 
                         // Bind texture.
                         self.gl.active_texture(glow::TEXTURE0);
-                        self.gl.bind_texture(glow::TEXTURE_2D, Some(*texture));
+                        self.gl.bind_texture(glow::TEXTURE_2D, Some(texture));
                         program.uniform1i(&self.gl, ShaderUniform::BitmapTexture, 0);
 
                         // Set texture parameters.
@@ -1640,22 +3261,26 @@ impl CommandHandler for GlowRenderBackend {
     }
 
     fn draw_rect(&mut self, color: Color, matrix: Matrix) {
+        self.flush_bitmap_batch();
         self.draw_quad::<{ glow::TRIANGLE_FAN }, -1>(color, matrix)
     }
 
     fn draw_line(&mut self, color: Color, mut matrix: Matrix) {
+        self.flush_bitmap_batch();
         matrix.tx += Twips::HALF_PX;
         matrix.ty += Twips::HALF_PX;
-        self.draw_quad::<{ glow::LINE_STRIP }, 2>(color, matrix)
+        self.draw_hairline(LINE_DRAW, color, matrix)
     }
 
     fn draw_line_rect(&mut self, color: Color, mut matrix: Matrix) {
+        self.flush_bitmap_batch();
         matrix.tx += Twips::HALF_PX;
         matrix.ty += Twips::HALF_PX;
-        self.draw_quad::<{ glow::LINE_LOOP }, -1>(color, matrix)
+        self.draw_hairline(LINE_RECT_DRAW, color, matrix)
     }
 
     fn push_mask(&mut self) {
+        self.flush_bitmap_batch();
         debug_assert!(
             self.mask_state == MaskState::NoMask || self.mask_state == MaskState::DrawMaskedContent
         );
@@ -1665,18 +3290,21 @@ impl CommandHandler for GlowRenderBackend {
     }
 
     fn activate_mask(&mut self) {
+        self.flush_bitmap_batch();
         debug_assert!(self.num_masks > 0 && self.mask_state == MaskState::DrawMaskStencil);
         self.mask_state = MaskState::DrawMaskedContent;
         self.mask_state_dirty = true;
     }
 
     fn deactivate_mask(&mut self) {
+        self.flush_bitmap_batch();
         debug_assert!(self.num_masks > 0 && self.mask_state == MaskState::DrawMaskedContent);
         self.mask_state = MaskState::ClearMaskStencil;
         self.mask_state_dirty = true;
     }
 
     fn pop_mask(&mut self) {
+        self.flush_bitmap_batch();
         debug_assert!(self.num_masks > 0 && self.mask_state == MaskState::ClearMaskStencil);
         self.num_masks -= 1;
         self.mask_state = if self.num_masks == 0 {
@@ -1688,8 +3316,10 @@ impl CommandHandler for GlowRenderBackend {
     }
 
     fn blend(&mut self, commands: CommandList, blend: RenderBlendMode) {
+        self.flush_bitmap_batch();
         self.push_blend_mode(blend);
         commands.execute(self);
+        self.flush_bitmap_batch();
         self.pop_blend_mode();
     }
 
@@ -1770,13 +3400,23 @@ struct BitmapDraw {
 #[derive(Debug)]
 struct Mesh {
     gl2: Arc<glow::Context>,
-    draws: Vec<Draw>,
+    /// Filled in by `GlowRenderBackend::mesh_draws` once the shape is drawn after its
+    /// tessellation has finished.
+    draws: OnceCell<Vec<Draw>>,
+    pending: RefCell<Option<PendingShape>>,
+}
+
+/// A shape that's still being tessellated.
+#[derive(Debug)]
+struct PendingShape {
+    runs: Pending<Vec<ShapeRun>>,
+    bitmaps: HashMap<CharacterId, Option<BitmapHandle>>,
 }
 
 impl Drop for Mesh {
     fn drop(&mut self) {
         unsafe {
-            for draw in &self.draws {
+            for draw in self.draws.get().into_iter().flatten() {
                 self.gl2.delete_vertex_array(draw.vao);
             }
         }
@@ -1820,6 +3460,13 @@ enum DrawType {
     Color,
     Gradient(Box<Gradient>),
     Bitmap(BitmapDraw),
+    Stroke,
+}
+
+struct StageBuffers {
+    framebuffer: glow::Framebuffer,
+    stencil_renderbuffer: glow::Renderbuffer,
+    texture: glow::Texture,
 }
 
 struct MsaaBuffers {
@@ -1832,6 +3479,74 @@ struct MsaaBuffers {
     framebuffer_texture: glow::Texture,
 }
 
+/// The uniform buffers behind the `ViewBlock` and `DrawBlock` uniform blocks.
+struct UniformBuffers {
+    view_buffer: Buffer,
+    draw_buffer: Buffer,
+}
+
+impl UniformBuffers {
+    fn new(gl: &Arc<glow::Context>) -> Result<Self, Error> {
+        unsafe {
+            let create = |binding: u32, size: usize| -> Result<Buffer, Error> {
+                let buffer = gl
+                    .create_buffer()
+                    .map_err(|_| Error::UnableToCreateBuffer)?;
+                gl.bind_buffer(glow::UNIFORM_BUFFER, Some(buffer));
+                gl.buffer_data_size(glow::UNIFORM_BUFFER, size as i32, glow::DYNAMIC_DRAW);
+                gl.bind_buffer_base(glow::UNIFORM_BUFFER, binding, Some(buffer));
+                Ok(Buffer {
+                    gl: gl.clone(),
+                    buffer,
+                })
+            };
+            let view_buffer = create(VIEW_BLOCK_BINDING, std::mem::size_of::<[[f32; 4]; 4]>())?;
+            let draw_buffer = create(DRAW_BLOCK_BINDING, std::mem::size_of::<DrawUniforms>())?;
+            gl.bind_buffer(glow::UNIFORM_BUFFER, None);
+            Ok(Self {
+                view_buffer,
+                draw_buffer,
+            })
+        }
+    }
+
+    fn upload_view(&self, gl: &glow::Context, view_matrix: &[[f32; 4]; 4]) {
+        unsafe {
+            gl.bind_buffer(glow::UNIFORM_BUFFER, Some(self.view_buffer.buffer));
+            gl.buffer_sub_data_u8_slice(glow::UNIFORM_BUFFER, 0, bytemuck::cast_slice(view_matrix));
+        }
+    }
+
+    fn upload_draw(&self, gl: &glow::Context, uniforms: &DrawUniforms) {
+        unsafe {
+            gl.bind_buffer(glow::UNIFORM_BUFFER, Some(self.draw_buffer.buffer));
+            gl.buffer_sub_data_u8_slice(glow::UNIFORM_BUFFER, 0, bytemuck::bytes_of(uniforms));
+        }
+    }
+}
+
+/// Consecutive `render_bitmap` calls sharing a texture, drawn as one instanced quad.
+struct BitmapBatch {
+    program: ShaderProgram,
+    vao: glow::VertexArray,
+    #[expect(dead_code)]
+    vertex_buffer: Buffer,
+    #[expect(dead_code)]
+    index_buffer: Buffer,
+    instance_buffer: Buffer,
+    handle: Option<BitmapHandle>,
+    smoothing: bool,
+    instances: Vec<BitmapInstance>,
+}
+
+impl Drop for BitmapBatch {
+    fn drop(&mut self) {
+        unsafe {
+            self.instance_buffer.gl.delete_vertex_array(self.vao);
+        }
+    }
+}
+
 // Because the shaders are currently simple and few in number, we are using a
 // straightforward shader model. We maintain an enum of every possible uniform,
 // and each shader tries to grab the location of each uniform.
@@ -1840,11 +3555,13 @@ struct ShaderProgram {
     uniforms: [Option<glow::UniformLocation>; NUM_UNIFORMS],
     vertex_position_location: u32,
     vertex_color_location: u32,
+    vertex_tangent_location: u32,
+    vertex_stroke_location: u32,
     num_vertex_attributes: u32,
 }
 
 // These should match the uniform names in the shaders.
-const NUM_UNIFORMS: usize = 12;
+const NUM_UNIFORMS: usize = 18;
 const UNIFORM_NAMES: [&str; NUM_UNIFORMS] = [
     "world_matrix",
     "view_matrix",
@@ -1858,6 +3575,12 @@ const UNIFORM_NAMES: [&str; NUM_UNIFORMS] = [
     "u_focal_point",
     "u_interpolation",
     "u_texture",
+    "u_antialias",
+    "u_blur_step",
+    "u_texture_u",
+    "u_texture_v",
+    "u_texture_a",
+    "u_alpha_plane",
 ];
 
 enum ShaderUniform {
@@ -1873,6 +3596,12 @@ enum ShaderUniform {
     GradientFocalPoint,
     GradientInterpolation,
     BitmapTexture,
+    Antialias,
+    BlurStep,
+    YuvTextureU,
+    YuvTextureV,
+    YuvTextureA,
+    YuvAlphaPlane,
 }
 
 impl ShaderProgram {
@@ -1880,6 +3609,7 @@ impl ShaderProgram {
         gl: &glow::Context,
         vertex_shader: glow::Shader,
         fragment_shader: glow::Shader,
+        render_path: RenderPath,
     ) -> Result<Self, Error> {
         unsafe {
             let program = gl.create_program().unwrap();
@@ -1888,6 +3618,18 @@ impl ShaderProgram {
 
             gl.link_program(program);
 
+            // Hook the transform blocks up to the shared uniform buffers.
+            if render_path == RenderPath::Gles3 {
+                for (name, binding) in [
+                    ("ViewBlock", VIEW_BLOCK_BINDING),
+                    ("DrawBlock", DRAW_BLOCK_BINDING),
+                ] {
+                    if let Some(index) = gl.get_uniform_block_index(program, name) {
+                        gl.uniform_block_binding(program, index, binding);
+                    }
+                }
+            }
+
             // Find uniforms.
             let mut uniforms: [Option<glow::UniformLocation>; NUM_UNIFORMS] = Default::default();
             for i in 0..NUM_UNIFORMS {
@@ -1900,21 +3642,29 @@ impl ShaderProgram {
             let vertex_color_location = gl
                 .get_attrib_location(program, "color")
                 .unwrap_or(0xffff_ffff);
-            let num_vertex_attributes = if vertex_position_location != 0xffff_ffff {
-                1
-            } else {
-                0
-            } + if vertex_color_location != 0xffff_ffff {
-                1
-            } else {
-                0
-            };
+            let vertex_tangent_location = gl
+                .get_attrib_location(program, "tangent")
+                .unwrap_or(0xffff_ffff);
+            let vertex_stroke_location = gl
+                .get_attrib_location(program, "stroke")
+                .unwrap_or(0xffff_ffff);
+            let num_vertex_attributes = [
+                vertex_position_location,
+                vertex_color_location,
+                vertex_tangent_location,
+                vertex_stroke_location,
+            ]
+            .iter()
+            .filter(|&&location| location != 0xffff_ffff)
+            .count() as u32;
 
             Ok(ShaderProgram {
                 program,
                 uniforms,
                 vertex_position_location,
                 vertex_color_location,
+                vertex_tangent_location,
+                vertex_stroke_location,
                 num_vertex_attributes,
             })
         }
@@ -1938,6 +3688,12 @@ impl ShaderProgram {
         }
     }
 
+    fn uniform2f(&self, gl: &glow::Context, uniform: ShaderUniform, x: f32, y: f32) {
+        unsafe {
+            gl.uniform_2_f32(self.uniforms[uniform as usize].as_ref(), x, y);
+        }
+    }
+
     fn uniform4fv(&self, gl: &glow::Context, uniform: ShaderUniform, values: &[f32]) {
         unsafe {
             gl.uniform_4_f32_slice(self.uniforms[uniform as usize].as_ref(), values);
@@ -0,0 +1,819 @@
+//! On-disk cache of tessellated shape meshes.
+//!
+//! Tessellating every shape of a large vector SWF takes a long time on the Vita's CPU, and the
+//! result is the same on every launch. Each SWF gets its own cache file named after the hash of
+//! its data. The file is a header followed by an append-only list of records, each holding the
+//! mesh of one tessellated run of paths. A torn record at the end of the file (e.g. the app was
+//! closed while writing) is cut off the next time the file is opened.
+
+use crate::{Gradient, Vertex, MAX_GRADIENT_COLORS};
+use ruffle_render::bitmap::BitmapSource;
+use ruffle_render::shape_utils::{DistilledShape, DrawCommand, DrawPath, FillRule};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::hash::Hasher;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use swf::{
+    CharacterId, Color, FillStyle, GradientInterpolation, GradientSpread, LineCapStyle,
+    LineJoinStyle, LineStyle, Matrix, Rectangle, Twips,
+};
+
+const MAGIC: &[u8; 4] = b"RTES";
+
+/// Bump this whenever the record layout or the way meshes are built from the tessellator's
+/// output changes.
+const FORMAT_VERSION: u32 = 2;
+
+/// The revision of `ruffle_render` that was built against, found by the build script.
+const RUFFLE_RENDER_REVISION: &str = env!("RUFFLE_RENDER_REVISION");
+
+/// The tolerance the tessellator flattens curves with, which is lyon's default since Ruffle
+/// doesn't set its own.
+const TESSELLATION_TOLERANCE: f32 = 0.1;
+
+const HEADER_SIZE: u64 = 16;
+const RECORD_HEADER_SIZE: u64 = 16;
+
+const CACHE_EXTENSION: &str = "tess";
+
+/// A tessellated draw, ready to be uploaded to the GPU.
+pub(crate) struct MeshDraw {
+    pub draw_type: MeshDrawType,
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+    pub num_mask_indices: u32,
+}
+
+pub(crate) enum MeshDrawType {
+    Color,
+    Gradient(Box<Gradient>),
+    Bitmap {
+        matrix: [[f32; 3]; 3],
+        bitmap_id: CharacterId,
+        is_smoothed: bool,
+        is_repeating: bool,
+    },
+}
+
+pub struct ShapeCache {
+    file: File,
+    file_len: u64,
+    max_size: u64,
+    /// Offset and length of the payload of every record, by shape key.
+    records: HashMap<u64, (u64, u32)>,
+}
+
+impl ShapeCache {
+    /// Opens the cache file for `swf_data` in `directory`, creating it if needed.
+    ///
+    /// `max_size` limits the size of the whole directory: the least recently used cache files
+    /// of other SWFs are deleted to make room, and no more records are added once the file of
+    /// this SWF reaches the limit on its own.
+    pub fn open(directory: &Path, swf_data: &[u8], max_size: u64) -> std::io::Result<Self> {
+        std::fs::create_dir_all(directory)?;
+
+        let mut hasher = Fnv64::default();
+        hasher.write(swf_data);
+        let path = directory.join(format!("{:016x}.{CACHE_EXTENSION}", hasher.finish()));
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let mut data = vec![];
+        file.read_to_end(&mut data)?;
+
+        let mut records = HashMap::new();
+        let valid_len = if data.len() as u64 >= HEADER_SIZE
+            && &data[0..4] == MAGIC
+            && read_u32(&data, 4) == FORMAT_VERSION
+            && data[8..16] == tessellator_hash().to_le_bytes()
+        {
+            scan_records(&data, &mut records)
+        } else {
+            if !data.is_empty() {
+                log::info!("Discarding outdated tessellation cache {}", path.display());
+            }
+            0
+        };
+
+        if valid_len != data.len() as u64 {
+            file.set_len(valid_len)?;
+        }
+        if valid_len == 0 {
+            file.seek(SeekFrom::Start(0))?;
+            file.write_all(MAGIC)?;
+            file.write_all(&FORMAT_VERSION.to_le_bytes())?;
+            file.write_all(&tessellator_hash().to_le_bytes())?;
+        }
+        let file_len = valid_len.max(HEADER_SIZE);
+        // Touch the file, so that eviction sees it as recently used.
+        let _ = file.set_modified(std::time::SystemTime::now());
+
+        evict_old_files(directory, &path, max_size);
+
+        log::info!(
+            "Loaded {} cached shapes from {}",
+            records.len(),
+            path.display()
+        );
+
+        Ok(Self {
+            file,
+            file_len,
+            max_size,
+            records,
+        })
+    }
+
+    /// Returns the cached mesh for `key`, if any.
+    pub(crate) fn get(&mut self, key: u64) -> Option<Vec<MeshDraw>> {
+        let (offset, len) = *self.records.get(&key)?;
+        let mut payload = vec![0; len as usize];
+        let result = self
+            .file
+            .seek(SeekFrom::Start(offset))
+            .and_then(|_| self.file.read_exact(&mut payload));
+        if let Err(e) = result {
+            log::warn!("Couldn't read cached shape: {e}");
+            return None;
+        }
+        let draws = decode_mesh(&payload);
+        if draws.is_none() {
+            log::warn!("Ignoring malformed cached shape {key:016x}");
+            self.records.remove(&key);
+        }
+        draws
+    }
+
+    /// Appends the mesh for `key` to the cache file, unless that would exceed the size limit.
+    pub(crate) fn insert(&mut self, key: u64, draws: &[MeshDraw]) {
+        if self.records.contains_key(&key) {
+            return;
+        }
+
+        let payload = encode_mesh(draws);
+        let record_len = RECORD_HEADER_SIZE + payload.len() as u64;
+        if self.file_len + record_len > self.max_size {
+            return;
+        }
+        let record = encode_record(key, &payload);
+
+        let result = self
+            .file
+            .seek(SeekFrom::Start(self.file_len))
+            .and_then(|_| self.file.write_all(&record));
+        match result {
+            Ok(()) => {
+                self.records.insert(
+                    key,
+                    (self.file_len + RECORD_HEADER_SIZE, payload.len() as u32),
+                );
+                self.file_len += record_len;
+            }
+            Err(e) => {
+                log::warn!("Couldn't write to the tessellation cache: {e}");
+                // Don't leave a partial record behind for the next append.
+                let _ = self.file.set_len(self.file_len);
+            }
+        }
+    }
+}
+
+/// Hashes everything besides the shape itself that the meshes depend on. A cache file made with
+/// a different tessellator is discarded, rather than serving meshes it wouldn't produce.
+fn tessellator_hash() -> u64 {
+    let mut hasher = Fnv64::default();
+    hasher.write(RUFFLE_RENDER_REVISION.as_bytes());
+    hasher.write_u32(TESSELLATION_TOLERANCE.to_bits());
+    hasher.finish()
+}
+
+/// Returns the key of a run of paths, or `None` if its mesh shouldn't be cached.
+///
+/// Shape ids are only unique within one SWF, and a shape can be split into several runs, so the
+/// key also covers the contents of the paths, along with the tessellator that meshes them and
+/// the sizes of the bitmaps that its bitmap fills are mapped with.
+///
+/// Only character definitions of the SWF are cached. Shapes made at runtime, such as by the
+/// Drawing API, have no id and can change every frame, which would fill up the cache file. Nor
+/// are shapes cached while the size of one of their bitmaps is unknown, since the tessellator
+/// then leaves that fill out.
+pub(crate) fn shape_key(shape: &DistilledShape, bitmap_source: &dyn BitmapSource) -> Option<u64> {
+    if shape.id == 0 {
+        return None;
+    }
+    let mut bitmap_ids: Vec<CharacterId> = shape
+        .paths
+        .iter()
+        .filter_map(|path| {
+            let style = match path {
+                DrawPath::Fill { style, .. } => *style,
+                DrawPath::Stroke { style, .. } => style.fill_style(),
+            };
+            match style {
+                FillStyle::Bitmap { id, .. } => Some(*id),
+                _ => None,
+            }
+        })
+        .collect();
+    bitmap_ids.sort_unstable();
+    bitmap_ids.dedup();
+
+    let mut hasher = Fnv64::default();
+    hasher.write_u64(tessellator_hash());
+    hasher.write_u16(shape.id);
+    hasher.write_u32(bitmap_ids.len() as u32);
+    for id in bitmap_ids {
+        let size = bitmap_source.bitmap_size(id)?;
+        hasher.write_u16(id);
+        hasher.write_u32(size.width.into());
+        hasher.write_u32(size.height.into());
+    }
+    hash_rectangle(&mut hasher, &shape.shape_bounds);
+    hash_rectangle(&mut hasher, &shape.edge_bounds);
+    for path in &shape.paths {
+        let commands = match path {
+            DrawPath::Fill {
+                style,
+                commands,
+                winding_rule,
+            } => {
+                hasher.write_u8(0);
+                hash_fill_style(&mut hasher, style);
+                hasher.write_u8(match winding_rule {
+                    FillRule::EvenOdd => 0,
+                    FillRule::NonZero => 1,
+                });
+                commands
+            }
+            DrawPath::Stroke {
+                style,
+                is_closed,
+                commands,
+            } => {
+                hasher.write_u8(1);
+                hash_line_style(&mut hasher, style);
+                hasher.write_u8(*is_closed as u8);
+                commands
+            }
+        };
+        hasher.write_u32(commands.len() as u32);
+        for command in commands {
+            match command {
+                DrawCommand::MoveTo(point) => {
+                    hasher.write_u8(0);
+                    hasher.write_i32(point.x.get());
+                    hasher.write_i32(point.y.get());
+                }
+                DrawCommand::LineTo(point) => {
+                    hasher.write_u8(1);
+                    hasher.write_i32(point.x.get());
+                    hasher.write_i32(point.y.get());
+                }
+                DrawCommand::QuadraticCurveTo { control, anchor } => {
+                    hasher.write_u8(2);
+                    for point in [control, anchor] {
+                        hasher.write_i32(point.x.get());
+                        hasher.write_i32(point.y.get());
+                    }
+                }
+                DrawCommand::CubicCurveTo {
+                    control_a,
+                    control_b,
+                    anchor,
+                } => {
+                    hasher.write_u8(3);
+                    for point in [control_a, control_b, anchor] {
+                        hasher.write_i32(point.x.get());
+                        hasher.write_i32(point.y.get());
+                    }
+                }
+            }
+        }
+    }
+    Some(hasher.finish())
+}
+
+fn hash_rectangle(hasher: &mut Fnv64, rectangle: &Rectangle<Twips>) {
+    hasher.write_i32(rectangle.x_min.get());
+    hasher.write_i32(rectangle.x_max.get());
+    hasher.write_i32(rectangle.y_min.get());
+    hasher.write_i32(rectangle.y_max.get());
+}
+
+fn hash_color(hasher: &mut Fnv64, color: &Color) {
+    hasher.write(&[color.r, color.g, color.b, color.a]);
+}
+
+fn hash_matrix(hasher: &mut Fnv64, matrix: &Matrix) {
+    for value in [matrix.a, matrix.b, matrix.c, matrix.d] {
+        hasher.write_i32(value.get());
+    }
+    hasher.write_i32(matrix.tx.get());
+    hasher.write_i32(matrix.ty.get());
+}
+
+fn hash_gradient(hasher: &mut Fnv64, gradient: &swf::Gradient) {
+    hash_matrix(hasher, &gradient.matrix);
+    hasher.write_u8(match gradient.spread {
+        GradientSpread::Pad => 0,
+        GradientSpread::Reflect => 1,
+        GradientSpread::Repeat => 2,
+    });
+    hasher.write_u8(match gradient.interpolation {
+        GradientInterpolation::Rgb => 0,
+        GradientInterpolation::LinearRgb => 1,
+    });
+    hasher.write_u32(gradient.records.len() as u32);
+    for record in &gradient.records {
+        hasher.write_u8(record.ratio);
+        hash_color(hasher, &record.color);
+    }
+}
+
+fn hash_fill_style(hasher: &mut Fnv64, style: &FillStyle) {
+    match style {
+        FillStyle::Color(color) => {
+            hasher.write_u8(0);
+            hash_color(hasher, color);
+        }
+        FillStyle::LinearGradient(gradient) => {
+            hasher.write_u8(1);
+            hash_gradient(hasher, gradient);
+        }
+        FillStyle::RadialGradient(gradient) => {
+            hasher.write_u8(2);
+            hash_gradient(hasher, gradient);
+        }
+        FillStyle::FocalGradient {
+            gradient,
+            focal_point,
+        } => {
+            hasher.write_u8(3);
+            hash_gradient(hasher, gradient);
+            hasher.write_i16(focal_point.get());
+        }
+        FillStyle::Bitmap {
+            id,
+            matrix,
+            is_smoothed,
+            is_repeating,
+        } => {
+            hasher.write_u8(4);
+            hasher.write_u16(*id);
+            hash_matrix(hasher, matrix);
+            hasher.write_u8(*is_smoothed as u8);
+            hasher.write_u8(*is_repeating as u8);
+        }
+    }
+}
+
+fn hash_line_style(hasher: &mut Fnv64, style: &LineStyle) {
+    hasher.write_i32(style.width().get());
+    hash_fill_style(hasher, style.fill_style());
+    for cap in [style.start_cap(), style.end_cap()] {
+        hasher.write_u8(match cap {
+            LineCapStyle::Round => 0,
+            LineCapStyle::None => 1,
+            LineCapStyle::Square => 2,
+        });
+    }
+    match style.join_style() {
+        LineJoinStyle::Round => hasher.write_u8(0),
+        LineJoinStyle::Bevel => hasher.write_u8(1),
+        LineJoinStyle::Miter(limit) => {
+            hasher.write_u8(2);
+            hasher.write_i16(limit.get());
+        }
+    }
+    hasher.write_u8(style.allow_scale_x() as u8);
+    hasher.write_u8(style.allow_scale_y() as u8);
+    hasher.write_u8(style.is_pixel_hinted() as u8);
+    hasher.write_u8(style.allow_close() as u8);
+}
+
+/// Prefixes `payload` with the header of its record.
+fn encode_record(key: u64, payload: &[u8]) -> Vec<u8> {
+    let mut checksum = Fnv64::default();
+    checksum.write(payload);
+
+    let mut record = Vec::with_capacity(RECORD_HEADER_SIZE as usize + payload.len());
+    record.extend_from_slice(&key.to_le_bytes());
+    record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    record.extend_from_slice(&(checksum.finish() as u32).to_le_bytes());
+    record.extend_from_slice(payload);
+    record
+}
+
+/// Indexes the records of a cache file, returning the length of the valid part of the file.
+fn scan_records(data: &[u8], records: &mut HashMap<u64, (u64, u32)>) -> u64 {
+    let mut offset = HEADER_SIZE as usize;
+    while data.len() - offset >= RECORD_HEADER_SIZE as usize {
+        let key = u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap());
+        let len = read_u32(data, offset + 8);
+        let checksum = read_u32(data, offset + 12);
+        let start = offset + RECORD_HEADER_SIZE as usize;
+        // A corrupt length can overflow `usize` on 32-bit targets.
+        let Some(end) = start.checked_add(len as usize) else {
+            break;
+        };
+        let Some(payload) = data.get(start..end) else {
+            break;
+        };
+        let mut hasher = Fnv64::default();
+        hasher.write(payload);
+        if hasher.finish() as u32 != checksum {
+            break;
+        }
+        records.insert(key, (start as u64, len));
+        offset = end;
+    }
+    offset as u64
+}
+
+/// Evicts the least recently used cache files until the directory fits in `max_size`.
+fn evict_old_files(directory: &Path, current: &Path, max_size: u64) {
+    let Ok(entries) = std::fs::read_dir(directory) else {
+        return;
+    };
+    let mut files: Vec<(PathBuf, u64, std::time::SystemTime)> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let path = entry.path();
+            if path.extension()? != CACHE_EXTENSION {
+                return None;
+            }
+            let metadata = entry.metadata().ok()?;
+            Some((path, metadata.len(), metadata.modified().ok()?))
+        })
+        .collect();
+
+    let mut total_size: u64 = files.iter().map(|(_, len, _)| len).sum();
+    files.sort_by_key(|(_, _, modified)| *modified);
+    for (path, len, _) in files {
+        if total_size <= max_size {
+            break;
+        }
+        if path == current {
+            continue;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => total_size -= len,
+            Err(e) => log::warn!("Couldn't evict {}: {e}", path.display()),
+        }
+    }
+}
+
+fn encode_mesh(draws: &[MeshDraw]) -> Vec<u8> {
+    let mut out = vec![];
+    out.extend_from_slice(&(draws.len() as u32).to_le_bytes());
+    for draw in draws {
+        match &draw.draw_type {
+            MeshDrawType::Color => out.push(0),
+            MeshDrawType::Gradient(gradient) => {
+                out.push(1);
+                write_matrix(&mut out, &gradient.matrix);
+                out.extend_from_slice(&gradient.gradient_type.to_le_bytes());
+                for ratio in gradient.ratios {
+                    out.extend_from_slice(&ratio.to_le_bytes());
+                }
+                for color in gradient.colors.iter().flatten() {
+                    out.extend_from_slice(&color.to_le_bytes());
+                }
+                out.extend_from_slice(&gradient.repeat_mode.to_le_bytes());
+                out.extend_from_slice(&gradient.focal_point.to_le_bytes());
+                out.push(match gradient.interpolation {
+                    GradientInterpolation::Rgb => 0,
+                    GradientInterpolation::LinearRgb => 1,
+                });
+            }
+            MeshDrawType::Bitmap {
+                matrix,
+                bitmap_id,
+                is_smoothed,
+                is_repeating,
+            } => {
+                out.push(2);
+                write_matrix(&mut out, matrix);
+                out.extend_from_slice(&bitmap_id.to_le_bytes());
+                out.push(*is_smoothed as u8);
+                out.push(*is_repeating as u8);
+            }
+        }
+        out.extend_from_slice(&draw.num_mask_indices.to_le_bytes());
+        out.extend_from_slice(&(draw.vertices.len() as u32).to_le_bytes());
+        for vertex in &draw.vertices {
+            out.extend_from_slice(&vertex.position[0].to_le_bytes());
+            out.extend_from_slice(&vertex.position[1].to_le_bytes());
+            out.extend_from_slice(&vertex.color.to_le_bytes());
+        }
+        out.extend_from_slice(&(draw.indices.len() as u32).to_le_bytes());
+        for index in &draw.indices {
+            out.extend_from_slice(&index.to_le_bytes());
+        }
+    }
+    out
+}
+
+fn decode_mesh(data: &[u8]) -> Option<Vec<MeshDraw>> {
+    let mut reader = Reader { data, offset: 0 };
+    let num_draws = reader.u32()?;
+    let mut draws = Vec::with_capacity(num_draws.min(1024) as usize);
+    for _ in 0..num_draws {
+        let draw_type = match reader.u8()? {
+            0 => MeshDrawType::Color,
+            1 => {
+                let matrix = reader.matrix()?;
+                let gradient_type = reader.u32()? as i32;
+                let mut ratios = [0.0; MAX_GRADIENT_COLORS];
+                for ratio in &mut ratios {
+                    *ratio = reader.f32()?;
+                }
+                let mut colors = [[0.0; 4]; MAX_GRADIENT_COLORS];
+                for component in colors.iter_mut().flatten() {
+                    *component = reader.f32()?;
+                }
+                let repeat_mode = reader.u32()? as i32;
+                let focal_point = reader.f32()?;
+                let interpolation = match reader.u8()? {
+                    0 => GradientInterpolation::Rgb,
+                    1 => GradientInterpolation::LinearRgb,
+                    _ => return None,
+                };
+                MeshDrawType::Gradient(Box::new(Gradient {
+                    matrix,
+                    gradient_type,
+                    ratios,
+                    colors,
+                    repeat_mode,
+                    focal_point,
+                    interpolation,
+                }))
+            }
+            2 => MeshDrawType::Bitmap {
+                matrix: reader.matrix()?,
+                bitmap_id: reader.u16()?,
+                is_smoothed: reader.u8()? != 0,
+                is_repeating: reader.u8()? != 0,
+            },
+            _ => return None,
+        };
+        let num_mask_indices = reader.u32()?;
+
+        let num_vertices = reader.u32()? as usize;
+        if reader.remaining() < num_vertices.checked_mul(12)? {
+            return None;
+        }
+        let mut vertices = Vec::with_capacity(num_vertices);
+        for _ in 0..num_vertices {
+            vertices.push(Vertex {
+                position: [reader.f32()?, reader.f32()?],
+                color: reader.u32()?,
+            });
+        }
+
+        let num_indices = reader.u32()? as usize;
+        if reader.remaining() < num_indices.checked_mul(4)? {
+            return None;
+        }
+        let mut indices = Vec::with_capacity(num_indices);
+        for _ in 0..num_indices {
+            indices.push(reader.u32()?);
+        }
+
+        draws.push(MeshDraw {
+            draw_type,
+            vertices,
+            indices,
+            num_mask_indices,
+        });
+    }
+    Some(draws)
+}
+
+fn write_matrix(out: &mut Vec<u8>, matrix: &[[f32; 3]; 3]) {
+    for value in matrix.iter().flatten() {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl Reader<'_> {
+    fn remaining(&self) -> usize {
+        self.data.len() - self.offset
+    }
+
+    fn bytes<const N: usize>(&mut self) -> Option<[u8; N]> {
+        let bytes = self.data.get(self.offset..self.offset.checked_add(N)?)?;
+        self.offset += N;
+        bytes.try_into().ok()
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes::<1>()?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.bytes()?))
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes()?))
+    }
+
+    fn f32(&mut self) -> Option<f32> {
+        Some(f32::from_le_bytes(self.bytes()?))
+    }
+
+    fn matrix(&mut self) -> Option<[[f32; 3]; 3]> {
+        let mut matrix = [[0.0; 3]; 3];
+        for value in matrix.iter_mut().flatten() {
+            *value = self.f32()?;
+        }
+        Some(matrix)
+    }
+}
+
+/// 64-bit FNV-1a. Unlike `DefaultHasher`, its output is stable across Rust releases, which
+/// matters for keys that are persisted to disk.
+struct Fnv64(u64);
+
+impl Default for Fnv64 {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv64 {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0000_0100_0000_01b3);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn draws() -> Vec<MeshDraw> {
+        let vertices = vec![
+            Vertex {
+                position: [0.0, 0.0],
+                color: 0xff00_00ff,
+            },
+            Vertex {
+                position: [20.5, -3.25],
+                color: 0x8000_ff00,
+            },
+            Vertex {
+                position: [7.0, 100.0],
+                color: 0xffff_ffff,
+            },
+        ];
+        let mut ratios = [0.0; MAX_GRADIENT_COLORS];
+        ratios[1] = 1.0;
+        let mut colors = [[0.0; 4]; MAX_GRADIENT_COLORS];
+        colors[1] = [1.0, 0.5, 0.25, 1.0];
+        vec![
+            MeshDraw {
+                draw_type: MeshDrawType::Color,
+                vertices: vertices.clone(),
+                indices: vec![0, 1, 2],
+                num_mask_indices: 3,
+            },
+            MeshDraw {
+                draw_type: MeshDrawType::Gradient(Box::new(Gradient {
+                    matrix: [[1.0, 0.0, 0.0], [0.0, 2.0, 0.0], [3.0, 4.0, 1.0]],
+                    gradient_type: 2,
+                    ratios,
+                    colors,
+                    repeat_mode: 1,
+                    focal_point: -0.5,
+                    interpolation: GradientInterpolation::LinearRgb,
+                })),
+                vertices: vertices.clone(),
+                indices: vec![2, 1, 0],
+                num_mask_indices: 0,
+            },
+            MeshDraw {
+                draw_type: MeshDrawType::Bitmap {
+                    matrix: [[0.5, 0.0, 0.0], [0.0, 0.5, 0.0], [0.0, 0.0, 1.0]],
+                    bitmap_id: 42,
+                    is_smoothed: true,
+                    is_repeating: false,
+                },
+                vertices,
+                indices: vec![0, 2, 1, 0, 1, 2],
+                num_mask_indices: 6,
+            },
+        ]
+    }
+
+    fn header() -> Vec<u8> {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        data.extend_from_slice(&tessellator_hash().to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn meshes_round_trip() {
+        let encoded = encode_mesh(&draws());
+        let decoded = decode_mesh(&encoded).unwrap();
+        assert_eq!(decoded.len(), 3);
+        assert!(matches!(decoded[0].draw_type, MeshDrawType::Color));
+        assert_eq!(decoded[0].indices, [0, 1, 2]);
+        assert_eq!(decoded[0].num_mask_indices, 3);
+        assert_eq!(decoded[1].vertices[1].position, [20.5, -3.25]);
+        assert_eq!(decoded[1].vertices[1].color, 0x8000_ff00);
+        match &decoded[1].draw_type {
+            MeshDrawType::Gradient(gradient) => {
+                assert_eq!(gradient.gradient_type, 2);
+                assert_eq!(gradient.colors[1], [1.0, 0.5, 0.25, 1.0]);
+                assert_eq!(gradient.focal_point, -0.5);
+                assert_eq!(gradient.interpolation, GradientInterpolation::LinearRgb);
+            }
+            _ => panic!("expected a gradient"),
+        }
+        assert!(matches!(
+            decoded[2].draw_type,
+            MeshDrawType::Bitmap {
+                bitmap_id: 42,
+                is_smoothed: true,
+                is_repeating: false,
+                ..
+            }
+        ));
+        assert_eq!(encode_mesh(&decoded), encoded);
+    }
+
+    #[test]
+    fn truncated_meshes_are_rejected() {
+        let encoded = encode_mesh(&draws());
+        for len in [0, 1, 5, encoded.len() / 2, encoded.len() - 1] {
+            assert!(decode_mesh(&encoded[..len]).is_none(), "{len}");
+        }
+    }
+
+    #[test]
+    fn torn_records_are_cut_off() {
+        let first = encode_mesh(&draws());
+        let second = encode_mesh(&draws()[..1]);
+        let mut data = header();
+        data.extend_from_slice(&encode_record(1, &first));
+        let valid_len = data.len() as u64;
+        let torn = encode_record(2, &second);
+        data.extend_from_slice(&torn[..torn.len() - 3]);
+
+        let mut records = HashMap::new();
+        assert_eq!(scan_records(&data, &mut records), valid_len);
+        assert_eq!(records.len(), 1);
+        let (offset, len) = records[&1];
+        assert_eq!(offset, HEADER_SIZE + RECORD_HEADER_SIZE);
+        assert_eq!(len as usize, first.len());
+    }
+
+    #[test]
+    fn corrupt_records_are_cut_off() {
+        let payload = encode_mesh(&draws());
+        let mut data = header();
+        data.extend_from_slice(&encode_record(1, &payload));
+        let valid_len = data.len() as u64;
+
+        // A flipped bit fails the checksum.
+        let mut corrupt = encode_record(2, &payload);
+        let last = corrupt.len() - 1;
+        corrupt[last] ^= 1;
+        let mut records = HashMap::new();
+        let mut with_corrupt = data.clone();
+        with_corrupt.extend_from_slice(&corrupt);
+        assert_eq!(scan_records(&with_corrupt, &mut records), valid_len);
+        assert!(!records.contains_key(&2));
+
+        // A length running past the end of the file doesn't panic.
+        let mut huge = encode_record(3, &payload);
+        huge[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        let mut records = HashMap::new();
+        data.extend_from_slice(&huge);
+        assert_eq!(scan_records(&data, &mut records), valid_len);
+        assert_eq!(records.len(), 1);
+    }
+}
@@ -0,0 +1,601 @@
+//! Strokes that are expanded into quads by the stroke shader instead of the tessellator.
+//!
+//! GL line primitives rasterize differently on every driver (and barely work on vitaGL),
+//! and tessellated strokes bake their thickness into shape space. Hairlines and solid strokes
+//! with a non-normal `LineScaleMode` are therefore stored as centerline segments, and the
+//! vertex shader pushes each side out by a thickness measured in screen pixels.
+//!
+//! Strokes wider than a hairline get their caps and joins as fans around the points they
+//! belong to, whose rim is pushed out the same way. Miter joins are drawn round. Where the
+//! pieces overlap, translucent strokes show it, as they do where tessellated strokes overlap.
+
+use std::f32::consts::TAU;
+
+use bytemuck::{Pod, Zeroable};
+use ruffle_render::shape_utils::{DrawCommand, DrawPath};
+use swf::{FillStyle, LineCapStyle, LineJoinStyle, LineStyle, Twips};
+
+/// Number of line segments used to approximate each curve.
+const CURVE_SEGMENTS: usize = 16;
+
+/// Number of triangles in a round cap, which round joins use their share of.
+const ROUND_SEGMENTS: usize = 16;
+
+/// Half the width of a hairline, in pixels.
+const HAIRLINE_HALF_WIDTH: f32 = 0.5;
+
+/// Which axes of the world matrix the stroke thickness follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrokeScale {
+    /// `LineScaleMode.NONE` and hairlines: always the same number of pixels.
+    None = 0,
+
+    /// `LineScaleMode.HORIZONTAL`: only horizontal scaling affects the thickness.
+    Horizontal = 1,
+
+    /// `LineScaleMode.VERTICAL`: only vertical scaling affects the thickness.
+    Vertical = 2,
+}
+
+impl StrokeScale {
+    fn from_line_style(style: &LineStyle) -> Self {
+        match (style.allow_scale_x(), style.allow_scale_y()) {
+            (true, false) => StrokeScale::Horizontal,
+            (false, true) => StrokeScale::Vertical,
+            _ => StrokeScale::None,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+pub struct StrokeVertex {
+    pub position: [f32; 2],
+    pub color: u32,
+    /// Direction of the segment in shape space.
+    pub tangent: [f32; 2],
+    /// How far the vertex is pushed out across and along the segment on screen, in half
+    /// widths, then the half width in pixels and the `StrokeScale`. The distance across also
+    /// drives the fade-out, so the rim of a fan is 1 across a tangent turned to point its way.
+    pub stroke: [f32; 4],
+}
+
+/// Returns whether this path should be drawn by the stroke shader.
+pub fn is_screen_space_stroke(path: &DrawPath) -> bool {
+    match path {
+        DrawPath::Stroke { style, .. } => {
+            let non_scaling = !style.allow_scale_x() || !style.allow_scale_y();
+            matches!(style.fill_style(), FillStyle::Color(_))
+                && (style.width() == Twips::ZERO || non_scaling)
+        }
+        DrawPath::Fill { .. } => false,
+    }
+}
+
+/// How a path is stroked.
+struct Pen {
+    color: u32,
+    half_width: f32,
+    scale: StrokeScale,
+    /// The start cap, end cap and joins, which hairlines go without.
+    outline: Option<(LineCapStyle, LineCapStyle, LineJoinStyle)>,
+}
+
+impl Pen {
+    /// A white hairline, for `draw_line` and `draw_line_rect`.
+    fn hairline() -> Self {
+        Self {
+            color: 0xffff_ffff,
+            half_width: HAIRLINE_HALF_WIDTH,
+            scale: StrokeScale::None,
+            outline: None,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct StrokeMesh {
+    pub vertices: Vec<StrokeVertex>,
+    pub indices: Vec<u32>,
+}
+
+impl StrokeMesh {
+    /// Mesh for `draw_line`: a hairline from (0, 0) to (1, 0).
+    pub fn line() -> Self {
+        let mut mesh = Self::default();
+        mesh.add_polyline(&[[0.0, 0.0], [1.0, 0.0]], false, &Pen::hairline());
+        mesh
+    }
+
+    /// Mesh for `draw_line_rect`: a hairline around the unit square.
+    pub fn line_rect() -> Self {
+        let mut mesh = Self::default();
+        let corners = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+        mesh.add_polyline(&corners, true, &Pen::hairline());
+        mesh
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Adds a `DrawPath::Stroke` accepted by `is_screen_space_stroke`.
+    pub fn add_path(&mut self, path: &DrawPath) {
+        let DrawPath::Stroke {
+            style,
+            is_closed,
+            commands,
+        } = path
+        else {
+            return;
+        };
+        let FillStyle::Color(color) = style.fill_style() else {
+            return;
+        };
+
+        let hairline = style.width() == Twips::ZERO;
+        let pen = Pen {
+            color: u32::from_le_bytes([color.r, color.g, color.b, color.a]),
+            half_width: if hairline {
+                HAIRLINE_HALF_WIDTH
+            } else {
+                (style.width().to_pixels() as f32 / 2.0).max(HAIRLINE_HALF_WIDTH)
+            },
+            scale: StrokeScale::from_line_style(style),
+            outline: (!hairline).then(|| (style.start_cap(), style.end_cap(), style.join_style())),
+        };
+
+        // Each subpath is flattened into the points it goes through.
+        let mut points = vec![[0.0, 0.0]];
+        for command in commands {
+            let cursor = points[points.len() - 1];
+            match command {
+                DrawCommand::MoveTo(point) => {
+                    self.add_polyline(&points, *is_closed, &pen);
+                    points = vec![to_pixels(point.x, point.y)];
+                }
+                DrawCommand::LineTo(point) => points.push(to_pixels(point.x, point.y)),
+                DrawCommand::QuadraticCurveTo { control, anchor } => {
+                    let control = to_pixels(control.x, control.y);
+                    let anchor = to_pixels(anchor.x, anchor.y);
+                    for i in 1..=CURVE_SEGMENTS {
+                        let t = i as f32 / CURVE_SEGMENTS as f32;
+                        let u = 1.0 - t;
+                        points.push([
+                            u * u * cursor[0] + 2.0 * u * t * control[0] + t * t * anchor[0],
+                            u * u * cursor[1] + 2.0 * u * t * control[1] + t * t * anchor[1],
+                        ]);
+                    }
+                }
+                DrawCommand::CubicCurveTo {
+                    control_a,
+                    control_b,
+                    anchor,
+                } => {
+                    let control_a = to_pixels(control_a.x, control_a.y);
+                    let control_b = to_pixels(control_b.x, control_b.y);
+                    let anchor = to_pixels(anchor.x, anchor.y);
+                    for i in 1..=CURVE_SEGMENTS {
+                        let t = i as f32 / CURVE_SEGMENTS as f32;
+                        let u = 1.0 - t;
+                        let (a, b, c, d) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+                        points.push([
+                            a * cursor[0] + b * control_a[0] + c * control_b[0] + d * anchor[0],
+                            a * cursor[1] + b * control_a[1] + c * control_b[1] + d * anchor[1],
+                        ]);
+                    }
+                }
+            }
+        }
+        self.add_polyline(&points, *is_closed, &pen);
+    }
+
+    /// Adds the segments between `points`, with the caps and joins of `pen`.
+    fn add_polyline(&mut self, points: &[[f32; 2]], is_closed: bool, pen: &Pen) {
+        if points.len() < 2 {
+            // Only moved to, so nothing is drawn.
+            return;
+        }
+        // Segments that go nowhere have no direction for the joins to follow.
+        let mut points = points.to_vec();
+        points.dedup_by(|point, previous| direction(*previous, *point).is_none());
+        if is_closed && points.len() > 2 && direction(points[points.len() - 1], points[0]).is_none()
+        {
+            points.pop();
+        }
+
+        if points.len() == 1 {
+            // A stroke that goes nowhere shows as a dot, made of its caps.
+            if let Some((start_cap, end_cap, _)) = pen.outline {
+                if !is_closed {
+                    self.add_cap(points[0], [1.0, 0.0], -1.0, start_cap, pen);
+                    if (start_cap, end_cap) != (LineCapStyle::Round, LineCapStyle::Round) {
+                        self.add_cap(points[0], [1.0, 0.0], 1.0, end_cap, pen);
+                    }
+                }
+            }
+            return;
+        }
+
+        let num_segments = if is_closed {
+            points.len()
+        } else {
+            points.len() - 1
+        };
+        let mut tangents = Vec::with_capacity(num_segments);
+        for i in 0..num_segments {
+            let (from, to) = (points[i], points[(i + 1) % points.len()]);
+            tangents.push(direction(from, to).unwrap_or([1.0, 0.0]));
+            self.add_segment(from, to, tangents[i], pen);
+        }
+
+        let Some((start_cap, end_cap, join)) = pen.outline else {
+            return;
+        };
+        let first_join = if is_closed { 0 } else { 1 };
+        for i in first_join..num_segments {
+            let incoming = tangents[(i + num_segments - 1) % num_segments];
+            self.add_join(points[i], incoming, tangents[i], join, pen);
+        }
+        if !is_closed {
+            self.add_cap(points[0], tangents[0], -1.0, start_cap, pen);
+            let last = points.len() - 1;
+            self.add_cap(points[last], tangents[last - 1], 1.0, end_cap, pen);
+        }
+    }
+
+    /// Adds a quad covering the segment from `from` to `to`.
+    /// The vertices sit on the centerline and are extruded in the vertex shader.
+    fn add_segment(&mut self, from: [f32; 2], to: [f32; 2], tangent: [f32; 2], pen: &Pen) {
+        let base = self.vertices.len() as u32;
+        for (position, across) in [(from, -1.0), (from, 1.0), (to, 1.0), (to, -1.0)] {
+            self.add_vertex(position, tangent, across, 0.0, pen);
+        }
+        self.add_quad_indices(base);
+    }
+
+    /// Fills the corner between two segments meeting at `point`.
+    fn add_join(
+        &mut self,
+        point: [f32; 2],
+        incoming: [f32; 2],
+        outgoing: [f32; 2],
+        join: LineJoinStyle,
+        pen: &Pen,
+    ) {
+        let cross = incoming[0] * outgoing[1] - incoming[1] * outgoing[0];
+        let dot = incoming[0] * outgoing[0] + incoming[1] * outgoing[1];
+        let turn = cross.atan2(dot);
+        if turn.abs() <= f32::EPSILON {
+            return;
+        }
+        let steps = match join {
+            LineJoinStyle::Bevel => 1,
+            LineJoinStyle::Round | LineJoinStyle::Miter(_) => {
+                ((turn.abs() * ROUND_SEGMENTS as f32 / TAU).ceil() as usize).max(1)
+            }
+        };
+        // Which side is outside the corner on screen depends on whether the world matrix
+        // mirrors the shape, so both are filled.
+        for across in [-1.0, 1.0] {
+            self.add_fan(point, incoming, turn, steps, across, pen);
+        }
+    }
+
+    /// Adds the cap at an end of a path, where `tangent` is the direction of the path and
+    /// `along` is -1 at the start and 1 at the end.
+    fn add_cap(
+        &mut self,
+        point: [f32; 2],
+        tangent: [f32; 2],
+        along: f32,
+        cap: LineCapStyle,
+        pen: &Pen,
+    ) {
+        match cap {
+            LineCapStyle::None => {}
+            // A whole disc, since a half one would end up on the wrong side of a mirrored end.
+            LineCapStyle::Round => self.add_fan(point, tangent, TAU, ROUND_SEGMENTS, 1.0, pen),
+            LineCapStyle::Square => {
+                let base = self.vertices.len() as u32;
+                for (across, along) in [(-1.0, 0.0), (1.0, 0.0), (1.0, along), (-1.0, along)] {
+                    self.add_vertex(point, tangent, across, along, pen);
+                }
+                self.add_quad_indices(base);
+            }
+        }
+    }
+
+    /// Adds a fan around `point` whose rim is pushed out across tangents turning from `tangent`
+    /// by `angle`, in `steps` triangles. The rim stays a half width away on screen however the
+    /// world matrix skews the tangents.
+    fn add_fan(
+        &mut self,
+        point: [f32; 2],
+        tangent: [f32; 2],
+        angle: f32,
+        steps: usize,
+        across: f32,
+        pen: &Pen,
+    ) {
+        let center = self.vertices.len() as u32;
+        self.add_vertex(point, tangent, 0.0, 0.0, pen);
+        for i in 0..=steps {
+            let (sin, cos) = (angle * i as f32 / steps as f32).sin_cos();
+            let rim_tangent = [
+                tangent[0] * cos - tangent[1] * sin,
+                tangent[0] * sin + tangent[1] * cos,
+            ];
+            self.add_vertex(point, rim_tangent, across, 0.0, pen);
+        }
+        for i in 0..steps as u32 {
+            self.indices
+                .extend_from_slice(&[center, center + 1 + i, center + 2 + i]);
+        }
+    }
+
+    /// Splits the quad made of the four vertices from `base` into two triangles.
+    fn add_quad_indices(&mut self, base: u32) {
+        self.indices
+            .extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    fn add_vertex(
+        &mut self,
+        position: [f32; 2],
+        tangent: [f32; 2],
+        across: f32,
+        along: f32,
+        pen: &Pen,
+    ) {
+        self.vertices.push(StrokeVertex {
+            position,
+            color: pen.color,
+            tangent,
+            stroke: [across, along, pen.half_width, pen.scale as u32 as f32],
+        });
+    }
+}
+
+/// The unit vector from `from` to `to`, if they're apart.
+fn direction(from: [f32; 2], to: [f32; 2]) -> Option<[f32; 2]> {
+    let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+    let length = (dx * dx + dy * dy).sqrt();
+    (length > f32::EPSILON).then(|| [dx / length, dy / length])
+}
+
+fn to_pixels(x: Twips, y: Twips) -> [f32; 2] {
+    [x.to_pixels() as f32, y.to_pixels() as f32]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use swf::{Color, Point};
+
+    fn style(width: f64) -> LineStyle {
+        LineStyle::new()
+            .with_width(Twips::from_pixels(width))
+            .with_color(Color {
+                r: 255,
+                g: 0,
+                b: 0,
+                a: 255,
+            })
+            .with_allow_scale_x(false)
+            .with_allow_scale_y(false)
+    }
+
+    fn point(x: f64, y: f64) -> Point<Twips> {
+        Point::new(Twips::from_pixels(x), Twips::from_pixels(y))
+    }
+
+    fn line_to(x: f64, y: f64) -> DrawCommand {
+        DrawCommand::LineTo(point(x, y))
+    }
+
+    fn mesh(style: &LineStyle, is_closed: bool, commands: Vec<DrawCommand>) -> StrokeMesh {
+        let mut mesh = StrokeMesh::default();
+        mesh.add_path(&DrawPath::Stroke {
+            style,
+            is_closed,
+            commands,
+        });
+        mesh
+    }
+
+    fn num_triangles(mesh: &StrokeMesh) -> usize {
+        mesh.indices.len() / 3
+    }
+
+    #[test]
+    fn stroke_scale_follows_the_line_scale_mode() {
+        let style = LineStyle::new();
+        let scale = |x, y| {
+            StrokeScale::from_line_style(&style.clone().with_allow_scale_x(x).with_allow_scale_y(y))
+        };
+        assert_eq!(scale(false, false), StrokeScale::None);
+        assert_eq!(scale(true, false), StrokeScale::Horizontal);
+        assert_eq!(scale(false, true), StrokeScale::Vertical);
+        // Only hairlines get here with normal scaling, and those stay a pixel wide.
+        assert_eq!(scale(true, true), StrokeScale::None);
+    }
+
+    #[test]
+    fn only_solid_hairlines_and_non_scaling_strokes_use_the_shader() {
+        let stroke = |style: &LineStyle| {
+            is_screen_space_stroke(&DrawPath::Stroke {
+                style,
+                is_closed: false,
+                commands: vec![],
+            })
+        };
+        assert!(stroke(&style(0.0)));
+        assert!(stroke(
+            &style(0.0).with_allow_scale_x(true).with_allow_scale_y(true)
+        ));
+        assert!(stroke(&style(20.0)));
+        assert!(stroke(&style(20.0).with_allow_scale_x(true)));
+        assert!(!stroke(
+            &style(20.0)
+                .with_allow_scale_x(true)
+                .with_allow_scale_y(true)
+        ));
+        assert!(!stroke(&style(20.0).with_fill_style(FillStyle::Bitmap {
+            id: 1,
+            matrix: swf::Matrix::IDENTITY,
+            is_smoothed: true,
+            is_repeating: false,
+        })));
+    }
+
+    #[test]
+    fn hairlines_are_one_quad_per_segment() {
+        let hairline = style(0.0);
+        let commands = vec![
+            DrawCommand::MoveTo(point(0.0, 0.0)),
+            line_to(10.0, 0.0),
+            line_to(10.0, 0.0),
+            line_to(10.0, 10.0),
+            line_to(0.0, 10.0),
+        ];
+        let open = mesh(&hairline, false, commands.clone());
+        // The segment going nowhere is left out.
+        assert_eq!(open.vertices.len(), 3 * 4);
+        assert_eq!(num_triangles(&open), 3 * 2);
+        assert!(open
+            .vertices
+            .iter()
+            .all(|vertex| vertex.stroke[1] == 0.0 && vertex.stroke[2] == HAIRLINE_HALF_WIDTH));
+
+        let closed = mesh(&hairline, true, commands);
+        assert_eq!(num_triangles(&closed), 4 * 2);
+
+        // A closed path that ends where it started isn't closed again.
+        let square = mesh(
+            &hairline,
+            true,
+            vec![
+                DrawCommand::MoveTo(point(0.0, 0.0)),
+                line_to(10.0, 0.0),
+                line_to(10.0, 10.0),
+                line_to(0.0, 10.0),
+                line_to(0.0, 0.0),
+            ],
+        );
+        assert_eq!(num_triangles(&square), 4 * 2);
+    }
+
+    #[test]
+    fn each_subpath_and_curve_is_split_up() {
+        let hairline = style(0.0);
+        let mesh = mesh(
+            &hairline,
+            true,
+            vec![
+                DrawCommand::MoveTo(point(0.0, 0.0)),
+                DrawCommand::QuadraticCurveTo {
+                    control: point(10.0, 0.0),
+                    anchor: point(10.0, 10.0),
+                },
+                DrawCommand::MoveTo(point(20.0, 0.0)),
+                line_to(30.0, 0.0),
+                line_to(30.0, 10.0),
+                // Only moved to, so it adds nothing.
+                DrawCommand::MoveTo(point(50.0, 50.0)),
+            ],
+        );
+        // The curve and the line back to its start, then the triangle.
+        assert_eq!(num_triangles(&mesh), (CURVE_SEGMENTS + 1 + 3) * 2);
+    }
+
+    #[test]
+    fn wide_strokes_get_caps_and_joins() {
+        let corner = vec![
+            DrawCommand::MoveTo(point(0.0, 0.0)),
+            line_to(10.0, 0.0),
+            line_to(10.0, 10.0),
+        ];
+        let with = |start_cap, end_cap, join| {
+            let style = style(8.0)
+                .with_start_cap(start_cap)
+                .with_end_cap(end_cap)
+                .with_join_style(join);
+            num_triangles(&mesh(&style, false, corner.clone()))
+        };
+        let segments = 2 * 2;
+
+        // A bevel is a triangle on either side of the corner.
+        assert_eq!(
+            with(LineCapStyle::None, LineCapStyle::None, LineJoinStyle::Bevel),
+            segments + 2
+        );
+        // A quarter turn takes a quarter of a round cap's triangles on either side.
+        let round_join = 2 * ROUND_SEGMENTS / 4;
+        assert_eq!(
+            with(LineCapStyle::None, LineCapStyle::None, LineJoinStyle::Round),
+            segments + round_join
+        );
+        assert_eq!(
+            with(
+                LineCapStyle::Square,
+                LineCapStyle::Round,
+                LineJoinStyle::Round
+            ),
+            segments + round_join + 2 + ROUND_SEGMENTS
+        );
+
+        // A closed path has a join where it started and no caps.
+        let style = style(8.0).with_join_style(LineJoinStyle::Bevel);
+        assert_eq!(num_triangles(&mesh(&style, true, corner)), 3 * 2 + 3 * 2);
+    }
+
+    #[test]
+    fn square_caps_reach_past_the_ends() {
+        let style = style(8.0)
+            .with_start_cap(LineCapStyle::Square)
+            .with_end_cap(LineCapStyle::Square);
+        let mesh = mesh(
+            &style,
+            false,
+            vec![DrawCommand::MoveTo(point(0.0, 0.0)), line_to(10.0, 0.0)],
+        );
+        let along: Vec<f32> = mesh
+            .vertices
+            .iter()
+            .map(|vertex| vertex.stroke[1])
+            .collect();
+        assert_eq!(
+            along,
+            [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, -1.0, -1.0, 0.0, 0.0, 1.0, 1.0]
+        );
+        assert!(mesh.vertices.iter().all(|vertex| vertex.stroke[2] == 4.0));
+    }
+
+    #[test]
+    fn strokes_going_nowhere_are_dots() {
+        let style = style(8.0);
+        let dot = mesh(
+            &style,
+            false,
+            vec![DrawCommand::MoveTo(point(5.0, 5.0)), line_to(5.0, 5.0)],
+        );
+        assert_eq!(num_triangles(&dot), ROUND_SEGMENTS);
+        // The rim is pushed out the whole half width, and the center not at all.
+        assert!(dot
+            .vertices
+            .iter()
+            .all(|vertex| vertex.position == [5.0, 5.0]));
+        assert_eq!(dot.vertices[0].stroke[0], 0.0);
+        assert!(dot.vertices[1..]
+            .iter()
+            .all(|vertex| vertex.stroke[0] == 1.0));
+
+        let hairline_dot = mesh(
+            &style(0.0),
+            false,
+            vec![DrawCommand::MoveTo(point(5.0, 5.0)), line_to(5.0, 5.0)],
+        );
+        assert!(hairline_dot.is_empty());
+    }
+}
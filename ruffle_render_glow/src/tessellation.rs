@@ -0,0 +1,258 @@
+//! Turning shapes into meshes, off the GL thread.
+//!
+//! `DistilledShape` borrows its styles from the movie, so it's first copied into an
+//! `OwnedShape` that can be sent to a worker. Everything the tessellator needs from the
+//! `BitmapSource` is gathered up front as well.
+
+use crate::shape_cache::{shape_key, MeshDraw, MeshDrawType, ShapeCache};
+use crate::stroke::{is_screen_space_stroke, StrokeMesh};
+use crate::{Gradient, Vertex};
+use ruffle_render::backend::RenderBackend;
+use ruffle_render::bitmap::{BitmapHandle, BitmapSize, BitmapSource};
+use ruffle_render::shape_utils::{DistilledShape, DrawCommand, DrawPath, FillRule};
+use ruffle_render::tessellator::ShapeTessellator;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use swf::{CharacterId, FillStyle, LineStyle, Rectangle, Twips};
+
+thread_local! {
+    static TESSELLATOR: RefCell<ShapeTessellator> = RefCell::new(ShapeTessellator::new());
+}
+
+enum OwnedPath {
+    Fill {
+        style: usize,
+        commands: Vec<DrawCommand>,
+        winding_rule: FillRule,
+    },
+    Stroke {
+        style: usize,
+        is_closed: bool,
+        commands: Vec<DrawCommand>,
+    },
+}
+
+/// A copy of a `DistilledShape` that doesn't borrow from the movie.
+pub(crate) struct OwnedShape {
+    id: CharacterId,
+    shape_bounds: Rectangle<Twips>,
+    edge_bounds: Rectangle<Twips>,
+    fill_styles: Vec<FillStyle>,
+    line_styles: Vec<LineStyle>,
+    paths: Vec<OwnedPath>,
+    bitmap_sizes: HashMap<CharacterId, BitmapSize>,
+}
+
+impl OwnedShape {
+    /// Copies `shape`, along with the sizes of the bitmaps used by its fills.
+    pub fn new(shape: DistilledShape, bitmap_source: &dyn BitmapSource) -> Self {
+        let mut fill_styles = vec![];
+        let mut line_styles = vec![];
+        let mut bitmap_sizes = HashMap::new();
+        let mut add_bitmap_size = |fill_style: &FillStyle| {
+            if let FillStyle::Bitmap { id, .. } = fill_style {
+                if let Some(size) = bitmap_source.bitmap_size(*id) {
+                    bitmap_sizes.insert(*id, size);
+                }
+            }
+        };
+
+        let paths = shape
+            .paths
+            .into_iter()
+            .map(|path| match path {
+                DrawPath::Fill {
+                    style,
+                    commands,
+                    winding_rule,
+                } => {
+                    add_bitmap_size(style);
+                    fill_styles.push(style.clone());
+                    OwnedPath::Fill {
+                        style: fill_styles.len() - 1,
+                        commands,
+                        winding_rule,
+                    }
+                }
+                DrawPath::Stroke {
+                    style,
+                    is_closed,
+                    commands,
+                } => {
+                    add_bitmap_size(style.fill_style());
+                    line_styles.push(style.clone());
+                    OwnedPath::Stroke {
+                        style: line_styles.len() - 1,
+                        is_closed,
+                        commands,
+                    }
+                }
+            })
+            .collect();
+
+        Self {
+            id: shape.id,
+            shape_bounds: shape.shape_bounds,
+            edge_bounds: shape.edge_bounds,
+            fill_styles,
+            line_styles,
+            paths,
+            bitmap_sizes,
+        }
+    }
+
+    /// The ids of the bitmaps used by this shape, whose handles have to be looked up on the
+    /// GL thread.
+    pub fn bitmap_ids(&self) -> impl Iterator<Item = CharacterId> + '_ {
+        self.bitmap_sizes.keys().copied()
+    }
+}
+
+/// Consecutive paths of a shape that are drawn the same way.
+pub(crate) enum ShapeRun {
+    Tessellated(Vec<MeshDraw>),
+    Strokes(StrokeMesh),
+}
+
+/// Splits `shape` into runs, tessellating (or fetching from `shape_cache`) the runs that need it.
+pub(crate) fn tessellate(
+    shape: OwnedShape,
+    shape_cache: Option<&Mutex<ShapeCache>>,
+) -> Vec<ShapeRun> {
+    let OwnedShape {
+        id,
+        shape_bounds,
+        edge_bounds,
+        fill_styles,
+        line_styles,
+        paths,
+        bitmap_sizes,
+    } = shape;
+    let bitmap_sizes = BitmapSizes(bitmap_sizes);
+
+    let paths = paths.into_iter().map(|path| match path {
+        OwnedPath::Fill {
+            style,
+            commands,
+            winding_rule,
+        } => DrawPath::Fill {
+            style: &fill_styles[style],
+            commands,
+            winding_rule,
+        },
+        OwnedPath::Stroke {
+            style,
+            is_closed,
+            commands,
+        } => DrawPath::Stroke {
+            style: &line_styles[style],
+            is_closed,
+            commands,
+        },
+    });
+
+    let tessellate_run = |paths: Vec<DrawPath>| {
+        let run = DistilledShape {
+            paths,
+            shape_bounds: shape_bounds.clone(),
+            edge_bounds: edge_bounds.clone(),
+            id,
+        };
+        ShapeRun::Tessellated(build_mesh(run, &bitmap_sizes, shape_cache))
+    };
+
+    // Hairlines and non-scaling strokes bypass the tessellator. The paths are split into
+    // runs so that they still stack in the same order as the other paths of the shape.
+    let mut runs = vec![];
+    let mut tessellated_paths = vec![];
+    let mut strokes = StrokeMesh::default();
+    for path in paths {
+        if is_screen_space_stroke(&path) {
+            if !tessellated_paths.is_empty() {
+                runs.push(tessellate_run(std::mem::take(&mut tessellated_paths)));
+            }
+            strokes.add_path(&path);
+        } else {
+            if !strokes.is_empty() {
+                runs.push(ShapeRun::Strokes(std::mem::take(&mut strokes)));
+            }
+            tessellated_paths.push(path);
+        }
+    }
+    if !strokes.is_empty() {
+        runs.push(ShapeRun::Strokes(strokes));
+    }
+    if !tessellated_paths.is_empty() {
+        runs.push(tessellate_run(tessellated_paths));
+    }
+    runs
+}
+
+/// Runs the tessellator over `shape`, unless it's already in `shape_cache`.
+fn build_mesh(
+    shape: DistilledShape,
+    bitmap_source: &dyn BitmapSource,
+    shape_cache: Option<&Mutex<ShapeCache>>,
+) -> Vec<MeshDraw> {
+    use ruffle_render::tessellator::DrawType as TessDrawType;
+
+    let cache_key = shape_cache.and_then(|_| shape_key(&shape, bitmap_source));
+    if let (Some(shape_cache), Some(key)) = (shape_cache, cache_key) {
+        if let Some(mesh) = shape_cache.lock().ok().and_then(|mut cache| cache.get(key)) {
+            return mesh;
+        }
+    }
+
+    let lyon_mesh = TESSELLATOR
+        .with_borrow_mut(|tessellator| tessellator.tessellate_shape(shape, bitmap_source));
+
+    let mesh: Vec<MeshDraw> = lyon_mesh
+        .draws
+        .into_iter()
+        .map(|draw| MeshDraw {
+            draw_type: match draw.draw_type {
+                TessDrawType::Color => MeshDrawType::Color,
+                TessDrawType::Gradient { matrix, gradient } => {
+                    MeshDrawType::Gradient(Box::new(Gradient::new(
+                        lyon_mesh.gradients[gradient].clone(), // TODO: Gradient deduplication
+                        matrix,
+                    )))
+                }
+                TessDrawType::Bitmap(bitmap) => MeshDrawType::Bitmap {
+                    matrix: bitmap.matrix,
+                    bitmap_id: bitmap.bitmap_id,
+                    is_smoothed: bitmap.is_smoothed,
+                    is_repeating: bitmap.is_repeating,
+                },
+            },
+            vertices: draw.vertices.into_iter().map(Vertex::from).collect(),
+            indices: draw.indices,
+            num_mask_indices: draw.mask_index_count,
+        })
+        .collect();
+
+    if let (Some(shape_cache), Some(key)) = (shape_cache, cache_key) {
+        if let Ok(mut shape_cache) = shape_cache.lock() {
+            shape_cache.insert(key, &mesh);
+        }
+    }
+    mesh
+}
+
+/// Answers the tessellator's bitmap size queries on a worker thread.
+struct BitmapSizes(HashMap<CharacterId, BitmapSize>);
+
+impl BitmapSource for BitmapSizes {
+    fn bitmap_size(&self, id: CharacterId) -> Option<BitmapSize> {
+        self.0.get(&id).cloned()
+    }
+
+    fn bitmap_handle(
+        &self,
+        _id: CharacterId,
+        _renderer: &mut dyn RenderBackend,
+    ) -> Option<BitmapHandle> {
+        None
+    }
+}
@@ -0,0 +1,97 @@
+//! A small thread pool for the CPU-heavy parts of registering shapes and bitmaps.
+//!
+//! Only the results are handed back to the GL thread, which uploads them once they are needed
+//! and ready.
+
+use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::{Arc, Mutex};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// Stack size of the worker threads. The Vita's default is too small for the tessellator.
+const WORKER_STACK_SIZE: usize = 1024 * 1024;
+
+pub struct WorkerPool {
+    sender: Option<Sender<Job>>,
+}
+
+impl WorkerPool {
+    /// Spawns one worker per core, leaving one core to the main thread.
+    pub fn new() -> Self {
+        let num_workers = std::thread::available_parallelism()
+            .map(|n| n.get().saturating_sub(1))
+            .unwrap_or(2)
+            .max(1);
+
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        let mut spawned = 0;
+        for i in 0..num_workers {
+            let receiver = receiver.clone();
+            let result = std::thread::Builder::new()
+                .name(format!("glow worker {i}"))
+                .stack_size(WORKER_STACK_SIZE)
+                .spawn(move || loop {
+                    let job = match receiver.lock() {
+                        Ok(receiver) => receiver.recv(),
+                        Err(_) => break,
+                    };
+                    match job {
+                        Ok(job) => job(),
+                        Err(_) => break,
+                    }
+                });
+            match result {
+                Ok(_) => spawned += 1,
+                Err(e) => log::warn!("Couldn't spawn worker thread: {e}"),
+            }
+        }
+
+        log::info!("Using {spawned} worker threads");
+        Self {
+            // Without any workers, jobs just run on the calling thread.
+            sender: (spawned > 0).then_some(sender),
+        }
+    }
+
+    /// Runs `job` on a worker thread.
+    pub fn spawn<T: Send + 'static>(&self, job: impl FnOnce() -> T + Send + 'static) -> Pending<T> {
+        let (result_sender, result_receiver) = channel();
+        let job = move || {
+            let _ = result_sender.send(job());
+        };
+        match &self.sender {
+            Some(sender) => {
+                if let Err(e) = sender.send(Box::new(job)) {
+                    (e.0)();
+                }
+            }
+            None => job(),
+        }
+        Pending(result_receiver)
+    }
+}
+
+/// The result of a job that may still be running.
+pub struct Pending<T>(Receiver<T>);
+
+impl<T> std::fmt::Debug for Pending<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Pending").finish()
+    }
+}
+
+impl<T> Pending<T> {
+    /// Blocks until the job has finished. Returns `None` if the job panicked, which only
+    /// happens where panics unwind: the Vita and Switch profiles use `panic = "abort"`, so a
+    /// panicking job takes the whole process down there.
+    pub fn wait(self) -> Option<T> {
+        self.0.recv().ok()
+    }
+
+    /// Returns the result if the job has finished, without blocking. Fails with
+    /// `TryRecvError::Disconnected` if the job panicked, on the same terms as [`Pending::wait`].
+    pub fn try_wait(&self) -> Result<T, TryRecvError> {
+        self.0.try_recv()
+    }
+}
@@ -0,0 +1,239 @@
+//! Device fonts loaded from TTF, OTF and TTC files in the `fonts` directory.
+//!
+//! Only the names and styles of the faces are kept around until the player asks for one of them.
+//! Each font file is then read once, and its data shared by every face registered from it.
+
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use ruffle_core::FontQuery;
+use ruffle_core::backend::ui::FontDefinition;
+use ruffle_core::font::{FontFileData, FontType};
+use ttf_parser::{Face, name_id};
+
+const FONT_EXTENSIONS: [&str; 4] = ["ttf", "otf", "ttc", "otc"];
+
+/// Characters used to tell whether a face covers Chinese, Japanese or Korean text.
+const CJK_PROBES: [char; 3] = ['漢', 'あ', '한'];
+
+struct FontFace {
+    family: String,
+    is_bold: bool,
+    is_italic: bool,
+    path: PathBuf,
+    index: u32,
+    is_cjk: bool,
+}
+
+pub struct FontDatabase {
+    faces: Vec<FontFace>,
+    /// Families used for a font name, such as the generic `_sans`, in order of preference.
+    aliases: HashMap<String, Vec<String>>,
+    /// Families searched, in order, for characters missing from a font.
+    fallbacks: Vec<String>,
+    /// The data of the font files read so far.
+    files: RefCell<HashMap<PathBuf, FontFileData>>,
+    /// The name, boldness and italicness of every font registered with the player, which keeps
+    /// them for as long as it runs.
+    registered: RefCell<HashSet<(String, bool, bool)>>,
+}
+
+impl FontDatabase {
+    /// Loads every face in `dir`. Without configured `fallbacks`, every family is a fallback,
+    /// with the ones covering CJK text last.
+    pub fn load(
+        dir: &Path,
+        aliases: HashMap<String, Vec<String>>,
+        fallbacks: Option<Vec<String>>,
+    ) -> Self {
+        let mut paths: Vec<PathBuf> = match std::fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.extension()
+                        .and_then(|extension| extension.to_str())
+                        .is_some_and(|extension| {
+                            FONT_EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str())
+                        })
+                })
+                .collect(),
+            Err(e) => {
+                tracing::info!("No device fonts loaded from {}: {}", dir.display(), e);
+                vec![]
+            }
+        };
+        paths.sort();
+
+        let mut faces = vec![];
+        for path in paths {
+            let data = match std::fs::read(&path) {
+                Ok(data) => data,
+                Err(e) => {
+                    tracing::warn!("Unable to read font {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let num_faces = ttf_parser::fonts_in_collection(&data).unwrap_or(1);
+            for index in 0..num_faces {
+                match Face::parse(&data, index) {
+                    Ok(face) => {
+                        if let Some(family) = family_name(&face) {
+                            faces.push(FontFace {
+                                family,
+                                is_bold: face.is_bold(),
+                                is_italic: face.is_italic(),
+                                path: path.clone(),
+                                index,
+                                is_cjk: CJK_PROBES.iter().any(|&c| face.glyph_index(c).is_some()),
+                            });
+                        }
+                    }
+                    Err(e) => {
+                        tracing::warn!("Unable to parse font {}: {}", path.display(), e);
+                    }
+                }
+            }
+        }
+        tracing::info!("Loaded {} device font faces", faces.len());
+
+        let fallbacks = fallbacks.unwrap_or_else(|| {
+            let mut families: Vec<String> = vec![];
+            for is_cjk in [false, true] {
+                for face in faces.iter().filter(|face| face.is_cjk == is_cjk) {
+                    if !families.contains(&face.family) {
+                        families.push(face.family.clone());
+                    }
+                }
+            }
+            families
+        });
+
+        Self {
+            faces,
+            aliases,
+            fallbacks,
+            files: RefCell::default(),
+            registered: RefCell::default(),
+        }
+    }
+
+    /// The families to try for `name`, in order of preference.
+    fn families<'a>(&'a self, name: &'a str) -> Vec<&'a str> {
+        match self.aliases.get(name) {
+            Some(families) => families.iter().map(String::as_str).collect(),
+            None => vec![name],
+        }
+    }
+
+    /// Finds the face of `family` closest to the requested style.
+    fn find(&self, family: &str, is_bold: bool, is_italic: bool) -> Option<&FontFace> {
+        self.faces
+            .iter()
+            .filter(|face| face.family.eq_ignore_ascii_case(family))
+            .max_by_key(|face| {
+                // Italic matters more than weight, since a fake oblique looks worse.
+                (face.is_italic == is_italic) as u8 * 2 + (face.is_bold == is_bold) as u8
+            })
+    }
+
+    /// Registers `face` with the player as `name`, unless that was done already. Returns
+    /// whether the font is available.
+    fn register(
+        &self,
+        face: &FontFace,
+        name: String,
+        is_bold: bool,
+        is_italic: bool,
+        register: &mut dyn FnMut(FontDefinition),
+    ) -> bool {
+        let key = (name, is_bold, is_italic);
+        if self.registered.borrow().contains(&key) {
+            return true;
+        }
+        let data = match self.files.borrow_mut().entry(face.path.clone()) {
+            Entry::Occupied(entry) => entry.get().clone(),
+            Entry::Vacant(entry) => match std::fs::read(&face.path) {
+                Ok(data) => entry.insert(FontFileData::new(data)).clone(),
+                Err(e) => {
+                    tracing::warn!("Unable to read font {}: {}", face.path.display(), e);
+                    return false;
+                }
+            },
+        };
+        register(FontDefinition::FontFile {
+            name: key.0.clone(),
+            is_bold,
+            is_italic,
+            data,
+            index: face.index,
+        });
+        self.registered.borrow_mut().insert(key);
+        true
+    }
+
+    pub fn load_device_font(&self, query: &FontQuery, register: &mut dyn FnMut(FontDefinition)) {
+        for family in self.families(&query.name) {
+            if let Some(face) = self.find(family, query.is_bold, query.is_italic) {
+                if self.register(
+                    face,
+                    query.name.clone(),
+                    query.is_bold,
+                    query.is_italic,
+                    register,
+                ) {
+                    return;
+                }
+            }
+        }
+    }
+
+    pub fn sort_device_fonts(
+        &self,
+        query: &FontQuery,
+        register: &mut dyn FnMut(FontDefinition),
+    ) -> Vec<FontQuery> {
+        let mut families: Vec<&str> = vec![];
+        for family in self
+            .families(&query.name)
+            .into_iter()
+            .chain(self.fallbacks.iter().map(String::as_str))
+        {
+            if !families
+                .iter()
+                .any(|existing| existing.eq_ignore_ascii_case(family))
+            {
+                families.push(family);
+            }
+        }
+
+        let mut sorted = vec![];
+        for family in families {
+            if let Some(face) = self.find(family, query.is_bold, query.is_italic) {
+                let name = face.family.clone();
+                if self.register(face, name.clone(), query.is_bold, query.is_italic, register) {
+                    sorted.push(FontQuery::new(
+                        FontType::Device,
+                        name,
+                        query.is_bold,
+                        query.is_italic,
+                    ));
+                }
+            }
+        }
+        sorted
+    }
+}
+
+/// Returns the face's family name, preferring the typographic family that groups all weights.
+fn family_name(face: &Face) -> Option<String> {
+    [name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY]
+        .into_iter()
+        .find_map(|id| {
+            face.names()
+                .into_iter()
+                .filter(|name| name.name_id == id)
+                .find_map(|name| name.to_string())
+        })
+}
@@ -1,4 +1,6 @@
 pub mod audio;
+pub mod fonts;
 pub mod log;
 pub mod storage;
 pub mod ui;
+pub mod video;
@@ -1,4 +1,7 @@
 use std::boxed::Box;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU8, Ordering};
 
 use ruffle_core::FontQuery;
 
@@ -14,31 +17,136 @@ use sdl2::video::FullscreenType;
 use sdl2::video::Window;
 use url::Url;
 
-/// UiBackend that does nothing.
+use super::fonts::FontDatabase;
+
+/// UiBackend that does little besides fullscreen, the clipboard, the text input and device fonts.
 pub struct SdlUiBackend {
     window: Box<Window>,
+    fonts: FontDatabase,
+    mouse_visible: bool,
+    /// Where the virtual keyboard is opened and closed, when the frontend draws its own.
+    keyboard_requests: Option<KeyboardRequests>,
+    #[cfg(any(target_os = "horizon", target_os = "vita"))]
+    clipboard: SessionClipboard,
 }
 
 impl SdlUiBackend {
-    pub fn new(window: Box<Window>) -> Self {
-        Self { window }
+    /// `clipboard_path` is where the clipboard is saved on consoles, which have no clipboard of
+    /// their own. With `keyboard_requests`, the virtual keyboard is left to the frontend instead
+    /// of SDL.
+    pub fn new(
+        window: Box<Window>,
+        fonts: FontDatabase,
+        clipboard_path: Option<PathBuf>,
+        keyboard_requests: Option<KeyboardRequests>,
+    ) -> Self {
+        // Other platforms keep the system clipboard, which outlives the player anyway.
+        #[cfg(not(any(target_os = "horizon", target_os = "vita")))]
+        let _ = clipboard_path;
+        Self {
+            window,
+            fonts,
+            mouse_visible: true,
+            keyboard_requests,
+            #[cfg(any(target_os = "horizon", target_os = "vita"))]
+            clipboard: SessionClipboard::new(clipboard_path),
+        }
+    }
+}
+
+/// The latest request from the player to open or close the virtual keyboard, for the main loop
+/// to pick up.
+#[derive(Debug, Clone, Default)]
+pub struct KeyboardRequests(Arc<AtomicU8>);
+
+impl KeyboardRequests {
+    const NONE: u8 = 0;
+    const OPEN: u8 = 1;
+    const CLOSE: u8 = 2;
+
+    /// Whether the keyboard was asked to open or to close since the last call.
+    pub fn take(&self) -> Option<bool> {
+        match self.0.swap(Self::NONE, Ordering::Relaxed) {
+            Self::OPEN => Some(true),
+            Self::CLOSE => Some(false),
+            _ => None,
+        }
+    }
+
+    fn request(&self, open: bool) {
+        let request = if open { Self::OPEN } else { Self::CLOSE };
+        self.0.store(request, Ordering::Relaxed);
+    }
+}
+
+/// A clipboard that lasts for the session, or across sessions when it is saved to a file.
+#[cfg(any(target_os = "horizon", target_os = "vita"))]
+struct SessionClipboard {
+    content: String,
+    path: Option<PathBuf>,
+}
+
+#[cfg(any(target_os = "horizon", target_os = "vita"))]
+impl SessionClipboard {
+    fn new(path: Option<PathBuf>) -> Self {
+        let content = path
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .unwrap_or_default();
+        Self { content, path }
+    }
+
+    fn set(&mut self, content: String) {
+        if let Some(path) = &self.path {
+            if let Err(e) = std::fs::write(path, &content) {
+                tracing::warn!("Unable to save the clipboard to {}: {}", path.display(), e);
+            }
+        }
+        self.content = content;
     }
 }
 
 impl UiBackend for SdlUiBackend {
     fn mouse_visible(&self) -> bool {
-        true
+        self.mouse_visible
     }
 
-    fn set_mouse_visible(&mut self, _visible: bool) {}
+    fn set_mouse_visible(&mut self, visible: bool) {
+        self.mouse_visible = visible;
+    }
 
     fn set_mouse_cursor(&mut self, _cursor: MouseCursor) {}
 
+    #[cfg(not(any(target_os = "horizon", target_os = "vita")))]
+    fn clipboard_content(&mut self) -> String {
+        self.window
+            .subsystem()
+            .clipboard()
+            .clipboard_text()
+            .unwrap_or_default()
+    }
+
+    #[cfg(any(target_os = "horizon", target_os = "vita"))]
     fn clipboard_content(&mut self) -> String {
-        "".into()
+        self.clipboard.content.clone()
+    }
+
+    #[cfg(not(any(target_os = "horizon", target_os = "vita")))]
+    fn set_clipboard_content(&mut self, content: String) {
+        if let Err(e) = self
+            .window
+            .subsystem()
+            .clipboard()
+            .set_clipboard_text(&content)
+        {
+            tracing::warn!("Unable to set the clipboard: {}", e);
+        }
     }
 
-    fn set_clipboard_content(&mut self, _content: String) {}
+    #[cfg(any(target_os = "horizon", target_os = "vita"))]
+    fn set_clipboard_content(&mut self, content: String) {
+        self.clipboard.set(content);
+    }
 
     fn set_fullscreen(&mut self, is_full: bool) -> Result<(), FullscreenError> {
         //if is_full {
@@ -56,24 +164,34 @@ impl UiBackend for SdlUiBackend {
 
     fn message(&self, _message: &str) {}
 
-    fn display_unsupported_video(&self, _url: Url) {}
+    fn display_unsupported_video(&self, url: Url) {
+        tracing::warn!("Unsupported video: {}", url);
+    }
 
-    fn load_device_font(&self, _query: &FontQuery, _register: &mut dyn FnMut(FontDefinition)) {}
+    fn load_device_font(&self, query: &FontQuery, register: &mut dyn FnMut(FontDefinition)) {
+        self.fonts.load_device_font(query, register);
+    }
 
     fn sort_device_fonts(
         &self,
-        _query: &FontQuery,
-        _register: &mut dyn FnMut(FontDefinition),
+        query: &FontQuery,
+        register: &mut dyn FnMut(FontDefinition),
     ) -> Vec<FontQuery> {
-        Vec::new()
+        self.fonts.sort_device_fonts(query, register)
     }
 
     fn open_virtual_keyboard(&self) {
-        self.window.subsystem().text_input().start();
+        match &self.keyboard_requests {
+            Some(requests) => requests.request(true),
+            None => self.window.subsystem().text_input().start(),
+        }
     }
 
     fn close_virtual_keyboard(&self) {
-        self.window.subsystem().text_input().stop();
+        match &self.keyboard_requests {
+            Some(requests) => requests.request(false),
+            None => self.window.subsystem().text_input().stop(),
+        }
     }
 
     fn language(&self) -> LanguageIdentifier {
@@ -0,0 +1,328 @@
+//! Software video decoding on a pool of threads.
+//!
+//! Each stream decodes its frames in order on whichever thread gets to it first, while the
+//! player carries on. The player is handed the newest decoded frame, so when decoding falls
+//! behind, frames that were superseded before being shown are never uploaded, and a keyframe
+//! throws away the frames still queued before it.
+//!
+//! Frames stay in the decoder's output format, which the renderer converts.
+
+use std::collections::{HashSet, VecDeque};
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::sync::mpsc::{Sender, channel};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use ruffle_core::swf::{VideoCodec, VideoDeblocking};
+use ruffle_render::backend::RenderBackend;
+use ruffle_render::bitmap::{Bitmap, BitmapHandle, BitmapInfo, PixelRegion};
+use ruffle_video::VideoStreamHandle;
+use ruffle_video::backend::VideoBackend;
+use ruffle_video::error::Error;
+use ruffle_video::frame::{EncodedFrame, FrameDependency};
+use ruffle_video_software::decoder::VideoDecoder;
+use ruffle_video_software::decoder::h263::H263Decoder;
+use ruffle_video_software::decoder::screen::ScreenVideoDecoder;
+use ruffle_video_software::decoder::vp6::Vp6Decoder;
+use slotmap::SlotMap;
+
+/// How long the player waits for the frame it asked for before showing an older one.
+const DECODE_WAIT: Duration = Duration::from_millis(8);
+
+/// How long the player waits for the first frame of a stream, which it has nothing to show
+/// in place of.
+const FIRST_FRAME_WAIT: Duration = Duration::from_secs(1);
+
+type Job = Box<dyn FnOnce() + Send>;
+
+struct OwnedFrame {
+    codec: VideoCodec,
+    data: Vec<u8>,
+    frame_id: u32,
+}
+
+#[derive(Default)]
+struct StreamState {
+    queue: VecDeque<OwnedFrame>,
+    /// The newest decoded frame that hasn't been uploaded yet.
+    latest: Option<Bitmap<'static>>,
+    last_decoded_id: Option<u32>,
+    last_error: Option<String>,
+    /// Why the stream can't decode any more frames, if its decoder panicked.
+    failure: Option<String>,
+}
+
+/// The part of a stream shared with the decoding threads.
+struct SharedStream {
+    decoder: Mutex<Box<dyn VideoDecoder + Send>>,
+    state: Mutex<StreamState>,
+    decoded: Condvar,
+}
+
+impl SharedStream {
+    /// Locks the state. Nothing panics while holding it, so it's still whole if poisoned.
+    fn state(&self) -> MutexGuard<'_, StreamState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Gives up on the stream, waking the player so that it doesn't wait for frames that will
+    /// never be decoded.
+    fn fail(&self, failure: String) {
+        tracing::error!("Video decoder failed: {}", failure);
+        let mut state = self.state();
+        state.queue.clear();
+        state.failure = Some(failure);
+        self.decoded.notify_all();
+    }
+
+    /// Decodes every queued frame.
+    fn decode_queued(&self) {
+        // Holding the decoder keeps the frames in order when several threads pick up the stream.
+        let Ok(mut decoder) = self.decoder.lock() else {
+            self.fail("the decoder panicked earlier".to_string());
+            return;
+        };
+        loop {
+            let Some(frame) = self.state().queue.pop_front() else {
+                return;
+            };
+            // A panic would otherwise leave the player waiting for this frame. The Vita and
+            // Switch profiles abort on panic, so there's nothing to catch there.
+            let result = catch_unwind(AssertUnwindSafe(|| {
+                decoder.decode_frame(EncodedFrame {
+                    codec: frame.codec,
+                    data: &frame.data,
+                    frame_id: frame.frame_id,
+                })
+            }));
+            let Ok(result) = result else {
+                // The decoder may be left half-way through a frame, so it can't be trusted again.
+                self.fail(format!("the decoder panicked on frame {}", frame.frame_id));
+                return;
+            };
+
+            let mut state = self.state();
+            match result {
+                Ok(decoded) => state.latest = Some(decoded),
+                Err(e) => {
+                    tracing::warn!("Couldn't decode video frame {}: {}", frame.frame_id, e);
+                    state.last_error = Some(e.to_string());
+                }
+            }
+            state.last_decoded_id = Some(frame.frame_id);
+            self.decoded.notify_all();
+        }
+    }
+}
+
+struct Stream {
+    shared: Arc<SharedStream>,
+    keyframes: HashSet<u32>,
+    bitmap: Option<BitmapInfo>,
+}
+
+pub struct ThreadedVideoBackend {
+    streams: SlotMap<VideoStreamHandle, Stream>,
+    sender: Sender<Job>,
+}
+
+impl ThreadedVideoBackend {
+    pub fn new(num_threads: u32) -> Self {
+        let (sender, receiver) = channel::<Job>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for i in 0..num_threads.max(1) {
+            let receiver = receiver.clone();
+            let result = std::thread::Builder::new()
+                .name(format!("video decoder {}", i))
+                .spawn(move || {
+                    loop {
+                        let job = match receiver.lock() {
+                            Ok(receiver) => receiver.recv(),
+                            Err(_) => break,
+                        };
+                        match job {
+                            Ok(job) => job(),
+                            Err(_) => break,
+                        }
+                    }
+                });
+            if let Err(e) = result {
+                tracing::warn!("Unable to spawn video decoder thread {}", e);
+            }
+        }
+
+        Self {
+            streams: SlotMap::with_key(),
+            sender,
+        }
+    }
+
+    fn upload(
+        frame: Bitmap<'static>,
+        bitmap: Option<&BitmapHandle>,
+        renderer: &mut dyn RenderBackend,
+    ) -> Result<BitmapInfo, Error> {
+        let (width, height) = (frame.width(), frame.height());
+        let handle = match bitmap {
+            Some(bitmap) => {
+                renderer.update_texture(
+                    bitmap,
+                    frame,
+                    PixelRegion::for_whole_size(width, height),
+                )?;
+                bitmap.clone()
+            }
+            None => renderer.register_bitmap(frame)?,
+        };
+        Ok(BitmapInfo {
+            handle,
+            width: width as u16,
+            height: height as u16,
+        })
+    }
+}
+
+impl VideoBackend for ThreadedVideoBackend {
+    fn register_video_stream(
+        &mut self,
+        _num_frames: u32,
+        size: (u16, u16),
+        codec: VideoCodec,
+        filter: VideoDeblocking,
+    ) -> Result<VideoStreamHandle, Error> {
+        let decoder: Box<dyn VideoDecoder + Send> = match codec {
+            VideoCodec::H263 => Box::new(H263Decoder::new(filter)),
+            VideoCodec::Vp6 => Box::new(Vp6Decoder::new(false, size)),
+            VideoCodec::Vp6WithAlpha => Box::new(Vp6Decoder::new(true, size)),
+            VideoCodec::ScreenVideo => Box::new(ScreenVideoDecoder::new()),
+            _ => return Err(Error::UnsupportedCodec(codec)),
+        };
+        Ok(self.streams.insert(Stream {
+            shared: Arc::new(SharedStream {
+                decoder: Mutex::new(decoder),
+                state: Mutex::new(StreamState::default()),
+                decoded: Condvar::new(),
+            }),
+            keyframes: HashSet::new(),
+            bitmap: None,
+        }))
+    }
+
+    fn configure_video_stream_decoder(
+        &mut self,
+        stream: VideoStreamHandle,
+        configuration_data: &[u8],
+    ) -> Result<(), Error> {
+        let stream = self
+            .streams
+            .get_mut(stream)
+            .ok_or(Error::VideoStreamIsNotRegistered)?;
+        match stream.shared.decoder.lock() {
+            Ok(mut decoder) => decoder.configure_decoder(configuration_data),
+            Err(_) => Err(Error::DecoderError(
+                "the decoder panicked earlier".to_string().into(),
+            )),
+        }
+    }
+
+    fn preload_video_stream_frame(
+        &mut self,
+        stream: VideoStreamHandle,
+        encoded_frame: EncodedFrame<'_>,
+    ) -> Result<FrameDependency, Error> {
+        let stream = self
+            .streams
+            .get_mut(stream)
+            .ok_or(Error::VideoStreamIsNotRegistered)?;
+        let frame_id = encoded_frame.frame_id;
+        let dependency = match stream.shared.decoder.lock() {
+            Ok(mut decoder) => decoder.preload_frame(encoded_frame)?,
+            Err(_) => {
+                return Err(Error::DecoderError(
+                    "the decoder panicked earlier".to_string().into(),
+                ));
+            }
+        };
+        // Remembered so that frames queued before a keyframe can be dropped.
+        if matches!(dependency, FrameDependency::None) {
+            stream.keyframes.insert(frame_id);
+        }
+        Ok(dependency)
+    }
+
+    fn decode_video_stream_frame(
+        &mut self,
+        stream: VideoStreamHandle,
+        encoded_frame: EncodedFrame<'_>,
+        renderer: &mut dyn RenderBackend,
+    ) -> Result<BitmapInfo, Error> {
+        let stream = self
+            .streams
+            .get_mut(stream)
+            .ok_or(Error::VideoStreamIsNotRegistered)?;
+        let shared = &stream.shared;
+        let frame_id = encoded_frame.frame_id;
+
+        {
+            let mut state = shared.state();
+            if let Some(failure) = &state.failure {
+                return Err(Error::DecoderError(failure.clone().into()));
+            }
+            if stream.keyframes.contains(&frame_id) {
+                state.queue.clear();
+            }
+            state.queue.push_back(OwnedFrame {
+                codec: encoded_frame.codec,
+                data: encoded_frame.data.to_vec(),
+                frame_id,
+            });
+        }
+        let job_stream = shared.clone();
+        if let Err(e) = self
+            .sender
+            .send(Box::new(move || job_stream.decode_queued()))
+        {
+            // Without any decoder threads, decode right away.
+            (e.0)();
+        }
+
+        // Give the decoder a moment to catch up, but there has to be something to show at first.
+        let has_bitmap = stream.bitmap.is_some();
+        let wait = if has_bitmap {
+            DECODE_WAIT
+        } else {
+            FIRST_FRAME_WAIT
+        };
+        let (mut state, _) = shared
+            .decoded
+            .wait_timeout_while(shared.state(), wait, |state| {
+                state.failure.is_none()
+                    && state.last_decoded_id != Some(frame_id)
+                    && (has_bitmap || state.latest.is_none())
+            })
+            .unwrap_or_else(PoisonError::into_inner);
+
+        if let Some(failure) = &state.failure {
+            return Err(Error::DecoderError(failure.clone().into()));
+        }
+        let latest = state.latest.take();
+        let last_error = state.last_error.take();
+        drop(state);
+
+        match latest {
+            Some(frame) => {
+                let bitmap = stream.bitmap.as_ref().map(|bitmap| &bitmap.handle);
+                let bitmap = Self::upload(frame, bitmap, renderer)?;
+                stream.bitmap = Some(bitmap.clone());
+                Ok(bitmap)
+            }
+            None => match (&stream.bitmap, last_error) {
+                (Some(bitmap), _) => Ok(bitmap.clone()),
+                (None, Some(error)) => Err(Error::DecoderError(error.into())),
+                (None, None) => Err(Error::DecoderError(
+                    format!("frame {frame_id} took too long to decode").into(),
+                )),
+            },
+        }
+    }
+}
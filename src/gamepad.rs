@@ -0,0 +1,102 @@
+//! Controller inputs that Ruffle has no gamepad button for.
+//!
+//! The stick clicks, the guide button, the paddles and the touchpad click are mapped straight to
+//! keys by the frontend. The analog triggers become `LeftTrigger2` and `RightTrigger2` once they
+//! are pulled far enough, and the right stick can stand in for the d-pad or the mouse.
+
+use std::str::FromStr;
+
+use ruffle_core::PlayerEvent;
+use ruffle_core::events::{GamepadButton, KeyCode, KeyLocation};
+use sdl2::controller::{Axis, Button};
+
+/// Looks up a controller button that Ruffle doesn't know, by its name in `gamepad_config`.
+pub fn extra_button_from_str(name: &str) -> Option<Button> {
+    match name {
+        "LeftStick" => Some(Button::LeftStick),
+        "RightStick" => Some(Button::RightStick),
+        "Guide" => Some(Button::Guide),
+        "Misc1" => Some(Button::Misc1),
+        "Paddle1" => Some(Button::Paddle1),
+        "Paddle2" => Some(Button::Paddle2),
+        "Paddle3" => Some(Button::Paddle3),
+        "Paddle4" => Some(Button::Paddle4),
+        "Touchpad" => Some(Button::Touchpad),
+        _ => None,
+    }
+}
+
+/// The key event for a controller button mapped to a key.
+pub fn key_event(key_code: KeyCode, pressed: bool) -> PlayerEvent {
+    if pressed {
+        PlayerEvent::KeyDown {
+            key_code,
+            key_char: None,
+            key_location: KeyLocation::Standard,
+        }
+    } else {
+        PlayerEvent::KeyUp {
+            key_code,
+            key_char: None,
+            key_location: KeyLocation::Standard,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RightStickMode {
+    #[default]
+    None,
+    DPad,
+    Mouse,
+}
+
+impl FromStr for RightStickMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "none" => Ok(RightStickMode::None),
+            "dpad" => Ok(RightStickMode::DPad),
+            "mouse" => Ok(RightStickMode::Mouse),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Turns the analog triggers into buttons.
+#[derive(Debug, Default)]
+pub struct TriggerState {
+    left: bool,
+    right: bool,
+}
+
+impl TriggerState {
+    /// Takes the motion of a trigger, returning its button and whether it's now pressed if that
+    /// changed. Returns `None` for the other axes too.
+    pub fn axis_motion(
+        &mut self,
+        axis: Axis,
+        value: i16,
+        threshold: i16,
+    ) -> Option<(GamepadButton, bool)> {
+        let (pressed, button) = match axis {
+            Axis::TriggerLeft => (&mut self.left, GamepadButton::LeftTrigger2),
+            Axis::TriggerRight => (&mut self.right, GamepadButton::RightTrigger2),
+            _ => return None,
+        };
+        let is_pressed = value > threshold;
+        if is_pressed == *pressed {
+            return None;
+        }
+        *pressed = is_pressed;
+        Some((button, is_pressed))
+    }
+
+    /// Releases the pressed triggers, returning their buttons.
+    pub fn release(&mut self) -> Vec<GamepadButton> {
+        let left = std::mem::take(&mut self.left).then_some(GamepadButton::LeftTrigger2);
+        let right = std::mem::take(&mut self.right).then_some(GamepadButton::RightTrigger2);
+        left.into_iter().chain(right).collect()
+    }
+}
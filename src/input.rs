@@ -0,0 +1,508 @@
+//! What controller buttons do, as mapped in `gamepad_config` and `player_gamepad_config`.
+//!
+//! A button can press one or more keys together, given by name or by Flash key code, or click the
+//! mouse. It can also press its keys over and over while held, as turbo, or play a macro of timed
+//! steps. Buttons that aren't mapped are passed on to the player as they are. Each player can map
+//! buttons of their own over `gamepad_config`, so that two controllers can press different keys.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::Duration;
+
+use ruffle_core::events::{GamepadButton, KeyCode, MouseButton};
+use serde::Deserialize;
+
+use crate::gamepad;
+
+/// A key or mouse button in `gamepad_config`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ActionConfig {
+    Code(u32),
+    Name(String),
+}
+
+/// Keys or mouse buttons in `gamepad_config`, pressed together.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum KeysConfig {
+    One(ActionConfig),
+    Many(Vec<ActionConfig>),
+}
+
+/// What a controller button is mapped to in `gamepad_config`.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum BindingConfig {
+    Keys(KeysConfig),
+    /// Presses the keys `rate` times a second while the button is held.
+    Turbo {
+        turbo: KeysConfig,
+        rate: Option<u32>,
+    },
+    /// Plays the steps one after the other when the button is pressed.
+    Macro {
+        sequence: Vec<StepConfig>,
+    },
+}
+
+/// A step of a macro, holding `key` for `time` milliseconds, or just waiting without one.
+#[derive(Debug, Deserialize)]
+pub struct StepConfig {
+    key: Option<KeysConfig>,
+    time: Option<u32>,
+}
+
+/// Default number of times a second turbo presses its keys.
+const DEFAULT_TURBO_RATE: u32 = 10;
+
+/// Default length of a macro step, in milliseconds.
+const DEFAULT_STEP_TIME: u32 = 100;
+
+/// Something a controller button can do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Key(KeyCode),
+    Mouse(MouseButton),
+}
+
+/// What a controller button does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Binding {
+    /// Holds the keys and mouse buttons while the button is.
+    Hold(Vec<Action>),
+    /// Presses and releases the actions every `period` while the button is held.
+    Turbo {
+        actions: Vec<Action>,
+        period: Duration,
+    },
+    /// Plays the steps once when the button is pressed.
+    Macro(Vec<Step>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub actions: Vec<Action>,
+    pub time: Duration,
+}
+
+/// A controller button, either one Ruffle knows or one only the frontend does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Input {
+    Gamepad(GamepadButton),
+    Extra(sdl2::controller::Button),
+}
+
+impl FromStr for Input {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match gamepad::extra_button_from_str(s) {
+            Some(button) => Ok(Input::Extra(button)),
+            None => GamepadButton::from_str(s)
+                .map(Input::Gamepad)
+                .map_err(|_| ()),
+        }
+    }
+}
+
+/// What an on-screen control or a touchpad region presses.
+#[derive(Debug, Clone)]
+pub enum Target {
+    /// A controller button, which does what it's mapped to.
+    Button(Input),
+    /// Keys and mouse buttons of its own.
+    Binding(Binding),
+}
+
+impl Target {
+    /// Reads the `button` or `key` option of `what`, printing why they can't be used if so.
+    pub fn from_config(
+        button: Option<String>,
+        key: Option<BindingConfig>,
+        what: &str,
+    ) -> Option<Self> {
+        match (button, key) {
+            (Some(button), None) => match Input::from_str(&button) {
+                Ok(input) => Some(Target::Button(input)),
+                Err(_) => {
+                    println!("{} has unknown controller button \"{}\"", what, button);
+                    None
+                }
+            },
+            (None, Some(key)) => binding_from_config(key, what).map(Target::Binding),
+            _ => {
+                println!("{} needs either a button or a key", what);
+                None
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ButtonMapping {
+    bindings: HashMap<Input, Binding>,
+}
+
+impl ButtonMapping {
+    /// Reads `gamepad_config`, printing every entry that can't be used and leaving it out.
+    pub fn from_config(config: HashMap<String, ron::Value>) -> Self {
+        let mut mapping = Self::default();
+        mapping.insert_config(config, "gamepad_config");
+        mapping
+    }
+
+    pub fn binding(&self, input: Input) -> Option<&Binding> {
+        self.bindings.get(&input)
+    }
+
+    /// Adds the entries of `config`, a mapping in `what`, over the ones already there.
+    fn insert_config(&mut self, config: HashMap<String, ron::Value>, what: &str) {
+        for (button, value) in config {
+            let Ok(input) = Input::from_str(&button) else {
+                println!("{}: unknown controller button \"{}\"", what, button);
+                continue;
+            };
+            let binding = match value.into_rust::<BindingConfig>() {
+                Ok(binding) => binding,
+                Err(e) => {
+                    println!(
+                        "{}: \"{}\": expected keys, a turbo or a macro: {}",
+                        what, button, e
+                    );
+                    continue;
+                }
+            };
+            if let Some(binding) = binding_from_config(binding, &format!("{}: {}", what, button)) {
+                self.bindings.insert(input, binding);
+            }
+        }
+    }
+}
+
+/// The mapping of each player's controller.
+#[derive(Debug, Default)]
+pub struct PlayerMappings {
+    shared: ButtonMapping,
+    players: Vec<ButtonMapping>,
+}
+
+impl PlayerMappings {
+    /// Reads `gamepad_config`, shared by every player, and `player_gamepad_config`, with the
+    /// entries of each player over it in player order.
+    pub fn from_config(
+        shared: HashMap<String, ron::Value>,
+        players: Vec<HashMap<String, ron::Value>>,
+    ) -> Self {
+        let shared = ButtonMapping::from_config(shared);
+        let players = players
+            .into_iter()
+            .enumerate()
+            .map(|(index, config)| {
+                let mut mapping = shared.clone();
+                mapping.insert_config(
+                    config,
+                    &format!("player_gamepad_config: player {}", index + 1),
+                );
+                mapping
+            })
+            .collect();
+        Self { shared, players }
+    }
+
+    /// The mapping of the player at `index`, counting from 0.
+    pub fn player(&self, index: usize) -> &ButtonMapping {
+        self.players.get(index).unwrap_or(&self.shared)
+    }
+
+    /// How many players have mappings of their own.
+    pub fn player_count(&self) -> usize {
+        self.players.len()
+    }
+}
+
+/// Reads what something is mapped to, printing the keys that don't exist as mapped by `what`
+/// and leaving them out. Returns `None` if nothing is left.
+pub fn binding_from_config(binding: BindingConfig, what: &str) -> Option<Binding> {
+    let binding = match binding {
+        BindingConfig::Keys(keys) => Binding::Hold(actions_from_config(keys, what)),
+        BindingConfig::Turbo { turbo, rate } => {
+            let rate = match rate {
+                Some(0) => {
+                    println!(
+                        "{} has a turbo rate of 0, using {}",
+                        what, DEFAULT_TURBO_RATE
+                    );
+                    DEFAULT_TURBO_RATE
+                }
+                rate => rate.unwrap_or(DEFAULT_TURBO_RATE),
+            };
+            Binding::Turbo {
+                actions: actions_from_config(turbo, what),
+                period: Duration::from_secs(1) / rate,
+            }
+        }
+        BindingConfig::Macro { sequence } => Binding::Macro(
+            sequence
+                .into_iter()
+                .map(|step| Step {
+                    actions: step
+                        .key
+                        .map_or_else(Vec::new, |keys| actions_from_config(keys, what)),
+                    time: Duration::from_millis(step.time.unwrap_or(DEFAULT_STEP_TIME).into()),
+                })
+                .collect(),
+        ),
+    };
+    let empty = match &binding {
+        Binding::Hold(actions) | Binding::Turbo { actions, .. } => actions.is_empty(),
+        Binding::Macro(steps) => steps.iter().all(|step| step.actions.is_empty()),
+    };
+    (!empty).then_some(binding)
+}
+
+fn actions_from_config(keys: KeysConfig, what: &str) -> Vec<Action> {
+    let configs = match keys {
+        KeysConfig::One(action) => vec![action],
+        KeysConfig::Many(actions) => actions,
+    };
+    let mut actions = vec![];
+    for config in configs {
+        match Action::from_config(&config) {
+            Some(action) => actions.push(action),
+            None => println!("{} is mapped to unknown key {}", what, config),
+        }
+    }
+    actions
+}
+
+impl std::fmt::Display for ActionConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ActionConfig::Code(code) => write!(f, "{}", code),
+            ActionConfig::Name(name) => write!(f, "\"{}\"", name),
+        }
+    }
+}
+
+impl Action {
+    fn from_config(config: &ActionConfig) -> Option<Self> {
+        match config {
+            // Flash key codes fit in a byte.
+            ActionConfig::Code(code) => {
+                (*code <= 255).then(|| Action::Key(KeyCode::from_code(*code)))
+            }
+            ActionConfig::Name(name) => mouse_button_from_name(name)
+                .map(Action::Mouse)
+                .or_else(|| key_from_name(name).map(Action::Key)),
+        }
+    }
+}
+
+fn mouse_button_from_name(name: &str) -> Option<MouseButton> {
+    match name {
+        "MouseLeft" => Some(MouseButton::Left),
+        "MouseRight" => Some(MouseButton::Right),
+        "MouseMiddle" => Some(MouseButton::Middle),
+        _ => None,
+    }
+}
+
+/// Looks a key up by its name, as in the `code` of a browser's keyboard events, or a letter or
+/// digit key by its character. Names are case insensitive.
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    const NAMED_KEYS: &[(&str, KeyCode)] = &[
+        ("Backspace", KeyCode::BACKSPACE),
+        ("Tab", KeyCode::TAB),
+        ("Enter", KeyCode::RETURN),
+        ("Return", KeyCode::RETURN),
+        ("Shift", KeyCode::SHIFT),
+        ("Control", KeyCode::CONTROL),
+        ("Ctrl", KeyCode::CONTROL),
+        ("Alt", KeyCode::ALT),
+        ("Pause", KeyCode::PAUSE),
+        ("CapsLock", KeyCode::CAPS_LOCK),
+        ("Escape", KeyCode::ESCAPE),
+        ("Esc", KeyCode::ESCAPE),
+        ("Space", KeyCode::SPACE),
+        ("PageUp", KeyCode::PG_UP),
+        ("PageDown", KeyCode::PG_DOWN),
+        ("End", KeyCode::END),
+        ("Home", KeyCode::HOME),
+        ("ArrowLeft", KeyCode::LEFT),
+        ("ArrowUp", KeyCode::UP),
+        ("ArrowRight", KeyCode::RIGHT),
+        ("ArrowDown", KeyCode::DOWN),
+        ("Left", KeyCode::LEFT),
+        ("Up", KeyCode::UP),
+        ("Right", KeyCode::RIGHT),
+        ("Down", KeyCode::DOWN),
+        ("Insert", KeyCode::INSERT),
+        ("Delete", KeyCode::DELETE),
+        ("NumpadMultiply", KeyCode::NUMPAD_MULTIPLY),
+        ("NumpadAdd", KeyCode::NUMPAD_ADD),
+        ("NumpadSubtract", KeyCode::NUMPAD_SUBTRACT),
+        ("NumpadDecimal", KeyCode::NUMPAD_DECIMAL),
+        ("NumpadDivide", KeyCode::NUMPAD_DIVIDE),
+        ("NumLock", KeyCode::NUM_LOCK),
+        ("ScrollLock", KeyCode::SCROLL_LOCK),
+        ("Semicolon", KeyCode::SEMICOLON),
+        ("Equal", KeyCode::EQUALS),
+        ("Equals", KeyCode::EQUALS),
+        ("Comma", KeyCode::COMMA),
+        ("Minus", KeyCode::MINUS),
+        ("Period", KeyCode::PERIOD),
+        ("Slash", KeyCode::SLASH),
+        ("Backquote", KeyCode::GRAVE),
+        ("BracketLeft", KeyCode::LBRACKET),
+        ("Backslash", KeyCode::BACKSLASH),
+        ("BracketRight", KeyCode::RBRACKET),
+        ("Quote", KeyCode::APOSTROPHE),
+    ];
+    if let Some((_, key)) = NAMED_KEYS
+        .iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+    {
+        return Some(*key);
+    }
+
+    // Letters and digits, alone or as in "KeyA" and "Digit1".
+    let lower = name.to_ascii_lowercase();
+    let character = lower
+        .strip_prefix("key")
+        .or_else(|| lower.strip_prefix("digit"))
+        .unwrap_or(&lower);
+    if let [c] = character.as_bytes() {
+        if c.is_ascii_alphanumeric() {
+            return Some(KeyCode::from_code(c.to_ascii_uppercase() as u32));
+        }
+    }
+
+    // The numbered keys, "F1" to "F15" and "Numpad0" to "Numpad9", whose Flash key codes run
+    // from 112 and 96.
+    if let Some(n) = lower.strip_prefix('f').and_then(|n| n.parse::<u32>().ok()) {
+        if (1..=15).contains(&n) {
+            return Some(KeyCode::from_code(111 + n));
+        }
+    }
+    if let Some(n) = lower
+        .strip_prefix("numpad")
+        .and_then(|n| n.parse::<u32>().ok())
+    {
+        if n <= 9 {
+            return Some(KeyCode::from_code(96 + n));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::controller::Button;
+
+    #[test]
+    fn keys_are_found_by_name() {
+        let cases = [
+            ("Space", KeyCode::SPACE),
+            ("space", KeyCode::SPACE),
+            ("ENTER", KeyCode::RETURN),
+            ("ArrowLeft", KeyCode::LEFT),
+            ("Left", KeyCode::LEFT),
+            ("BracketRight", KeyCode::RBRACKET),
+            ("a", KeyCode::A),
+            ("KeyZ", KeyCode::Z),
+            ("7", KeyCode::KEY7),
+            ("Digit0", KeyCode::KEY0),
+            ("F1", KeyCode::F1),
+            ("f12", KeyCode::F12),
+            ("F15", KeyCode::F15),
+            ("Numpad0", KeyCode::NUMPAD0),
+            ("Numpad9", KeyCode::NUMPAD9),
+            ("NumpadAdd", KeyCode::NUMPAD_ADD),
+        ];
+        for (name, key) in cases {
+            assert_eq!(key_from_name(name), Some(key), "{name}");
+        }
+    }
+
+    #[test]
+    fn unknown_key_names_are_rejected() {
+        for name in [
+            "",
+            "F0",
+            "F16",
+            "Numpad10",
+            "KeyAB",
+            "Digit",
+            "Ä",
+            "MouseLeft",
+        ] {
+            assert_eq!(key_from_name(name), None, "{name}");
+        }
+    }
+
+    #[test]
+    fn actions_are_keys_codes_or_mouse_buttons() {
+        let action = |config| Action::from_config(&config);
+        assert_eq!(
+            action(ActionConfig::Name("MouseRight".into())),
+            Some(Action::Mouse(MouseButton::Right))
+        );
+        assert_eq!(
+            action(ActionConfig::Name("Shift".into())),
+            Some(Action::Key(KeyCode::SHIFT))
+        );
+        assert_eq!(
+            action(ActionConfig::Code(32)),
+            Some(Action::Key(KeyCode::SPACE))
+        );
+        assert_eq!(
+            action(ActionConfig::Code(255)),
+            Some(Action::Key(KeyCode::from_code(255)))
+        );
+        assert_eq!(action(ActionConfig::Code(256)), None);
+        assert_eq!(action(ActionConfig::Code(u32::MAX)), None);
+        assert_eq!(action(ActionConfig::Name("Hyper".into())), None);
+    }
+
+    #[test]
+    fn malformed_entries_are_left_out_alone() {
+        let config: HashMap<String, ron::Value> = ron::from_str(
+            r#"{
+                "LeftStick": "Space",
+                "RightStick": (turbo: "X", rate: "fast"),
+                "Guide": (sequence: [(key: "Left", time: 50), (time: 20)]),
+                "Misc1": ["Up", 90],
+                "Nope": "Z",
+            }"#,
+        )
+        .unwrap();
+        let mapping = ButtonMapping::from_config(config);
+        assert_eq!(
+            mapping.binding(Input::Extra(Button::LeftStick)),
+            Some(&Binding::Hold(vec![Action::Key(KeyCode::SPACE)]))
+        );
+        assert_eq!(mapping.binding(Input::Extra(Button::RightStick)), None);
+        assert_eq!(
+            mapping.binding(Input::Extra(Button::Guide)),
+            Some(&Binding::Macro(vec![
+                Step {
+                    actions: vec![Action::Key(KeyCode::LEFT)],
+                    time: Duration::from_millis(50),
+                },
+                Step {
+                    actions: vec![],
+                    time: Duration::from_millis(20),
+                },
+            ]))
+        );
+        assert_eq!(
+            mapping.binding(Input::Extra(Button::Misc1)),
+            Some(&Binding::Hold(vec![
+                Action::Key(KeyCode::UP),
+                Action::Key(KeyCode::Z),
+            ]))
+        );
+    }
+}
@@ -0,0 +1,395 @@
+//! Translation of SDL keyboard events to Flash key codes and text editing controls.
+//!
+//! Letters follow the keyboard layout, so that an `A` on an AZERTY keyboard is reported as `A`.
+//! Every other key is looked up by its position, which also tells left and right modifiers and
+//! the numpad apart.
+
+use ruffle_core::PlayerEvent;
+use ruffle_core::events::{KeyCode, KeyLocation, TextControlCode};
+use sdl2::keyboard::{Keycode, Mod, Scancode};
+
+/// Builds the `KeyDown` or `KeyUp` event for a key, or `None` for keys Flash doesn't know.
+pub fn key_event(
+    pressed: bool,
+    scancode: Scancode,
+    keycode: Option<Keycode>,
+    keymod: Mod,
+) -> Option<PlayerEvent> {
+    let (key_code, key_location) = match layout_letter(keycode) {
+        Some(letter) => (
+            KeyCode::from_code(letter.to_ascii_uppercase() as u32),
+            KeyLocation::Standard,
+        ),
+        None => scancode_to_key_code(scancode, keymod.contains(Mod::NUMMOD))?,
+    };
+    let key_char = key_char(scancode, keycode, keymod);
+    Some(if pressed {
+        PlayerEvent::KeyDown {
+            key_code,
+            key_char,
+            key_location,
+        }
+    } else {
+        PlayerEvent::KeyUp {
+            key_code,
+            key_char,
+            key_location,
+        }
+    })
+}
+
+/// The text editing control a key triggers in a focused text field, if any.
+pub fn text_control(
+    scancode: Scancode,
+    keycode: Option<Keycode>,
+    keymod: Mod,
+) -> Option<TextControlCode> {
+    let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+    let ctrl = keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+
+    if ctrl {
+        match layout_letter(keycode) {
+            Some('a') => return Some(TextControlCode::SelectAll),
+            Some('c') => return Some(TextControlCode::Copy),
+            Some('x') => return Some(TextControlCode::Cut),
+            Some('v') => return Some(TextControlCode::Paste),
+            _ => {}
+        }
+    }
+
+    let code = match (scancode, shift, ctrl) {
+        (Scancode::Left, false, false) => TextControlCode::MoveLeft,
+        (Scancode::Left, false, true) => TextControlCode::MoveLeftWord,
+        (Scancode::Left, true, false) => TextControlCode::SelectLeft,
+        (Scancode::Left, true, true) => TextControlCode::SelectLeftWord,
+        (Scancode::Right, false, false) => TextControlCode::MoveRight,
+        (Scancode::Right, false, true) => TextControlCode::MoveRightWord,
+        (Scancode::Right, true, false) => TextControlCode::SelectRight,
+        (Scancode::Right, true, true) => TextControlCode::SelectRightWord,
+        (Scancode::Home, false, false) => TextControlCode::MoveLeftLine,
+        (Scancode::Home, false, true) => TextControlCode::MoveLeftDocument,
+        (Scancode::Home, true, false) => TextControlCode::SelectLeftLine,
+        (Scancode::Home, true, true) => TextControlCode::SelectLeftDocument,
+        (Scancode::End, false, false) => TextControlCode::MoveRightLine,
+        (Scancode::End, false, true) => TextControlCode::MoveRightDocument,
+        (Scancode::End, true, false) => TextControlCode::SelectRightLine,
+        (Scancode::End, true, true) => TextControlCode::SelectRightDocument,
+        (Scancode::Backspace, _, false) => TextControlCode::Backspace,
+        (Scancode::Backspace, _, true) => TextControlCode::BackspaceWord,
+        (Scancode::Delete, _, false) => TextControlCode::Delete,
+        (Scancode::Delete, _, true) => TextControlCode::DeleteWord,
+        (Scancode::Return | Scancode::KpEnter, _, _) => TextControlCode::Enter,
+        // Shift+Insert and Ctrl+Insert are the older spellings of paste and copy.
+        (Scancode::Insert, true, false) => TextControlCode::Paste,
+        (Scancode::Insert, false, true) => TextControlCode::Copy,
+        _ => return None,
+    };
+    Some(code)
+}
+
+/// The lowercase letter a key types in the current layout.
+fn layout_letter(keycode: Option<Keycode>) -> Option<char> {
+    let c = char::from_u32(keycode?.into_i32() as u32)?;
+    c.is_ascii_lowercase().then_some(c)
+}
+
+/// The character a key types, as far as it can be told from the key alone. Text entry uses the
+/// `TextInput` events instead, which also know about dead keys and input methods.
+fn key_char(scancode: Scancode, keycode: Option<Keycode>, keymod: Mod) -> Option<char> {
+    let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+    if let Some(letter) = layout_letter(keycode) {
+        let caps = keymod.contains(Mod::CAPSMOD);
+        return Some(if shift != caps {
+            letter.to_ascii_uppercase()
+        } else {
+            letter
+        });
+    }
+    let numlock = keymod.contains(Mod::NUMMOD);
+    let c = match scancode {
+        Scancode::Return | Scancode::KpEnter => '\r',
+        Scancode::Tab => '\t',
+        Scancode::Backspace => '\u{8}',
+        Scancode::Escape => '\u{1b}',
+        Scancode::Delete => '\u{7f}',
+        Scancode::KpDivide => '/',
+        Scancode::KpMultiply => '*',
+        Scancode::KpMinus => '-',
+        Scancode::KpPlus => '+',
+        Scancode::Kp0 if numlock => '0',
+        Scancode::Kp1 if numlock => '1',
+        Scancode::Kp2 if numlock => '2',
+        Scancode::Kp3 if numlock => '3',
+        Scancode::Kp4 if numlock => '4',
+        Scancode::Kp5 if numlock => '5',
+        Scancode::Kp6 if numlock => '6',
+        Scancode::Kp7 if numlock => '7',
+        Scancode::Kp8 if numlock => '8',
+        Scancode::Kp9 if numlock => '9',
+        Scancode::KpPeriod if numlock => '.',
+        _ => {
+            // Shifted symbols depend on the layout, so only the unshifted ones are known.
+            let c = char::from_u32(keycode?.into_i32() as u32)?;
+            if shift || !(c == ' ' || c.is_ascii_graphic()) {
+                return None;
+            }
+            c
+        }
+    };
+    Some(c)
+}
+
+/// Looks a key up by its position. Without num lock, the numpad works as the navigation keys.
+fn scancode_to_key_code(scancode: Scancode, numlock: bool) -> Option<(KeyCode, KeyLocation)> {
+    use KeyLocation::{Left, Numpad, Right, Standard};
+
+    let key = match scancode {
+        Scancode::A => (KeyCode::A, Standard),
+        Scancode::B => (KeyCode::B, Standard),
+        Scancode::C => (KeyCode::C, Standard),
+        Scancode::D => (KeyCode::D, Standard),
+        Scancode::E => (KeyCode::E, Standard),
+        Scancode::F => (KeyCode::F, Standard),
+        Scancode::G => (KeyCode::G, Standard),
+        Scancode::H => (KeyCode::H, Standard),
+        Scancode::I => (KeyCode::I, Standard),
+        Scancode::J => (KeyCode::J, Standard),
+        Scancode::K => (KeyCode::K, Standard),
+        Scancode::L => (KeyCode::L, Standard),
+        Scancode::M => (KeyCode::M, Standard),
+        Scancode::N => (KeyCode::N, Standard),
+        Scancode::O => (KeyCode::O, Standard),
+        Scancode::P => (KeyCode::P, Standard),
+        Scancode::Q => (KeyCode::Q, Standard),
+        Scancode::R => (KeyCode::R, Standard),
+        Scancode::S => (KeyCode::S, Standard),
+        Scancode::T => (KeyCode::T, Standard),
+        Scancode::U => (KeyCode::U, Standard),
+        Scancode::V => (KeyCode::V, Standard),
+        Scancode::W => (KeyCode::W, Standard),
+        Scancode::X => (KeyCode::X, Standard),
+        Scancode::Y => (KeyCode::Y, Standard),
+        Scancode::Z => (KeyCode::Z, Standard),
+
+        Scancode::Num0 => (KeyCode::KEY0, Standard),
+        Scancode::Num1 => (KeyCode::KEY1, Standard),
+        Scancode::Num2 => (KeyCode::KEY2, Standard),
+        Scancode::Num3 => (KeyCode::KEY3, Standard),
+        Scancode::Num4 => (KeyCode::KEY4, Standard),
+        Scancode::Num5 => (KeyCode::KEY5, Standard),
+        Scancode::Num6 => (KeyCode::KEY6, Standard),
+        Scancode::Num7 => (KeyCode::KEY7, Standard),
+        Scancode::Num8 => (KeyCode::KEY8, Standard),
+        Scancode::Num9 => (KeyCode::KEY9, Standard),
+
+        Scancode::F1 => (KeyCode::F1, Standard),
+        Scancode::F2 => (KeyCode::F2, Standard),
+        Scancode::F3 => (KeyCode::F3, Standard),
+        Scancode::F4 => (KeyCode::F4, Standard),
+        Scancode::F5 => (KeyCode::F5, Standard),
+        Scancode::F6 => (KeyCode::F6, Standard),
+        Scancode::F7 => (KeyCode::F7, Standard),
+        Scancode::F8 => (KeyCode::F8, Standard),
+        Scancode::F9 => (KeyCode::F9, Standard),
+        Scancode::F10 => (KeyCode::F10, Standard),
+        Scancode::F11 => (KeyCode::F11, Standard),
+        Scancode::F12 => (KeyCode::F12, Standard),
+        Scancode::F13 => (KeyCode::F13, Standard),
+        Scancode::F14 => (KeyCode::F14, Standard),
+        Scancode::F15 => (KeyCode::F15, Standard),
+
+        Scancode::Return => (KeyCode::RETURN, Standard),
+        Scancode::Escape => (KeyCode::ESCAPE, Standard),
+        Scancode::Backspace => (KeyCode::BACKSPACE, Standard),
+        Scancode::Tab => (KeyCode::TAB, Standard),
+        Scancode::Space => (KeyCode::SPACE, Standard),
+        Scancode::CapsLock => (KeyCode::CAPS_LOCK, Standard),
+        Scancode::ScrollLock => (KeyCode::SCROLL_LOCK, Standard),
+        Scancode::Pause => (KeyCode::PAUSE, Standard),
+        Scancode::Insert => (KeyCode::INSERT, Standard),
+        Scancode::Delete => (KeyCode::DELETE, Standard),
+        Scancode::Home => (KeyCode::HOME, Standard),
+        Scancode::End => (KeyCode::END, Standard),
+        Scancode::PageUp => (KeyCode::PG_UP, Standard),
+        Scancode::PageDown => (KeyCode::PG_DOWN, Standard),
+        Scancode::Left => (KeyCode::LEFT, Standard),
+        Scancode::Right => (KeyCode::RIGHT, Standard),
+        Scancode::Up => (KeyCode::UP, Standard),
+        Scancode::Down => (KeyCode::DOWN, Standard),
+
+        Scancode::Minus => (KeyCode::MINUS, Standard),
+        Scancode::Equals => (KeyCode::EQUALS, Standard),
+        Scancode::LeftBracket => (KeyCode::LBRACKET, Standard),
+        Scancode::RightBracket => (KeyCode::RBRACKET, Standard),
+        Scancode::Backslash | Scancode::NonUsHash | Scancode::NonUsBackslash => {
+            (KeyCode::BACKSLASH, Standard)
+        }
+        Scancode::Semicolon => (KeyCode::SEMICOLON, Standard),
+        Scancode::Apostrophe => (KeyCode::APOSTROPHE, Standard),
+        Scancode::Grave => (KeyCode::GRAVE, Standard),
+        Scancode::Comma => (KeyCode::COMMA, Standard),
+        Scancode::Period => (KeyCode::PERIOD, Standard),
+        Scancode::Slash => (KeyCode::SLASH, Standard),
+
+        Scancode::LShift => (KeyCode::SHIFT, Left),
+        Scancode::RShift => (KeyCode::SHIFT, Right),
+        Scancode::LCtrl => (KeyCode::CONTROL, Left),
+        Scancode::RCtrl => (KeyCode::CONTROL, Right),
+        Scancode::LAlt => (KeyCode::ALT, Left),
+        Scancode::RAlt => (KeyCode::ALT, Right),
+
+        Scancode::NumLockClear => (KeyCode::NUM_LOCK, Numpad),
+        Scancode::KpDivide => (KeyCode::NUMPAD_DIVIDE, Numpad),
+        Scancode::KpMultiply => (KeyCode::NUMPAD_MULTIPLY, Numpad),
+        Scancode::KpMinus => (KeyCode::NUMPAD_SUBTRACT, Numpad),
+        Scancode::KpPlus => (KeyCode::NUMPAD_ADD, Numpad),
+        Scancode::KpEnter => (KeyCode::RETURN, Numpad),
+        Scancode::Kp0 if numlock => (KeyCode::NUMPAD0, Numpad),
+        Scancode::Kp1 if numlock => (KeyCode::NUMPAD1, Numpad),
+        Scancode::Kp2 if numlock => (KeyCode::NUMPAD2, Numpad),
+        Scancode::Kp3 if numlock => (KeyCode::NUMPAD3, Numpad),
+        Scancode::Kp4 if numlock => (KeyCode::NUMPAD4, Numpad),
+        Scancode::Kp5 if numlock => (KeyCode::NUMPAD5, Numpad),
+        Scancode::Kp6 if numlock => (KeyCode::NUMPAD6, Numpad),
+        Scancode::Kp7 if numlock => (KeyCode::NUMPAD7, Numpad),
+        Scancode::Kp8 if numlock => (KeyCode::NUMPAD8, Numpad),
+        Scancode::Kp9 if numlock => (KeyCode::NUMPAD9, Numpad),
+        Scancode::KpPeriod if numlock => (KeyCode::NUMPAD_DECIMAL, Numpad),
+        Scancode::Kp0 => (KeyCode::INSERT, Numpad),
+        Scancode::Kp1 => (KeyCode::END, Numpad),
+        Scancode::Kp2 => (KeyCode::DOWN, Numpad),
+        Scancode::Kp3 => (KeyCode::PG_DOWN, Numpad),
+        Scancode::Kp4 => (KeyCode::LEFT, Numpad),
+        Scancode::Kp5 => (KeyCode::NUMPAD5, Numpad),
+        Scancode::Kp6 => (KeyCode::RIGHT, Numpad),
+        Scancode::Kp7 => (KeyCode::HOME, Numpad),
+        Scancode::Kp8 => (KeyCode::UP, Numpad),
+        Scancode::Kp9 => (KeyCode::PG_UP, Numpad),
+        Scancode::KpPeriod => (KeyCode::DELETE, Numpad),
+
+        _ => return None,
+    };
+    Some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scancodes_map_to_key_codes_and_locations() {
+        let cases = [
+            (Scancode::A, (KeyCode::A, KeyLocation::Standard)),
+            (Scancode::Num7, (KeyCode::KEY7, KeyLocation::Standard)),
+            (Scancode::F12, (KeyCode::F12, KeyLocation::Standard)),
+            (Scancode::Grave, (KeyCode::GRAVE, KeyLocation::Standard)),
+            (
+                Scancode::NonUsBackslash,
+                (KeyCode::BACKSLASH, KeyLocation::Standard),
+            ),
+            (Scancode::LShift, (KeyCode::SHIFT, KeyLocation::Left)),
+            (Scancode::RCtrl, (KeyCode::CONTROL, KeyLocation::Right)),
+            (Scancode::KpEnter, (KeyCode::RETURN, KeyLocation::Numpad)),
+            (
+                Scancode::KpMinus,
+                (KeyCode::NUMPAD_SUBTRACT, KeyLocation::Numpad),
+            ),
+        ];
+        for (scancode, key) in cases {
+            assert_eq!(
+                scancode_to_key_code(scancode, false),
+                Some(key),
+                "{:?}",
+                scancode
+            );
+        }
+        assert_eq!(scancode_to_key_code(Scancode::Application, false), None);
+    }
+
+    #[test]
+    fn numpad_follows_num_lock() {
+        assert_eq!(
+            scancode_to_key_code(Scancode::Kp4, true),
+            Some((KeyCode::NUMPAD4, KeyLocation::Numpad))
+        );
+        assert_eq!(
+            scancode_to_key_code(Scancode::Kp4, false),
+            Some((KeyCode::LEFT, KeyLocation::Numpad))
+        );
+        assert_eq!(
+            scancode_to_key_code(Scancode::KpPeriod, true),
+            Some((KeyCode::NUMPAD_DECIMAL, KeyLocation::Numpad))
+        );
+        assert_eq!(
+            scancode_to_key_code(Scancode::KpPeriod, false),
+            Some((KeyCode::DELETE, KeyLocation::Numpad))
+        );
+        // 5 has no navigation key of its own.
+        assert_eq!(
+            scancode_to_key_code(Scancode::Kp5, false),
+            Some((KeyCode::NUMPAD5, KeyLocation::Numpad))
+        );
+
+        assert!(matches!(
+            key_event(true, Scancode::Kp1, Some(Keycode::Kp1), Mod::NUMMOD),
+            Some(PlayerEvent::KeyDown {
+                key_code: KeyCode::NUMPAD1,
+                key_char: Some('1'),
+                key_location: KeyLocation::Numpad,
+            })
+        ));
+        assert!(matches!(
+            key_event(true, Scancode::Kp1, Some(Keycode::Kp1), Mod::NOMOD),
+            Some(PlayerEvent::KeyDown {
+                key_code: KeyCode::END,
+                key_char: None,
+                key_location: KeyLocation::Numpad,
+            })
+        ));
+    }
+
+    #[test]
+    fn letters_follow_the_layout() {
+        assert_eq!(layout_letter(Some(Keycode::Q)), Some('q'));
+        assert_eq!(layout_letter(Some(Keycode::Num1)), None);
+        assert_eq!(layout_letter(Some(Keycode::Return)), None);
+        assert_eq!(layout_letter(None), None);
+
+        // The key in the place of QWERTY's A types a Q on AZERTY.
+        assert!(matches!(
+            key_event(true, Scancode::A, Some(Keycode::Q), Mod::NOMOD),
+            Some(PlayerEvent::KeyDown {
+                key_code: KeyCode::Q,
+                key_char: Some('q'),
+                key_location: KeyLocation::Standard,
+            })
+        ));
+        assert!(matches!(
+            key_event(false, Scancode::A, Some(Keycode::Q), Mod::LSHIFTMOD),
+            Some(PlayerEvent::KeyUp {
+                key_code: KeyCode::Q,
+                key_char: Some('Q'),
+                key_location: KeyLocation::Standard,
+            })
+        ));
+        // Caps lock and shift cancel out.
+        assert!(matches!(
+            key_event(
+                true,
+                Scancode::A,
+                Some(Keycode::A),
+                Mod::CAPSMOD | Mod::RSHIFTMOD
+            ),
+            Some(PlayerEvent::KeyDown {
+                key_code: KeyCode::A,
+                key_char: Some('a'),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn unknown_keys_send_nothing() {
+        assert!(key_event(true, Scancode::Application, None, Mod::NOMOD).is_none());
+    }
+}
@@ -2,6 +2,19 @@
 #![allow(dead_code)]
 
 mod backends;
+mod gamepad;
+mod input;
+mod keyboard;
+mod motion;
+mod on_screen_keyboard;
+mod players;
+mod rear_touch;
+mod scaling;
+mod stick;
+mod timed;
+mod touch;
+mod virtual_controls;
+mod virtual_mouse;
 
 use std::collections::HashMap;
 use std::fs::File;
@@ -16,22 +29,38 @@ use ron::from_str;
 
 use ruffle_core::backend::navigator::{NullExecutor, NullNavigatorBackend};
 use ruffle_core::config::Letterbox;
-use ruffle_core::events::{GamepadButton, KeyCode, MouseButton, ParseEnumError, TextControlCode};
+use ruffle_core::events::{GamepadButton, MouseButton};
 use ruffle_core::limits::ExecutionLimit;
+use ruffle_core::swf::Color;
 use ruffle_core::tag_utils::SwfMovie;
-use ruffle_core::{PlayerBuilder, PlayerEvent, ViewportDimensions};
+use ruffle_core::{Player, PlayerBuilder, PlayerEvent, ViewportDimensions};
 
 use ruffle_render::quality::StageQuality;
-use ruffle_render_glow::GlowRenderBackend;
+use ruffle_render_glow::{GlowRenderBackend, LetterboxBorder, ScreenRotation, ShapeCache};
 
 use sdl2::controller::Axis;
 use serde::Deserialize;
 use tracing_subscriber::layer::SubscriberExt;
 
 use backends::audio::SdlAudioBackend;
+use backends::fonts::FontDatabase;
 use backends::log::ConsoleLogBackend;
 use backends::storage::DiskStorageBackend;
-use backends::ui::SdlUiBackend;
+use backends::ui::{KeyboardRequests, SdlUiBackend};
+use backends::video::ThreadedVideoBackend;
+
+use gamepad::RightStickMode;
+use input::{Action, Binding, ButtonMapping, Input, PlayerMappings, Target};
+use motion::{Motion, MotionConfig};
+use on_screen_keyboard::{KeyboardBitmaps, OnScreenKeyboard, label_bitmap};
+use players::{Controllers, PadInput};
+use rear_touch::{RearTouch, RearTouchConfig};
+use scaling::{ScalingMode, StageLayout};
+use stick::{Deadzone, DeadzoneConfig, Directions};
+use timed::TimedInputs;
+use touch::{TouchConfig, TouchMode, TouchMouse, TouchPhase, Touches};
+use virtual_controls::{ControlBitmaps, VirtualControls, VirtualControlsConfig};
+use virtual_mouse::{Stick, VirtualMouse, VirtualMouseConfig};
 
 //#[cfg(any(target_os = "vita", target_os = "horizon"))]
 #[cfg(target_os = "horizon")]
@@ -132,24 +161,6 @@ pub fn get_default_display_resolution() -> Result<(u32, u32), u32> {
         Err(rc)
     }
 }
-pub struct AxisState {
-    pub up: bool,
-    pub down: bool,
-    pub left: bool,
-    pub right: bool,
-}
-
-impl Default for AxisState {
-    fn default() -> Self {
-        AxisState {
-            up: false,
-            down: false,
-            left: false,
-            right: false,
-        }
-    }
-}
-
 #[cfg(target_os = "vita")]
 const BASE_PATH: &str = "ux0:data/ruffle";
 
@@ -159,6 +170,18 @@ const BASE_PATH: &str = "/switch/ruffle";
 #[cfg(not(any(target_os = "horizon", target_os = "vita")))]
 const BASE_PATH: &str = "./ruffle";
 
+/// Default share of the movie that the zoom scaling mode may crop on each axis, in percent.
+const DEFAULT_ZOOM_MARGIN: u32 = 10;
+
+/// Default share of their travel that the analog triggers are pulled before they press, in percent.
+const DEFAULT_TRIGGER_THRESHOLD: u32 = 50;
+
+/// Default number of threads decoding video.
+const DEFAULT_VIDEO_DECODE_THREADS: u32 = 1;
+
+/// Default size limit of the tessellation cache, in MiB.
+const DEFAULT_TESSELLATION_CACHE_SIZE: u64 = 32;
+
 const CONFIG: &str = "
 Config(
     gamepad_config: {},
@@ -167,21 +190,213 @@ use tracing_subscriber::util::SubscriberInitExt;
 
 #[derive(Debug, Deserialize)]
 struct Config {
-    gamepad_config: HashMap<String, u32>,
+    // Read entry by entry, so that one malformed entry doesn't lose the whole file.
+    gamepad_config: HashMap<String, ron::Value>,
+    player_gamepad_config: Option<Vec<HashMap<String, ron::Value>>>,
     swf_url: Option<String>,
     swf_name: Option<String>,
     letterbox: Option<String>,
+    letterbox_border: Option<String>,
+    scaling: Option<String>,
+    zoom_margin: Option<u32>,
+    line_antialiasing: Option<bool>,
+    tessellation_cache_size: Option<u64>,
+    rotation: Option<u32>,
+    video_decode_threads: Option<u32>,
+    device_fonts: Option<HashMap<String, Vec<String>>>,
+    font_fallbacks: Option<Vec<String>>,
+    save_clipboard: Option<bool>,
+    virtual_mouse: Option<VirtualMouseConfig>,
+    right_stick: Option<String>,
+    trigger_threshold: Option<u32>,
+    left_stick_deadzone: Option<DeadzoneConfig>,
+    right_stick_deadzone: Option<DeadzoneConfig>,
+    dpad_directions: Option<u32>,
+    touch: Option<TouchConfig>,
+    rear_touch: Option<RearTouchConfig>,
+    virtual_controls: Option<VirtualControlsConfig>,
+    on_screen_keyboard: Option<bool>,
+    motion: Option<MotionConfig>,
+}
+
+struct Settings {
+    mappings: PlayerMappings,
+    swf_name: Option<String>,
+    swf_url: Option<String>,
+    letterbox: Letterbox,
+    letterbox_border: Option<LetterboxBorder>,
+    scaling: ScalingMode,
+    zoom_margin: f64,
+    line_antialiasing: bool,
+    tessellation_cache_size: u64,
+    rotation: ScreenRotation,
+    video_decode_threads: u32,
+    device_fonts: HashMap<String, Vec<String>>,
+    font_fallbacks: Option<Vec<String>>,
+    save_clipboard: bool,
+    virtual_mouse: Option<VirtualMouse>,
+    right_stick: RightStickMode,
+    trigger_threshold: i16,
+    left_deadzone: Deadzone,
+    right_deadzone: Deadzone,
+    dpad_directions: Directions,
+    touch_mouse: TouchMouse,
+    rear_touch: RearTouch,
+    virtual_controls: Option<VirtualControls>,
+    on_screen_keyboard: bool,
+    motion: Motion,
+}
+
+impl Settings {
+    fn from_config(config: Config) -> Self {
+        #[cfg(not(any(target_os = "horizon", target_os = "vita")))]
+        if config.save_clipboard == Some(true) {
+            println!("save_clipboard only applies on consoles, the system clipboard is used here");
+        }
+        let right_stick = match config.right_stick {
+            Some(mode) => RightStickMode::from_str(&mode).unwrap_or_else(|_| {
+                println!(
+                    "Unsupported right stick mode {}, expected none, dpad or mouse",
+                    mode
+                );
+                RightStickMode::None
+            }),
+            None => RightStickMode::None,
+        };
+        let left_deadzone = Deadzone::from_config(config.left_stick_deadzone.unwrap_or_default());
+        let right_deadzone = Deadzone::from_config(config.right_stick_deadzone.unwrap_or_default());
+        let dpad_directions = match config.dpad_directions {
+            Some(count) => Directions::from_count(count).unwrap_or_else(|| {
+                println!("Unsupported d-pad directions {}, expected 4 or 8", count);
+                Directions::Eight
+            }),
+            None => Directions::Eight,
+        };
+        let mut virtual_mouse = config.virtual_mouse.map(VirtualMouse::from_config);
+        if right_stick == RightStickMode::Mouse {
+            let mut mouse = virtual_mouse
+                .unwrap_or_else(|| VirtualMouse::from_config(VirtualMouseConfig::default()));
+            mouse.set_stick(Stick::Right);
+            virtual_mouse = Some(mouse);
+        }
+        let motion = Motion::from_config(config.motion.unwrap_or_default());
+        if motion.gyro_mouse() && virtual_mouse.is_none() {
+            virtual_mouse = Some(VirtualMouse::from_config(VirtualMouseConfig::default()));
+        }
+        if let Some(mouse) = &mut virtual_mouse {
+            mouse.set_deadzone(match mouse.stick() {
+                Stick::Left => left_deadzone,
+                Stick::Right => right_deadzone,
+            });
+        }
+        Self {
+            mappings: PlayerMappings::from_config(
+                config.gamepad_config,
+                config.player_gamepad_config.unwrap_or_default(),
+            ),
+            swf_name: config.swf_name,
+            swf_url: config.swf_url,
+            letterbox: Letterbox::from_str(&config.letterbox.unwrap_or("on".to_string()))
+                .unwrap_or(Letterbox::On),
+            letterbox_border: config
+                .letterbox_border
+                .and_then(|border| load_letterbox_border(&border)),
+            scaling: match config.scaling {
+                Some(scaling) => ScalingMode::from_str(&scaling).unwrap_or_else(|_| {
+                    println!(
+                        "Unsupported scaling mode {}, expected fit, integer, pixel_perfect, stretch or zoom",
+                        scaling
+                    );
+                    ScalingMode::Fit
+                }),
+                None => ScalingMode::Fit,
+            },
+            zoom_margin: config.zoom_margin.unwrap_or(DEFAULT_ZOOM_MARGIN).min(100) as f64 / 100.0,
+            line_antialiasing: config.line_antialiasing.unwrap_or(true),
+            tessellation_cache_size: config
+                .tessellation_cache_size
+                .unwrap_or(DEFAULT_TESSELLATION_CACHE_SIZE),
+            rotation: match config.rotation {
+                Some(degrees) => ScreenRotation::from_degrees(degrees).unwrap_or_else(|| {
+                    println!(
+                        "Unsupported rotation {}, expected 0, 90, 180 or 270",
+                        degrees
+                    );
+                    ScreenRotation::None
+                }),
+                None => ScreenRotation::None,
+            },
+            video_decode_threads: config
+                .video_decode_threads
+                .unwrap_or(DEFAULT_VIDEO_DECODE_THREADS)
+                .max(1),
+            device_fonts: config.device_fonts.unwrap_or_default(),
+            font_fallbacks: config.font_fallbacks,
+            save_clipboard: config.save_clipboard.unwrap_or(false),
+            virtual_mouse,
+            right_stick,
+            trigger_threshold: (config
+                .trigger_threshold
+                .unwrap_or(DEFAULT_TRIGGER_THRESHOLD)
+                .min(100)
+                * i16::MAX as u32
+                / 100) as i16,
+            left_deadzone,
+            right_deadzone,
+            dpad_directions,
+            touch_mouse: TouchMouse::from_config(config.touch.unwrap_or_default()),
+            rear_touch: RearTouch::from_config(config.rear_touch.unwrap_or_default()),
+            virtual_controls: config.virtual_controls.map(|controls| {
+                VirtualControls::from_config(
+                    controls,
+                    dpad_directions,
+                    format!("{}/{}", BASE_PATH, "virtual_controls.ron").into(),
+                )
+            }),
+            // Opt-in, since SDL already shows the system keyboard of the Switch and the Vita.
+            on_screen_keyboard: config.on_screen_keyboard.unwrap_or(false),
+            motion,
+        }
+    }
+}
+
+/// Parses the `letterbox_border` option: "blur", a "#rrggbb" color, or an image in `BASE_PATH`.
+fn load_letterbox_border(border: &str) -> Option<LetterboxBorder> {
+    if border == "blur" {
+        return Some(LetterboxBorder::Blur);
+    }
+    if let Some(hex) = border.strip_prefix('#') {
+        return match u32::from_str_radix(hex, 16) {
+            // `from_str_radix` also takes a leading sign.
+            Ok(rgb) if hex.len() == 6 && hex.bytes().all(|b| b.is_ascii_hexdigit()) => {
+                Some(LetterboxBorder::Color(Color::from_rgb(rgb, 255)))
+            }
+            _ => {
+                println!(
+                    "Invalid letterbox border color {}, expected #rrggbb",
+                    border
+                );
+                None
+            }
+        };
+    }
+
+    let path = format!("{}/{}", BASE_PATH, border);
+    let bitmap = std::fs::read(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|data| {
+            ruffle_render::utils::decode_define_bits_jpeg(&data, None).map_err(|e| e.to_string())
+        });
+    match bitmap {
+        Ok(bitmap) => Some(LetterboxBorder::Image(bitmap)),
+        Err(e) => {
+            println!("Couldn't load letterbox border {}: {}", path, e);
+            None
+        }
+    }
 }
 
-fn load_config() -> Result<
-    (
-        HashMap<GamepadButton, KeyCode>,
-        Option<String>,
-        Option<String>,
-        Letterbox,
-    ),
-    ParseEnumError,
-> {
+fn load_config() -> Settings {
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::builder().parse_lossy("info,ruffle=info,avm_trace=info"),
@@ -208,34 +423,20 @@ fn load_config() -> Result<
                 from_str(CONFIG).unwrap()
             }
         };
-        let mut gamepad_button_mapping: HashMap<GamepadButton, KeyCode> = HashMap::new();
-        for (button, key) in config.gamepad_config.into_iter() {
-            gamepad_button_mapping
-                .insert(GamepadButton::from_str(&button)?, KeyCode::from_code(key));
-        }
-        Ok((
-            gamepad_button_mapping,
-            config.swf_name,
-            config.swf_url,
-            Letterbox::from_str(&config.letterbox.unwrap_or("on".to_string()))
-                .unwrap_or(Letterbox::On),
-        ))
+        Settings::from_config(config)
     } else {
         println!("Couldn't load config file:{}", config_file_clone);
         let config: Config = from_str(CONFIG).unwrap();
-        let mut gamepad_button_mapping: HashMap<GamepadButton, KeyCode> = HashMap::new();
-        for (button, key) in config.gamepad_config.into_iter() {
-            gamepad_button_mapping
-                .insert(GamepadButton::from_str(&button)?, KeyCode::from_code(key));
-        }
-        Ok((
-            gamepad_button_mapping,
-            config.swf_name,
-            config.swf_url,
-            Letterbox::from_str(&config.letterbox.unwrap_or("on".to_string()))
-                .unwrap_or(Letterbox::On),
-        ))
+        Settings::from_config(config)
+    }
+}
+
+/// Resizes the player's viewport and places it on the screen.
+fn apply_layout(player: &mut Player, layout: &StageLayout) {
+    if let Some(renderer) = player.renderer_mut().downcast_mut::<GlowRenderBackend>() {
+        renderer.set_stage_output(layout.output);
     }
+    player.set_viewport_dimensions(layout.viewport);
 }
 
 #[cfg(not(target_os = "vita"))]
@@ -274,7 +475,10 @@ pub fn main_func() {
 
     sdl2::hint::set("SDL_TOUCH_MOUSE_EVENTS", "0");
 
-    let mut axis_state = AxisState::default();
+    let mut touches = Touches::default();
+    let mut timed_inputs = TimedInputs::default();
+    // Where mapped mouse clicks happen, on the stage.
+    let mut pointer = (0.0, 0.0);
     let sdl2_context = sdl2::init().unwrap();
     let sdl2_video = sdl2_context.video().unwrap();
     let sdl2_game_controller = sdl2_context.game_controller().unwrap();
@@ -302,23 +506,49 @@ pub fn main_func() {
 
     let gl_attr = sdl2_video.gl_attr();
     gl_attr.set_context_profile(sdl2::video::GLProfile::GLES);
+    // vitaGL only speaks GLES2, everything else gets the GLES3 fast path if the driver allows it.
+    #[cfg(target_os = "vita")]
     gl_attr.set_context_version(2, 0);
+    #[cfg(not(target_os = "vita"))]
+    gl_attr.set_context_version(3, 0);
     let _ = sdl2_video.gl_set_swap_interval(0);
 
-    let config = match load_config() {
-        Ok(x) => x,
-        Err(_e) => {
-            println!("Couldn't load default config");
-            std::process::exit(1);
-        }
-    };
-
-    let (gamepad_button_mapping, swf_name, swf_url, letterbox_config) = config;
-
-    let mut controllers: Vec<sdl2::controller::GameController> = Vec::new();
+    let settings = load_config();
+
+    let Settings {
+        mappings,
+        swf_name,
+        swf_url,
+        letterbox: letterbox_config,
+        letterbox_border,
+        scaling,
+        zoom_margin,
+        line_antialiasing,
+        tessellation_cache_size,
+        rotation,
+        video_decode_threads,
+        device_fonts,
+        font_fallbacks,
+        save_clipboard,
+        mut virtual_mouse,
+        right_stick,
+        trigger_threshold,
+        left_deadzone,
+        right_deadzone,
+        dpad_directions,
+        mut touch_mouse,
+        mut rear_touch,
+        mut virtual_controls,
+        on_screen_keyboard,
+        mut motion,
+    } = settings;
+
+    // Touch input, like the virtual controls, presses buttons as the first player.
+    let button_mapping = mappings.player(0);
+    let mut controllers = Controllers::new(mappings.player_count(), motion.sensors());
     for i in 0..sdl2_joystick.num_joysticks().unwrap() {
         if sdl2_game_controller.is_game_controller(i) {
-            controllers.push(sdl2_game_controller.open(i).unwrap());
+            controllers.add(sdl2_game_controller.open(i).unwrap());
         }
     }
 
@@ -356,7 +586,17 @@ pub fn main_func() {
         .build()
         .unwrap();
 
-    let gl_context = sdl2_window.gl_create_context().unwrap();
+    let gl_context = match sdl2_window.gl_create_context() {
+        Ok(gl_context) => gl_context,
+        Err(e) => {
+            println!(
+                "Couldn't create a GLES3 context, falling back to GLES2: {}",
+                e
+            );
+            gl_attr.set_context_version(2, 0);
+            sdl2_window.gl_create_context().unwrap()
+        }
+    };
     let _ = sdl2_window.gl_make_current(&gl_context);
     let swf_name = if swf_name.is_some() {
         swf_name.unwrap()
@@ -369,22 +609,66 @@ pub fn main_func() {
         "file:///movie.swf".into()
     };
 
-    let swf_data = std::fs::read(format!("{}/{}", BASE_PATH, swf_name));
-    let movie = SwfMovie::from_data(&swf_data.unwrap(), swf_url.into(), None)
-        .map_err(|e| anyhow!(e.to_string()));
+    let swf_data = std::fs::read(format!("{}/{}", BASE_PATH, swf_name)).unwrap();
+    let movie =
+        SwfMovie::from_data(&swf_data, swf_url.into(), None).map_err(|e| anyhow!(e.to_string()));
 
     if movie.is_err() {
         println!("Couldn't load {}", format!("{}/{}", BASE_PATH, swf_name));
         std::process::exit(1);
     }
+    let movie = movie.unwrap();
+    let (movie_width, movie_height) = (movie.width().to_pixels(), movie.height().to_pixels());
+    let mut layout = StageLayout::new(
+        scaling,
+        zoom_margin,
+        dimensions,
+        rotation,
+        movie_width,
+        movie_height,
+    );
 
     // Glow can only realistically be used in vita and horizon, need
     let context = Arc::new(unsafe {
         glow::Context::from_loader_function(|s| sdl2_video.gl_get_proc_address(s) as *const _)
     });
-    let renderer = GlowRenderBackend::new(context, false, StageQuality::High).unwrap();
+    let mut renderer = GlowRenderBackend::new(context, false, StageQuality::High).unwrap();
+    renderer.set_stroke_antialiasing(line_antialiasing);
+    renderer.set_rotation(rotation);
+    renderer.set_stage_output(layout.output);
+    if let Some(border) = letterbox_border {
+        if !matches!(letterbox_config, Letterbox::Off) {
+            if let Err(e) = renderer.set_letterbox_border(border, movie_width, movie_height) {
+                println!("Couldn't set up the letterbox border: {}", e);
+            }
+        }
+    }
+    if tessellation_cache_size > 0 {
+        match ShapeCache::open(
+            std::path::Path::new(&format!("{}/{}", BASE_PATH, "cache")),
+            &swf_data,
+            tessellation_cache_size * 1024 * 1024,
+        ) {
+            Ok(shape_cache) => renderer.set_shape_cache(shape_cache),
+            Err(e) => println!("Couldn't open the tessellation cache: {}", e),
+        }
+    }
     let audio = SdlAudioBackend::new(sdl2_context.audio().unwrap()).unwrap();
-    let ui_backend = SdlUiBackend::new(Box::new(sdl2_window.clone()));
+    let fonts = FontDatabase::load(
+        std::path::Path::new(&format!("{}/{}", BASE_PATH, "fonts")),
+        device_fonts,
+        font_fallbacks,
+    );
+    let clipboard_path =
+        save_clipboard.then(|| format!("{}/{}", BASE_PATH, "clipboard.txt").into());
+    let keyboard_requests = on_screen_keyboard.then(KeyboardRequests::default);
+    let ui_backend = SdlUiBackend::new(
+        Box::new(sdl2_window.clone()),
+        fonts,
+        clipboard_path,
+        keyboard_requests.clone(),
+    );
+    let video = ThreadedVideoBackend::new(video_decode_threads);
 
     let storage_path = format!("{}/{}", BASE_PATH, "storage");
     let _ = std::fs::create_dir_all(storage_path.clone());
@@ -399,32 +683,94 @@ pub fn main_func() {
         .with_renderer(renderer)
         .with_audio(audio)
         .with_ui(ui_backend)
+        .with_video(video)
         .with_storage(Box::new(DiskStorageBackend::new(std::path::PathBuf::from(
             storage_path,
         ))))
         .with_navigator(NullNavigatorBackend::with_base_path(executor_base_path, &executor).unwrap())
-        .with_movie(movie.unwrap())
-        .with_viewport_dimensions(dimensions.width, dimensions.height, dimensions.scale_factor)
+        .with_movie(movie)
+        .with_viewport_dimensions(
+            layout.viewport.width,
+            layout.viewport.height,
+            layout.viewport.scale_factor,
+        )
         .with_fullscreen(true)
         .with_letterbox(letterbox_config)
         .with_player_runtime(ruffle_core::PlayerRuntime::AIR)
-        .with_gamepad_button_mapping(gamepad_button_mapping)
         .with_autoplay(true)
         .with_log(ConsoleLogBackend::default())
         .build();
 
+    let cursor = if virtual_mouse.is_some()
+        || touch_mouse.mode() == TouchMode::Trackpad
+        || rear_touch.mouse().is_some()
+    {
+        player
+            .lock()
+            .unwrap()
+            .renderer_mut()
+            .register_bitmap(virtual_mouse::cursor_bitmap())
+            .ok()
+    } else {
+        None
+    };
+
+    let control_bitmaps = match virtual_controls {
+        Some(_) => {
+            let mut player = player.lock().unwrap();
+            let renderer = player.renderer_mut();
+            match (
+                renderer.register_bitmap(virtual_controls::disc_bitmap()),
+                renderer.register_bitmap(virtual_controls::ring_bitmap()),
+            ) {
+                (Ok(disc), Ok(ring)) => Some(ControlBitmaps { disc, ring }),
+                _ => None,
+            }
+        }
+        None => None,
+    };
+
+    let mut on_screen_keyboard = keyboard_requests
+        .as_ref()
+        .map(|_| OnScreenKeyboard::default());
+    let keyboard_bitmaps = match on_screen_keyboard {
+        Some(_) => {
+            let mut player = player.lock().unwrap();
+            let renderer = player.renderer_mut();
+            match (
+                renderer.register_bitmap(label_bitmap(false)),
+                renderer.register_bitmap(label_bitmap(true)),
+            ) {
+                (Ok(lower), Ok(upper)) => Some(KeyboardBitmaps { lower, upper }),
+                _ => None,
+            }
+        }
+        None => None,
+    };
+
     last_frame_time = Instant::now();
     player.lock().unwrap().preload(&mut ExecutionLimit::none());
 
     let mut event_pump = sdl2_context.event_pump().unwrap();
     'main: loop {
+        // Whether what the frontend draws over the stage changed, which the player doesn't know
+        // to render.
+        let mut overlay_changed = false;
         #[cfg(target_os = "horizon")]
         {
             let (nx_width, nx_height) = sdl2_window.drawable_size();
             if nx_width != dimensions.width || nx_height != dimensions.height {
                 dimensions.width = nx_width;
                 dimensions.height = nx_height;
-                player.lock().unwrap().set_viewport_dimensions(dimensions);
+                layout = StageLayout::new(
+                    scaling,
+                    zoom_margin,
+                    dimensions,
+                    rotation,
+                    movie_width,
+                    movie_height,
+                );
+                apply_layout(&mut player.lock().unwrap(), &layout);
             }
         }
         for event in event_pump.poll_iter() {
@@ -438,7 +784,15 @@ pub fn main_func() {
                     if w > 0 && h > 0 {
                         dimensions.width = w as u32;
                         dimensions.height = h as u32;
-                        player.lock().unwrap().set_viewport_dimensions(dimensions);
+                        layout = StageLayout::new(
+                            scaling,
+                            zoom_margin,
+                            dimensions,
+                            rotation,
+                            movie_width,
+                            movie_height,
+                        );
+                        apply_layout(&mut player.lock().unwrap(), &layout);
                     }
                 }
 
@@ -446,47 +800,57 @@ pub fn main_func() {
                     timestamp: _,
                     which,
                 } => {
-                    controllers.push(sdl2_game_controller.open(which).unwrap());
+                    controllers.add(sdl2_game_controller.open(which).unwrap());
                 }
 
                 sdl2::event::Event::ControllerDeviceRemoved {
                     timestamp: _,
                     which,
                 } => {
-                    if let Some(pos) = controllers.iter().position(|c| c.instance_id() == which) {
-                        controllers.remove(pos); // drops the controller -> SDL closes it
+                    if controllers.pad(which).is_some_and(|pad| pad.player == 0) {
+                        let mut player = player.lock().unwrap();
+                        for (target, pressed) in motion.stop() {
+                            press_target(
+                                &mut player,
+                                button_mapping,
+                                &mut timed_inputs,
+                                &target,
+                                pressed,
+                                pointer,
+                            );
+                        }
                     }
-                }
-
-                sdl2::event::Event::ControllerButtonDown {
-                    timestamp: _,
-                    which: _,
-                    button,
-                } => {
-                    let ruffle_button = sdl_gamepadbutton_to_ruffle(button);
-                    if let Some(ruffle_button) = ruffle_button {
-                        player
-                            .lock()
-                            .unwrap()
-                            .handle_event(PlayerEvent::GamepadButtonDown {
-                                button: ruffle_button,
-                            });
+                    // Let go of whatever the controller held, turbo included.
+                    let mut player = player.lock().unwrap();
+                    for pad_input in controllers.remove(which) {
+                        overlay_changed |= send_pad_input(
+                            &mut player,
+                            &mappings,
+                            &mut timed_inputs,
+                            &mut on_screen_keyboard,
+                            virtual_mouse.as_ref(),
+                            &layout,
+                            pointer,
+                            pad_input,
+                        );
                     }
                 }
 
-                sdl2::event::Event::ControllerButtonUp {
-                    timestamp: _,
-                    which: _,
-                    button,
-                } => {
-                    let ruffle_button = sdl_gamepadbutton_to_ruffle(button);
-                    if let Some(ruffle_button) = ruffle_button {
-                        player
-                            .lock()
-                            .unwrap()
-                            .handle_event(PlayerEvent::GamepadButtonUp {
-                                button: ruffle_button,
-                            });
+                sdl2::event::Event::ControllerButtonDown { which, button, .. }
+                | sdl2::event::Event::ControllerButtonUp { which, button, .. } => {
+                    let pressed = matches!(event, sdl2::event::Event::ControllerButtonDown { .. });
+                    let mut player = player.lock().unwrap();
+                    for pad_input in controllers.button(which, button, pressed) {
+                        overlay_changed |= send_pad_input(
+                            &mut player,
+                            &mappings,
+                            &mut timed_inputs,
+                            &mut on_screen_keyboard,
+                            virtual_mouse.as_ref(),
+                            &layout,
+                            pointer,
+                            pad_input,
+                        );
                     }
                 }
 
@@ -501,10 +865,12 @@ pub fn main_func() {
                     xrel: _,
                     yrel: _,
                 } => {
-                    player.lock().unwrap().handle_event(PlayerEvent::MouseMove {
-                        x: x.into(),
-                        y: y.into(),
-                    });
+                    let (x, y) = layout.screen_to_stage(x.into(), y.into());
+                    pointer = (x, y);
+                    player
+                        .lock()
+                        .unwrap()
+                        .handle_event(PlayerEvent::MouseMove { x, y });
                 }
 
                 #[cfg(not(any(target_os = "horizon", target_os = "vita")))]
@@ -519,9 +885,11 @@ pub fn main_func() {
                 } => {
                     let ruffle_button = sdl_mousebutton_to_ruffle(mouse_btn);
                     if let Some(ruffle_button) = ruffle_button {
+                        let (x, y) = layout.screen_to_stage(x.into(), y.into());
+                        pointer = (x, y);
                         player.lock().unwrap().handle_event(PlayerEvent::MouseDown {
-                            x: x.into(),
-                            y: y.into(),
+                            x,
+                            y,
                             button: ruffle_button,
                             index: None,
                         });
@@ -540,9 +908,11 @@ pub fn main_func() {
                 } => {
                     let ruffle_button = sdl_mousebutton_to_ruffle(mouse_btn);
                     if let Some(ruffle_button) = ruffle_button {
+                        let (x, y) = layout.screen_to_stage(x.into(), y.into());
+                        pointer = (x, y);
                         player.lock().unwrap().handle_event(PlayerEvent::MouseUp {
-                            x: x.into(),
-                            y: y.into(),
+                            x,
+                            y,
                             button: ruffle_button,
                         });
                     }
@@ -550,136 +920,286 @@ pub fn main_func() {
 
                 sdl2::event::Event::FingerMotion {
                     timestamp: _,
-                    touch_id: _,
-                    finger_id: _,
+                    touch_id,
+                    finger_id,
                     x,
                     y,
                     dx: _,
                     dy: _,
                     pressure: _,
                 } => {
-                    player.lock().unwrap().handle_event(PlayerEvent::MouseMove {
-                        x: x as f64 * dimensions.width as f64,
-                        y: y as f64 * dimensions.height as f64,
-                    });
+                    if rear_touch::is_touchpad(touch_id) {
+                        overlay_changed |= rear_touch_finger(
+                            &mut player.lock().unwrap(),
+                            &mut rear_touch,
+                            button_mapping,
+                            &mut timed_inputs,
+                            &mut pointer,
+                            (TouchPhase::Move, touch_id, finger_id),
+                            (x, y),
+                            &layout,
+                        );
+                        continue;
+                    }
+                    if on_screen_keyboard.as_mut().is_some_and(|keyboard| {
+                        keyboard.finger_motion(
+                            touch_id,
+                            finger_id,
+                            layout.finger_to_rotated(x, y),
+                            &layout,
+                        )
+                    }) {
+                        overlay_changed = true;
+                        continue;
+                    }
+                    if virtual_controls_finger(
+                        &mut player.lock().unwrap(),
+                        &mut virtual_controls,
+                        button_mapping,
+                        &mut timed_inputs,
+                        pointer,
+                        (TouchPhase::Move, touch_id, finger_id),
+                        layout.finger_to_rotated(x, y),
+                        &layout,
+                    ) {
+                        overlay_changed = true;
+                        continue;
+                    }
+                    if let Some(touch) = touches.finger_motion(touch_id, finger_id) {
+                        let (finger_x, finger_y) = (x, y);
+                        let (x, y) = layout.finger_to_stage(x, y);
+                        let mut player = player.lock().unwrap();
+                        player.handle_event(touch::touch_event(TouchPhase::Move, touch, x, y));
+                        let events = touch_mouse.touch(
+                            TouchPhase::Move,
+                            touch,
+                            layout.finger_to_rotated(finger_x, finger_y),
+                            Instant::now(),
+                            &layout,
+                        );
+                        send_mouse_events(&mut player, events, &mut pointer);
+                        overlay_changed |= touch_mouse.mode() == TouchMode::Trackpad;
+                    }
                 }
 
                 sdl2::event::Event::FingerDown {
                     timestamp: _,
-                    touch_id: _,
-                    finger_id: _,
+                    touch_id,
+                    finger_id,
                     x,
                     y,
                     dx: _,
                     dy: _,
                     pressure: _,
                 } => {
-                    player.lock().unwrap().handle_event(PlayerEvent::MouseDown {
-                        x: x as f64 * dimensions.width as f64,
-                        y: y as f64 * dimensions.height as f64,
-                        button: MouseButton::Left,
-                        index: None,
-                    });
+                    if rear_touch::is_touchpad(touch_id) {
+                        overlay_changed |= rear_touch_finger(
+                            &mut player.lock().unwrap(),
+                            &mut rear_touch,
+                            button_mapping,
+                            &mut timed_inputs,
+                            &mut pointer,
+                            (TouchPhase::Begin, touch_id, finger_id),
+                            (x, y),
+                            &layout,
+                        );
+                        continue;
+                    }
+                    if on_screen_keyboard.as_mut().is_some_and(|keyboard| {
+                        keyboard.finger_down(
+                            touch_id,
+                            finger_id,
+                            layout.finger_to_rotated(x, y),
+                            &layout,
+                        )
+                    }) {
+                        overlay_changed = true;
+                        continue;
+                    }
+                    if virtual_controls_finger(
+                        &mut player.lock().unwrap(),
+                        &mut virtual_controls,
+                        button_mapping,
+                        &mut timed_inputs,
+                        pointer,
+                        (TouchPhase::Begin, touch_id, finger_id),
+                        layout.finger_to_rotated(x, y),
+                        &layout,
+                    ) {
+                        overlay_changed = true;
+                        continue;
+                    }
+                    let (finger_x, finger_y) = (x, y);
+                    let (x, y) = layout.finger_to_stage(x, y);
+                    // Fingers that go down on the border around the stage are left alone, unless
+                    // they only move the trackpad's pointer.
+                    if !layout.contains(x, y) && touch_mouse.mode() != TouchMode::Trackpad {
+                        continue;
+                    }
+                    let touch = touches.finger_down(touch_id, finger_id);
+                    let mut player = player.lock().unwrap();
+                    player.handle_event(touch::touch_event(TouchPhase::Begin, touch, x, y));
+                    let events = touch_mouse.touch(
+                        TouchPhase::Begin,
+                        touch,
+                        layout.finger_to_rotated(finger_x, finger_y),
+                        Instant::now(),
+                        &layout,
+                    );
+                    send_mouse_events(&mut player, events, &mut pointer);
+                    overlay_changed |= touch_mouse.mode() == TouchMode::Trackpad;
                 }
 
                 sdl2::event::Event::FingerUp {
                     timestamp: _,
-                    touch_id: _,
-                    finger_id: _,
+                    touch_id,
+                    finger_id,
                     x,
                     y,
                     dx: _,
                     dy: _,
                     pressure: _,
                 } => {
-                    player.lock().unwrap().handle_event(PlayerEvent::MouseUp {
-                        x: x as f64 * dimensions.width as f64,
-                        y: y as f64 * dimensions.height as f64,
-                        button: MouseButton::Left,
-                    });
+                    if rear_touch::is_touchpad(touch_id) {
+                        overlay_changed |= rear_touch_finger(
+                            &mut player.lock().unwrap(),
+                            &mut rear_touch,
+                            button_mapping,
+                            &mut timed_inputs,
+                            &mut pointer,
+                            (TouchPhase::End, touch_id, finger_id),
+                            (x, y),
+                            &layout,
+                        );
+                        continue;
+                    }
+                    if let Some(events) = on_screen_keyboard.as_mut().and_then(|keyboard| {
+                        keyboard.finger_up(
+                            touch_id,
+                            finger_id,
+                            layout.finger_to_rotated(x, y),
+                            &layout,
+                        )
+                    }) {
+                        let mut player = player.lock().unwrap();
+                        for event in events {
+                            player.handle_event(event);
+                        }
+                        overlay_changed = true;
+                        continue;
+                    }
+                    if virtual_controls_finger(
+                        &mut player.lock().unwrap(),
+                        &mut virtual_controls,
+                        button_mapping,
+                        &mut timed_inputs,
+                        pointer,
+                        (TouchPhase::End, touch_id, finger_id),
+                        layout.finger_to_rotated(x, y),
+                        &layout,
+                    ) {
+                        overlay_changed = true;
+                        continue;
+                    }
+                    if let Some(touch) = touches.finger_up(touch_id, finger_id) {
+                        let (finger_x, finger_y) = (x, y);
+                        let (x, y) = layout.finger_to_stage(x, y);
+                        let mut player = player.lock().unwrap();
+                        player.handle_event(touch::touch_event(TouchPhase::End, touch, x, y));
+                        let events = touch_mouse.touch(
+                            TouchPhase::End,
+                            touch,
+                            layout.finger_to_rotated(finger_x, finger_y),
+                            Instant::now(),
+                            &layout,
+                        );
+                        send_mouse_events(&mut player, events, &mut pointer);
+                        overlay_changed |= touch_mouse.mode() == TouchMode::Trackpad;
+                    }
                 }
 
                 sdl2::event::Event::ControllerAxisMotion {
                     timestamp: _,
-                    which: _,
+                    which,
                     axis,
                     value,
                 } => {
-                    let x_axis = axis == Axis::LeftX;
-                    let y_axis = axis == Axis::LeftY;
-                    let deadzone = 8000;
-                    let left = if x_axis {
-                        value < -deadzone
-                    } else {
-                        axis_state.left
-                    };
-                    let right = if x_axis {
-                        value > deadzone
-                    } else {
-                        axis_state.right
-                    };
-                    let up = if y_axis {
-                        value < -deadzone
-                    } else {
-                        axis_state.up
-                    };
-                    let down = if y_axis {
-                        value > deadzone
-                    } else {
-                        axis_state.down
+                    // The virtual mouse follows the first player's controller.
+                    if let Some(mouse) = &mut virtual_mouse {
+                        if controllers.player(which) == 0 && mouse.axis_motion(axis, value) {
+                            continue;
+                        }
+                    }
+                    let Some(pad) = controllers.pad_mut(which) else {
+                        continue;
                     };
-
-                    if up != axis_state.up {
-                        let event_up = if up {
-                            PlayerEvent::GamepadButtonDown {
-                                button: GamepadButton::DPadUp,
-                            }
-                        } else {
-                            PlayerEvent::GamepadButtonUp {
-                                button: GamepadButton::DPadUp,
-                            }
-                        };
-                        axis_state.up = up;
-                        player.lock().unwrap().handle_event(event_up);
+                    let pad_mapping = mappings.player(pad.player);
+                    if matches!(axis, Axis::TriggerLeft | Axis::TriggerRight) {
+                        if let Some((button, pressed)) =
+                            pad.trigger_state
+                                .axis_motion(axis, value, trigger_threshold)
+                        {
+                            press_button(
+                                &mut player.lock().unwrap(),
+                                pad_mapping,
+                                &mut timed_inputs,
+                                Input::Gamepad(button),
+                                pressed,
+                                pointer,
+                            );
+                        }
+                        continue;
                     }
-                    if down != axis_state.down {
-                        let event_down = if down {
-                            PlayerEvent::GamepadButtonDown {
-                                button: GamepadButton::DPadDown,
-                            }
+                    let (axis_state, deadzone, horizontal) = match axis {
+                        Axis::LeftX | Axis::LeftY => {
+                            (&mut pad.axis_state, &left_deadzone, axis == Axis::LeftX)
+                        }
+                        Axis::RightX | Axis::RightY if right_stick == RightStickMode::DPad => (
+                            &mut pad.right_axis_state,
+                            &right_deadzone,
+                            axis == Axis::RightX,
+                        ),
+                        _ => continue,
+                    };
+                    let buttons =
+                        axis_state.axis_motion(horizontal, value, deadzone, dpad_directions);
+                    for (button, pressed) in buttons {
+                        let mut player = player.lock().unwrap();
+                        if keyboard_button(&mut player, &mut on_screen_keyboard, button, pressed) {
+                            overlay_changed = true;
                         } else {
-                            PlayerEvent::GamepadButtonUp {
-                                button: GamepadButton::DPadDown,
-                            }
-                        };
-                        axis_state.down = down;
-                        player.lock().unwrap().handle_event(event_down);
+                            press_button(
+                                &mut player,
+                                pad_mapping,
+                                &mut timed_inputs,
+                                Input::Gamepad(button),
+                                pressed,
+                                pointer,
+                            );
+                        }
                     }
-                    if left != axis_state.left {
-                        let event_left = if left {
-                            PlayerEvent::GamepadButtonDown {
-                                button: GamepadButton::DPadLeft,
-                            }
-                        } else {
-                            PlayerEvent::GamepadButtonUp {
-                                button: GamepadButton::DPadLeft,
-                            }
-                        };
-                        axis_state.left = left;
-                        player.lock().unwrap().handle_event(event_left);
+                }
+
+                sdl2::event::Event::ControllerSensorUpdated {
+                    timestamp: _,
+                    which,
+                    sensor,
+                    data,
+                } => {
+                    // Motion controls follow the first player's controller.
+                    if controllers.player(which) != 0 {
+                        continue;
                     }
-                    if right != axis_state.right {
-                        let event_right = if right {
-                            PlayerEvent::GamepadButtonDown {
-                                button: GamepadButton::DPadRight,
-                            }
-                        } else {
-                            PlayerEvent::GamepadButtonUp {
-                                button: GamepadButton::DPadRight,
-                            }
-                        };
-                        axis_state.right = right;
-                        player.lock().unwrap().handle_event(event_right);
+                    let mut player = player.lock().unwrap();
+                    for (target, pressed) in motion.sensor_update(sensor, data) {
+                        press_target(
+                            &mut player,
+                            button_mapping,
+                            &mut timed_inputs,
+                            &target,
+                            pressed,
+                            pointer,
+                        );
                     }
                 }
 
@@ -692,14 +1212,29 @@ pub fn main_func() {
                     }
                 }
 
-                sdl2::event::Event::KeyDown { scancode, .. } => {
-                    if scancode == Some(sdl2::keyboard::Scancode::Backspace) {
-                        player
-                            .lock()
-                            .unwrap()
-                            .handle_event(PlayerEvent::TextControl {
-                                code: TextControlCode::Backspace,
-                            });
+                sdl2::event::Event::KeyDown {
+                    scancode: Some(scancode),
+                    keycode,
+                    keymod,
+                    ..
+                } => {
+                    let mut player = player.lock().unwrap();
+                    if let Some(event) = keyboard::key_event(true, scancode, keycode, keymod) {
+                        player.handle_event(event);
+                    }
+                    if let Some(code) = keyboard::text_control(scancode, keycode, keymod) {
+                        player.handle_event(PlayerEvent::TextControl { code });
+                    }
+                }
+
+                sdl2::event::Event::KeyUp {
+                    scancode: Some(scancode),
+                    keycode,
+                    keymod,
+                    ..
+                } => {
+                    if let Some(event) = keyboard::key_event(false, scancode, keycode, keymod) {
+                        player.lock().unwrap().handle_event(event);
                     }
                 }
                 _ => {}
@@ -711,8 +1246,59 @@ pub fn main_func() {
         if dt > 0 {
             last_frame_time = new_time;
             if let Ok(mut player) = player.lock() {
+                if let (Some(requests), Some(keyboard)) =
+                    (&keyboard_requests, &mut on_screen_keyboard)
+                {
+                    if let Some(open) = requests.take() {
+                        keyboard.set_open(open);
+                        overlay_changed = true;
+                    }
+                }
+                let events = touch_mouse.update(new_time, &layout);
+                send_mouse_events(&mut player, events, &mut pointer);
+                if let Some(mouse) = rear_touch.mouse() {
+                    let events = mouse.update(new_time, &layout);
+                    send_mouse_events(&mut player, events, &mut pointer);
+                }
+                let mut cursors = vec![];
+                if let Some(mouse) = &mut virtual_mouse {
+                    let dt = dt as f64 / 1_000_000.0;
+                    let (dx, dy) = motion.pointer_motion(dt, &layout);
+                    let moved = mouse.update(dt, &layout);
+                    if mouse.nudge(dx, dy, &layout) || moved {
+                        let (x, y) = mouse.stage_position(&layout);
+                        pointer = (x, y);
+                        player.handle_event(PlayerEvent::MouseMove { x, y });
+                        overlay_changed = true;
+                    }
+                    cursors.push(mouse.screen_position(&layout));
+                }
+                for (actions, pressed) in timed_inputs.update(new_time) {
+                    send_actions(&mut player, &actions, pressed, pointer);
+                }
+                cursors.extend(touch_mouse.cursor(&layout));
+                cursors.extend(rear_touch.mouse().and_then(|mouse| mouse.cursor(&layout)));
+                let mut overlay = vec![];
+                if let (Some(controls), Some(bitmaps)) = (&virtual_controls, &control_bitmaps) {
+                    overlay.extend(controls.overlay(bitmaps, &layout));
+                }
+                if let (Some(keyboard), Some(bitmaps)) = (&on_screen_keyboard, &keyboard_bitmaps) {
+                    overlay.extend(keyboard.overlay(bitmaps, &layout));
+                }
+                if let Some(cursor) = &cursor {
+                    if player.ui().mouse_visible() {
+                        overlay.extend(
+                            cursors
+                                .into_iter()
+                                .map(|(x, y)| virtual_mouse::cursor_overlay(cursor, x, y, &layout)),
+                        );
+                    }
+                }
+                if let Some(renderer) = player.renderer_mut().downcast_mut::<GlowRenderBackend>() {
+                    renderer.set_overlay(overlay);
+                }
                 player.tick(dt as f64 / 1000.0);
-                if player.needs_render() {
+                if player.needs_render() || overlay_changed {
                     player.render();
                     sdl2_window.gl_swap_window();
                 }
@@ -722,6 +1308,261 @@ pub fn main_func() {
     drop(controllers);
 }
 
+/// Handles a finger on the rear touchpad, at `position` normalized to the touchpad. Returns
+/// whether it moved the trackpad's pointer.
+#[allow(clippy::too_many_arguments)]
+fn rear_touch_finger(
+    player: &mut Player,
+    rear_touch: &mut RearTouch,
+    mapping: &ButtonMapping,
+    timed: &mut TimedInputs,
+    pointer: &mut (f64, f64),
+    (phase, touch_id, finger_id): (TouchPhase, i64, i64),
+    (x, y): (f32, f32),
+    layout: &StageLayout,
+) -> bool {
+    match rear_touch {
+        RearTouch::Unused => false,
+        RearTouch::Trackpad { .. } => {
+            let events = rear_touch.trackpad_finger(
+                phase,
+                touch_id,
+                finger_id,
+                x,
+                y,
+                Instant::now(),
+                layout,
+            );
+            send_mouse_events(player, events, pointer);
+            true
+        }
+        RearTouch::Regions { .. } => {
+            for (target, pressed) in rear_touch.region_finger(phase, touch_id, finger_id, x, y) {
+                press_target(player, mapping, timed, &target, pressed, *pointer);
+            }
+            false
+        }
+    }
+}
+
+/// Gives a controller button to the on-screen keyboard. Returns whether it took it.
+fn keyboard_button(
+    player: &mut Player,
+    keyboard: &mut Option<OnScreenKeyboard>,
+    button: GamepadButton,
+    pressed: bool,
+) -> bool {
+    let Some(events) = keyboard
+        .as_mut()
+        .and_then(|keyboard| keyboard.button(button, pressed))
+    else {
+        return false;
+    };
+    for event in events {
+        player.handle_event(event);
+    }
+    true
+}
+
+/// Handles a finger on the touch screen, at `position` in screen pixels in the stage's
+/// orientation. Returns whether it's on the virtual controls.
+#[allow(clippy::too_many_arguments)]
+fn virtual_controls_finger(
+    player: &mut Player,
+    virtual_controls: &mut Option<VirtualControls>,
+    mapping: &ButtonMapping,
+    timed: &mut TimedInputs,
+    pointer: (f64, f64),
+    (phase, touch_id, finger_id): (TouchPhase, i64, i64),
+    position: (f64, f64),
+    layout: &StageLayout,
+) -> bool {
+    let Some(controls) = virtual_controls else {
+        return false;
+    };
+    let changes = match phase {
+        TouchPhase::Begin => controls.finger_down(touch_id, finger_id, position, layout),
+        TouchPhase::Move => controls.finger_motion(touch_id, finger_id, position, layout),
+        TouchPhase::End => controls.finger_up(touch_id, finger_id, layout),
+    };
+    let Some(changes) = changes else {
+        return false;
+    };
+    for (target, pressed) in changes {
+        press_target(player, mapping, timed, &target, pressed, pointer);
+    }
+    true
+}
+
+/// Sends mouse events, keeping track of where the mouse is on the stage.
+fn send_mouse_events(player: &mut Player, events: Vec<PlayerEvent>, pointer: &mut (f64, f64)) {
+    for event in events {
+        if let PlayerEvent::MouseMove { x, y }
+        | PlayerEvent::MouseDown { x, y, .. }
+        | PlayerEvent::MouseUp { x, y, .. } = event
+        {
+            *pointer = (x, y);
+        }
+        player.handle_event(event);
+    }
+}
+
+/// Sends what a controller button is mapped to, or the button itself when it isn't mapped.
+/// Mapped mouse clicks happen at `pointer`, on the stage.
+fn press_button(
+    player: &mut Player,
+    mapping: &ButtonMapping,
+    timed: &mut TimedInputs,
+    input: Input,
+    pressed: bool,
+    pointer: (f64, f64),
+) {
+    match mapping.binding(input) {
+        Some(binding) => press_binding(player, timed, binding, pressed, pointer),
+        None => {
+            if let Input::Gamepad(button) = input {
+                player.handle_event(if pressed {
+                    PlayerEvent::GamepadButtonDown { button }
+                } else {
+                    PlayerEvent::GamepadButtonUp { button }
+                });
+            }
+        }
+    }
+}
+
+/// Passes on an input of a controller: to the on-screen keyboard while it's open, as a click of
+/// the virtual mouse for the first player, or as what the player's mapping binds it to. Returns
+/// whether the on-screen keyboard changed.
+#[allow(clippy::too_many_arguments)]
+fn send_pad_input(
+    player: &mut Player,
+    mappings: &PlayerMappings,
+    timed: &mut TimedInputs,
+    keyboard: &mut Option<OnScreenKeyboard>,
+    virtual_mouse: Option<&VirtualMouse>,
+    layout: &StageLayout,
+    pointer: (f64, f64),
+    pad_input: PadInput,
+) -> bool {
+    let (slot, button, pressed) = match pad_input {
+        PadInput::Button {
+            player: slot,
+            button,
+            pressed,
+        } => (slot, button, pressed),
+        PadInput::ReleaseAnalog {
+            player: slot,
+            button,
+        } => {
+            press_button(
+                player,
+                mappings.player(slot),
+                timed,
+                Input::Gamepad(button),
+                false,
+                pointer,
+            );
+            return false;
+        }
+    };
+    let input = match sdl_gamepadbutton_to_ruffle(button) {
+        Some(button) => Input::Gamepad(button),
+        None => Input::Extra(button),
+    };
+    if let Input::Gamepad(button) = input {
+        if keyboard_button(player, keyboard, button, pressed) {
+            return true;
+        }
+    }
+    let click = match (virtual_mouse, input) {
+        (Some(mouse), Input::Gamepad(button)) if slot == 0 => mouse.button(button),
+        _ => None,
+    };
+    let pointer = virtual_mouse.map_or(pointer, |mouse| mouse.stage_position(layout));
+    match click {
+        Some(button) if pressed => player.handle_event(PlayerEvent::MouseDown {
+            x: pointer.0,
+            y: pointer.1,
+            button,
+            index: None,
+        }),
+        Some(button) => player.handle_event(PlayerEvent::MouseUp {
+            x: pointer.0,
+            y: pointer.1,
+            button,
+        }),
+        None => press_button(
+            player,
+            mappings.player(slot),
+            timed,
+            input,
+            pressed,
+            pointer,
+        ),
+    }
+    false
+}
+
+/// Presses or releases what an on-screen control or a touchpad region is set to.
+fn press_target(
+    player: &mut Player,
+    mapping: &ButtonMapping,
+    timed: &mut TimedInputs,
+    target: &Target,
+    pressed: bool,
+    pointer: (f64, f64),
+) {
+    match target {
+        Target::Button(input) => press_button(player, mapping, timed, *input, pressed, pointer),
+        Target::Binding(binding) => press_binding(player, timed, binding, pressed, pointer),
+    }
+}
+
+/// Presses or releases a button bound to `binding`. Turbo and macros go on in `timed`.
+fn press_binding(
+    player: &mut Player,
+    timed: &mut TimedInputs,
+    binding: &Binding,
+    pressed: bool,
+    pointer: (f64, f64),
+) {
+    match binding {
+        Binding::Hold(actions) => send_actions(player, actions, pressed, pointer),
+        Binding::Turbo { actions, period } => {
+            if let Some((actions, pressed)) = timed.turbo(actions, *period, pressed, Instant::now())
+            {
+                send_actions(player, &actions, pressed, pointer);
+            }
+        }
+        Binding::Macro(steps) => {
+            if pressed {
+                let actions = timed.play(steps, Instant::now());
+                send_actions(player, &actions, true, pointer);
+            }
+        }
+    }
+}
+
+/// Presses or releases the keys and mouse buttons of `actions`. Mouse clicks happen at `pointer`,
+/// on the stage.
+fn send_actions(player: &mut Player, actions: &[Action], pressed: bool, pointer: (f64, f64)) {
+    let (x, y) = pointer;
+    for action in actions {
+        let event = match *action {
+            Action::Key(key_code) => gamepad::key_event(key_code, pressed),
+            Action::Mouse(button) if pressed => PlayerEvent::MouseDown {
+                x,
+                y,
+                button,
+                index: None,
+            },
+            Action::Mouse(button) => PlayerEvent::MouseUp { x, y, button },
+        };
+        player.handle_event(event);
+    }
+}
+
 fn sdl_gamepadbutton_to_ruffle(button: sdl2::controller::Button) -> Option<GamepadButton> {
     return match button {
         sdl2::controller::Button::DPadUp => Some(GamepadButton::DPadUp),
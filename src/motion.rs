@@ -0,0 +1,392 @@
+//! Motion controls, from the first player's controller: turning it moves the virtual mouse
+//! pointer, and tilting it presses buttons or keys.
+//!
+//! Tilt is measured from how the controller was held when its first reading came in, so that
+//! holding it at a comfortable angle doesn't count as tilting.
+
+use std::str::FromStr;
+
+use sdl2::sensor::SensorType;
+use serde::Deserialize;
+
+use crate::input::{BindingConfig, Target};
+use crate::scaling::StageLayout;
+
+/// Default pointer speed, in screen heights per radian turned.
+const DEFAULT_GYRO_SPEED: f64 = 1.0;
+
+/// Default angle a tilt has to reach to press, in degrees.
+const DEFAULT_TILT_ANGLE: u32 = 20;
+
+/// How far back a tilt has to come to release, in degrees, so that shaky hands don't repeat it.
+const TILT_RELEASE_MARGIN: f64 = 5.0;
+
+/// Turning slower than this, in radians per second, doesn't move the pointer, which keeps the
+/// gyroscope's drift from moving it.
+const GYRO_DEADZONE: f64 = 0.02;
+
+/// The `motion` section of `config.ron`.
+#[derive(Debug, Default, Deserialize)]
+pub struct MotionConfig {
+    /// Whether turning the controller moves the virtual mouse pointer.
+    gyro_mouse: Option<bool>,
+    /// Pointer speed, in screen heights per radian turned.
+    gyro_speed: Option<f64>,
+    tilt: Option<Vec<TiltConfig>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TiltConfig {
+    /// "left", "right", "forward" or "back".
+    direction: String,
+    /// In degrees.
+    angle: Option<u32>,
+    /// A controller button, named like in `gamepad_config`, which does what it's mapped to.
+    button: Option<String>,
+    /// Keys or mouse buttons, like in `gamepad_config`.
+    key: Option<BindingConfig>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TiltDirection {
+    Left,
+    Right,
+    Forward,
+    Back,
+}
+
+impl FromStr for TiltDirection {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(TiltDirection::Left),
+            "right" => Ok(TiltDirection::Right),
+            "forward" => Ok(TiltDirection::Forward),
+            "back" => Ok(TiltDirection::Back),
+            _ => Err(()),
+        }
+    }
+}
+
+struct Tilt {
+    direction: TiltDirection,
+    /// In degrees.
+    angle: f64,
+    target: Target,
+    pressed: bool,
+}
+
+impl Tilt {
+    /// Reads a tilt, printing why it can't be used if so.
+    fn from_config(config: TiltConfig, number: usize) -> Option<Self> {
+        let what = format!("motion: tilt {}", number);
+        let Ok(direction) = TiltDirection::from_str(&config.direction) else {
+            println!(
+                "{} has unsupported direction {}, expected left, right, forward or back",
+                what, config.direction
+            );
+            return None;
+        };
+        Some(Self {
+            direction,
+            angle: config.angle.unwrap_or(DEFAULT_TILT_ANGLE) as f64,
+            target: Target::from_config(config.button, config.key, &what)?,
+            pressed: false,
+        })
+    }
+}
+
+pub struct Motion {
+    /// Pointer speed, if the gyroscope moves the pointer.
+    gyro_speed: Option<f64>,
+    /// How fast the controller is turning, in radians per second, as yaw and pitch.
+    turning: (f64, f64),
+    tilts: Vec<Tilt>,
+    /// The roll and pitch the controller was held at when the first reading came in, in degrees.
+    neutral: Option<(f64, f64)>,
+}
+
+impl Motion {
+    /// Reads the `motion` section of the config, printing what's wrong with it and leaving out
+    /// what can't be used.
+    pub fn from_config(config: MotionConfig) -> Self {
+        let gyro_speed = config
+            .gyro_mouse
+            .unwrap_or(false)
+            .then(|| config.gyro_speed.unwrap_or(DEFAULT_GYRO_SPEED).max(0.0));
+        Self {
+            gyro_speed,
+            turning: (0.0, 0.0),
+            tilts: config
+                .tilt
+                .unwrap_or_default()
+                .into_iter()
+                .enumerate()
+                .filter_map(|(index, tilt)| Tilt::from_config(tilt, index + 1))
+                .collect(),
+            neutral: None,
+        }
+    }
+
+    /// Whether the gyroscope moves the pointer.
+    pub fn gyro_mouse(&self) -> bool {
+        self.gyro_speed.is_some()
+    }
+
+    /// The sensors that have to be turned on for what's configured.
+    pub fn sensors(&self) -> Vec<SensorType> {
+        let mut sensors = vec![];
+        if self.gyro_mouse() {
+            sensors.push(SensorType::Gyroscope);
+        }
+        if !self.tilts.is_empty() {
+            sensors.push(SensorType::Accelerometer);
+        }
+        sensors
+    }
+
+    /// Takes a reading of a sensor, returning the tilts it pressed or released, and whether they
+    /// are now pressed.
+    pub fn sensor_update(&mut self, sensor: SensorType, data: [f32; 3]) -> Vec<(Target, bool)> {
+        let [x, y, z] = data.map(f64::from);
+        match sensor {
+            SensorType::Gyroscope => {
+                self.turning = (y, x);
+                vec![]
+            }
+            SensorType::Accelerometer => self.tilt(x, y, z),
+            _ => vec![],
+        }
+    }
+
+    /// Forgets the controller's motion once it's gone, returning the tilts to release. The next
+    /// controller is measured from how it's held at first.
+    pub fn stop(&mut self) -> Vec<(Target, bool)> {
+        self.turning = (0.0, 0.0);
+        self.neutral = None;
+        self.tilts
+            .iter_mut()
+            .filter(|tilt| tilt.pressed)
+            .map(|tilt| {
+                tilt.pressed = false;
+                (tilt.target.clone(), false)
+            })
+            .collect()
+    }
+
+    /// Follows the direction of gravity, in m/s² along the controller's axes.
+    fn tilt(&mut self, x: f64, y: f64, z: f64) -> Vec<(Target, bool)> {
+        if x == 0.0 && y == 0.0 && z == 0.0 {
+            return vec![];
+        }
+        // Gravity leans to the left when the right side goes down, and towards the player when
+        // the far side does.
+        let roll = (-x).atan2(y).to_degrees();
+        let pitch = z.atan2(y).to_degrees();
+        let (neutral_roll, neutral_pitch) = *self.neutral.get_or_insert((roll, pitch));
+        let (roll, pitch) = (roll - neutral_roll, pitch - neutral_pitch);
+
+        let mut changes = vec![];
+        for tilt in &mut self.tilts {
+            let angle = match tilt.direction {
+                TiltDirection::Left => -roll,
+                TiltDirection::Right => roll,
+                TiltDirection::Forward => pitch,
+                TiltDirection::Back => -pitch,
+            };
+            let pressed = if tilt.pressed {
+                angle > tilt.angle - TILT_RELEASE_MARGIN
+            } else {
+                angle >= tilt.angle
+            };
+            if pressed != tilt.pressed {
+                tilt.pressed = pressed;
+                changes.push((tilt.target.clone(), pressed));
+            }
+        }
+        changes
+    }
+
+    /// How far the gyroscope moves the pointer in `dt` seconds, in screen pixels.
+    pub fn pointer_motion(&self, dt: f64, layout: &StageLayout) -> (f64, f64) {
+        let Some(speed) = self.gyro_speed else {
+            return (0.0, 0.0);
+        };
+        let (_, height) = layout.rotated_screen_size();
+        let scale = speed * height as f64 * dt;
+        // Turning left and pitching up both count up from SDL, and move the pointer left and up.
+        let (yaw, pitch) = self.turning;
+        let motion = |rate: f64| {
+            if rate.abs() < GYRO_DEADZONE {
+                0.0
+            } else {
+                -rate * scale
+            }
+        };
+        (motion(yaw), motion(pitch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ruffle_core::ViewportDimensions;
+    use ruffle_core::events::GamepadButton;
+    use ruffle_render_glow::ScreenRotation;
+
+    use super::*;
+    use crate::input::Input;
+    use crate::scaling::ScalingMode;
+
+    fn configured(gyro_mouse: bool, tilts: &[(&str, &str)]) -> Motion {
+        Motion::from_config(MotionConfig {
+            gyro_mouse: Some(gyro_mouse),
+            gyro_speed: None,
+            tilt: Some(
+                tilts
+                    .iter()
+                    .map(|(direction, button)| TiltConfig {
+                        direction: direction.to_string(),
+                        angle: None,
+                        button: Some(button.to_string()),
+                        key: None,
+                    })
+                    .collect(),
+            ),
+        })
+    }
+
+    const G: f32 = 9.81;
+
+    /// An accelerometer reading of the controller lying still and flat. SDL's axes point to the
+    /// right, up out of the controller's face and towards the player, and the reading is the
+    /// opposite of gravity, so it points up.
+    const FLAT: [f32; 3] = [0.0, G, 0.0];
+
+    /// A reading of the controller with its right side lowered by `degrees`.
+    fn rolled_right(degrees: f32) -> [f32; 3] {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        [-G * sin, G * cos, 0.0]
+    }
+
+    /// A reading of the controller with its far side lowered by `degrees`.
+    fn pitched_forward(degrees: f32) -> [f32; 3] {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        [0.0, G * cos, G * sin]
+    }
+
+    fn tilt(motion: &mut Motion, reading: [f32; 3]) -> Vec<(GamepadButton, bool)> {
+        motion
+            .sensor_update(SensorType::Accelerometer, reading)
+            .into_iter()
+            .map(|(target, pressed)| match target {
+                Target::Button(Input::Gamepad(button)) => (button, pressed),
+                target => panic!("unexpected target {:?}", target),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn tilting_presses_each_direction() {
+        let mut motion = configured(
+            false,
+            &[
+                ("left", "DPadLeft"),
+                ("right", "DPadRight"),
+                ("forward", "DPadUp"),
+                ("back", "DPadDown"),
+            ],
+        );
+        assert!(tilt(&mut motion, FLAT).is_empty());
+        // Tilted by 30 degrees each way, whose sine is a half.
+        let (sin, cos) = (0.5, 0.866_025_4);
+        for (reading, button) in [
+            ([G * sin, G * cos, 0.0], GamepadButton::DPadLeft),
+            ([-G * sin, G * cos, 0.0], GamepadButton::DPadRight),
+            ([0.0, G * cos, G * sin], GamepadButton::DPadUp),
+            ([0.0, G * cos, -G * sin], GamepadButton::DPadDown),
+        ] {
+            assert_eq!(tilt(&mut motion, reading), vec![(button, true)]);
+            assert_eq!(tilt(&mut motion, FLAT), vec![(button, false)]);
+        }
+    }
+
+    #[test]
+    fn tilt_is_measured_from_the_first_reading() {
+        let mut motion = configured(false, &[("forward", "DPadUp")]);
+        assert!(tilt(&mut motion, pitched_forward(40.0)).is_empty());
+        assert!(tilt(&mut motion, pitched_forward(55.0)).is_empty());
+        assert_eq!(
+            tilt(&mut motion, pitched_forward(65.0)),
+            vec![(GamepadButton::DPadUp, true)]
+        );
+        // Held flat, it's tilted back from where it started.
+        assert_eq!(
+            tilt(&mut motion, FLAT),
+            vec![(GamepadButton::DPadUp, false)]
+        );
+
+        // A new controller is measured from how it's held at first too.
+        motion.stop();
+        assert!(tilt(&mut motion, FLAT).is_empty());
+        assert_eq!(
+            tilt(&mut motion, pitched_forward(25.0)),
+            vec![(GamepadButton::DPadUp, true)]
+        );
+    }
+
+    #[test]
+    fn tilt_releases_past_the_margin() {
+        let mut motion = configured(false, &[("right", "DPadRight")]);
+        tilt(&mut motion, FLAT);
+        let angle = DEFAULT_TILT_ANGLE as f32;
+        assert!(tilt(&mut motion, rolled_right(angle - 1.0)).is_empty());
+        assert_eq!(
+            tilt(&mut motion, rolled_right(angle + 1.0)),
+            vec![(GamepadButton::DPadRight, true)]
+        );
+        let angle = angle - TILT_RELEASE_MARGIN as f32;
+        assert!(tilt(&mut motion, rolled_right(angle + 1.0)).is_empty());
+        assert_eq!(
+            tilt(&mut motion, rolled_right(angle - 1.0)),
+            vec![(GamepadButton::DPadRight, false)]
+        );
+    }
+
+    #[test]
+    fn turning_moves_the_pointer() {
+        let layout = StageLayout::new(
+            ScalingMode::Fit,
+            0.0,
+            ViewportDimensions {
+                width: 600,
+                height: 400,
+                scale_factor: 1.0,
+            },
+            ScreenRotation::None,
+            600.0,
+            400.0,
+        );
+        let mut motion = configured(true, &[]);
+        assert_eq!(motion.sensors(), vec![SensorType::Gyroscope]);
+
+        // Turning left and pitching up.
+        assert!(
+            motion
+                .sensor_update(SensorType::Gyroscope, [0.25, 0.5, 0.0])
+                .is_empty()
+        );
+        let (dx, dy) = motion.pointer_motion(0.1, &layout);
+        assert!((dx - -20.0).abs() < 1e-4, "{}", dx);
+        assert!((dy - -10.0).abs() < 1e-4, "{}", dy);
+
+        // Drift inside the deadzone doesn't move it.
+        let drift = (GYRO_DEADZONE / 2.0) as f32;
+        motion.sensor_update(SensorType::Gyroscope, [drift, -drift, 0.0]);
+        assert_eq!(motion.pointer_motion(0.1, &layout), (0.0, 0.0));
+
+        let mut motion = configured(false, &[]);
+        motion.sensor_update(SensorType::Gyroscope, [0.25, 0.5, 0.0]);
+        assert_eq!(motion.pointer_motion(0.1, &layout), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,564 @@
+//! A keyboard drawn over the bottom of the screen, for typing into text fields with a controller
+//! or the touch screen.
+//!
+//! The d-pad picks a key and A types it. B closes the keyboard, X deletes, Y toggles shift, the
+//! shoulder buttons move the caret and Start is Enter. Keys can also be tapped.
+
+use std::collections::{HashMap, HashSet};
+
+use ruffle_core::PlayerEvent;
+use ruffle_core::events::{GamepadButton, TextControlCode};
+use ruffle_render::bitmap::{Bitmap, BitmapFormat, BitmapHandle};
+use ruffle_render_glow::OverlayQuad;
+
+use crate::scaling::StageLayout;
+
+/// The rows of keys, each as its keys' labels without and with shift, or as a special key.
+const ROWS: [&[Slot]; 5] = [
+    &[Slot::Chars("`1234567890-=", "~!@#$%^&*()_+")],
+    &[Slot::Chars("qwertyuiop[]\\", "QWERTYUIOP{}|")],
+    &[
+        Slot::Chars("asdfghjkl;'", "ASDFGHJKL:\""),
+        Slot::Key(Key::Backspace, 2.0),
+    ],
+    &[
+        Slot::Key(Key::Shift, 2.0),
+        Slot::Chars("zxcvbnm,./", "ZXCVBNM<>?"),
+    ],
+    &[
+        Slot::Key(Key::Done, 2.0),
+        Slot::Key(Key::Left, 1.0),
+        Slot::Key(Key::Right, 1.0),
+        Slot::Key(Key::Space, 5.0),
+        Slot::Key(Key::Enter, 3.0),
+    ],
+];
+
+/// The width of the widest row, in keys.
+const COLUMNS: f64 = 13.0;
+
+/// The space around the keys, in keys.
+const PADDING: f64 = 0.25;
+
+/// The most of the screen's height the keyboard takes up.
+const MAX_HEIGHT: f64 = 0.45;
+
+/// The size of a key in the label bitmaps, in pixels.
+const CELL_SIZE: usize = 40;
+
+const PANEL_COLOR: [f32; 4] = [0.1, 0.1, 0.1, 0.85];
+const KEY_COLOR: [f32; 4] = [0.3, 0.3, 0.3, 0.9];
+const ACTIVE_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 0.9];
+const SELECTED_COLOR: [f32; 4] = [0.3, 0.5, 0.9, 0.95];
+
+/// A 5x7 font for printable ASCII, from the space on. Each glyph is 5 columns, with the top row in
+/// the lowest bit.
+const FONT: [[u8; 5]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00],
+    [0x00, 0x00, 0x5F, 0x00, 0x00],
+    [0x00, 0x07, 0x00, 0x07, 0x00],
+    [0x14, 0x7F, 0x14, 0x7F, 0x14],
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12],
+    [0x23, 0x13, 0x08, 0x64, 0x62],
+    [0x36, 0x49, 0x55, 0x22, 0x50],
+    [0x00, 0x05, 0x03, 0x00, 0x00],
+    [0x00, 0x1C, 0x22, 0x41, 0x00],
+    [0x00, 0x41, 0x22, 0x1C, 0x00],
+    [0x08, 0x2A, 0x1C, 0x2A, 0x08],
+    [0x08, 0x08, 0x3E, 0x08, 0x08],
+    [0x00, 0x50, 0x30, 0x00, 0x00],
+    [0x08, 0x08, 0x08, 0x08, 0x08],
+    [0x00, 0x60, 0x60, 0x00, 0x00],
+    [0x20, 0x10, 0x08, 0x04, 0x02],
+    [0x3E, 0x51, 0x49, 0x45, 0x3E],
+    [0x00, 0x42, 0x7F, 0x40, 0x00],
+    [0x42, 0x61, 0x51, 0x49, 0x46],
+    [0x21, 0x41, 0x45, 0x4B, 0x31],
+    [0x18, 0x14, 0x12, 0x7F, 0x10],
+    [0x27, 0x45, 0x45, 0x45, 0x39],
+    [0x3C, 0x4A, 0x49, 0x49, 0x30],
+    [0x01, 0x71, 0x09, 0x05, 0x03],
+    [0x36, 0x49, 0x49, 0x49, 0x36],
+    [0x06, 0x49, 0x49, 0x29, 0x1E],
+    [0x00, 0x36, 0x36, 0x00, 0x00],
+    [0x00, 0x56, 0x36, 0x00, 0x00],
+    [0x08, 0x14, 0x22, 0x41, 0x00],
+    [0x14, 0x14, 0x14, 0x14, 0x14],
+    [0x00, 0x41, 0x22, 0x14, 0x08],
+    [0x02, 0x01, 0x51, 0x09, 0x06],
+    [0x32, 0x49, 0x79, 0x41, 0x3E],
+    [0x7E, 0x11, 0x11, 0x11, 0x7E],
+    [0x7F, 0x49, 0x49, 0x49, 0x36],
+    [0x3E, 0x41, 0x41, 0x41, 0x22],
+    [0x7F, 0x41, 0x41, 0x22, 0x1C],
+    [0x7F, 0x49, 0x49, 0x49, 0x41],
+    [0x7F, 0x09, 0x09, 0x09, 0x01],
+    [0x3E, 0x41, 0x49, 0x49, 0x7A],
+    [0x7F, 0x08, 0x08, 0x08, 0x7F],
+    [0x00, 0x41, 0x7F, 0x41, 0x00],
+    [0x20, 0x40, 0x41, 0x3F, 0x01],
+    [0x7F, 0x08, 0x14, 0x22, 0x41],
+    [0x7F, 0x40, 0x40, 0x40, 0x40],
+    [0x7F, 0x02, 0x0C, 0x02, 0x7F],
+    [0x7F, 0x04, 0x08, 0x10, 0x7F],
+    [0x3E, 0x41, 0x41, 0x41, 0x3E],
+    [0x7F, 0x09, 0x09, 0x09, 0x06],
+    [0x3E, 0x41, 0x51, 0x21, 0x5E],
+    [0x7F, 0x09, 0x19, 0x29, 0x46],
+    [0x46, 0x49, 0x49, 0x49, 0x31],
+    [0x01, 0x01, 0x7F, 0x01, 0x01],
+    [0x3F, 0x40, 0x40, 0x40, 0x3F],
+    [0x1F, 0x20, 0x40, 0x20, 0x1F],
+    [0x3F, 0x40, 0x38, 0x40, 0x3F],
+    [0x63, 0x14, 0x08, 0x14, 0x63],
+    [0x07, 0x08, 0x70, 0x08, 0x07],
+    [0x61, 0x51, 0x49, 0x45, 0x43],
+    [0x00, 0x7F, 0x41, 0x41, 0x00],
+    [0x02, 0x04, 0x08, 0x10, 0x20],
+    [0x00, 0x41, 0x41, 0x7F, 0x00],
+    [0x04, 0x02, 0x01, 0x02, 0x04],
+    [0x40, 0x40, 0x40, 0x40, 0x40],
+    [0x00, 0x01, 0x02, 0x04, 0x00],
+    [0x20, 0x54, 0x54, 0x54, 0x78],
+    [0x7F, 0x48, 0x44, 0x44, 0x38],
+    [0x38, 0x44, 0x44, 0x44, 0x20],
+    [0x38, 0x44, 0x44, 0x48, 0x7F],
+    [0x38, 0x54, 0x54, 0x54, 0x18],
+    [0x08, 0x7E, 0x09, 0x01, 0x02],
+    [0x0C, 0x52, 0x52, 0x52, 0x3E],
+    [0x7F, 0x08, 0x04, 0x04, 0x78],
+    [0x00, 0x44, 0x7D, 0x40, 0x00],
+    [0x20, 0x40, 0x44, 0x3D, 0x00],
+    [0x7F, 0x10, 0x28, 0x44, 0x00],
+    [0x00, 0x41, 0x7F, 0x40, 0x00],
+    [0x7C, 0x04, 0x18, 0x04, 0x78],
+    [0x7C, 0x08, 0x04, 0x04, 0x78],
+    [0x38, 0x44, 0x44, 0x44, 0x38],
+    [0x7C, 0x14, 0x14, 0x14, 0x08],
+    [0x08, 0x14, 0x14, 0x18, 0x7C],
+    [0x7C, 0x08, 0x04, 0x04, 0x08],
+    [0x48, 0x54, 0x54, 0x54, 0x20],
+    [0x04, 0x3F, 0x44, 0x40, 0x20],
+    [0x3C, 0x40, 0x40, 0x20, 0x7C],
+    [0x1C, 0x20, 0x40, 0x20, 0x1C],
+    [0x3C, 0x40, 0x30, 0x40, 0x3C],
+    [0x44, 0x28, 0x10, 0x28, 0x44],
+    [0x0C, 0x50, 0x50, 0x50, 0x3C],
+    [0x44, 0x64, 0x54, 0x4C, 0x44],
+    [0x00, 0x08, 0x36, 0x41, 0x00],
+    [0x00, 0x00, 0x7F, 0x00, 0x00],
+    [0x00, 0x41, 0x36, 0x08, 0x00],
+    [0x08, 0x04, 0x08, 0x10, 0x08],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    /// A character, without and with shift.
+    Char(char, char),
+    Shift,
+    Backspace,
+    Left,
+    Right,
+    Space,
+    Enter,
+    Done,
+}
+
+impl Key {
+    fn label(self, shift: bool) -> String {
+        match self {
+            Key::Char(lower, upper) => (if shift { upper } else { lower }).to_string(),
+            Key::Shift => "Shift".to_string(),
+            Key::Backspace => "Bksp".to_string(),
+            Key::Left => "<".to_string(),
+            Key::Right => ">".to_string(),
+            Key::Space => "Space".to_string(),
+            Key::Enter => "Enter".to_string(),
+            Key::Done => "Done".to_string(),
+        }
+    }
+}
+
+/// Keys in `ROWS`: a run of character keys, or a special key and its width in keys.
+enum Slot {
+    Chars(&'static str, &'static str),
+    Key(Key, f64),
+}
+
+/// A key and where it is on the keyboard, in keys from the top left of the first row.
+#[derive(Debug, Clone, Copy)]
+struct KeyRect {
+    key: Key,
+    row: usize,
+    x: f64,
+    width: f64,
+}
+
+impl KeyRect {
+    fn center(&self) -> f64 {
+        self.x + self.width / 2.0
+    }
+}
+
+/// The keys of `ROWS`, each row centered.
+fn key_rects() -> Vec<KeyRect> {
+    let mut rects = vec![];
+    for (row, slots) in ROWS.iter().enumerate() {
+        let mut keys = vec![];
+        for slot in slots.iter() {
+            match slot {
+                Slot::Chars(lower, upper) => keys.extend(
+                    lower
+                        .chars()
+                        .zip(upper.chars())
+                        .map(|(lower, upper)| (Key::Char(lower, upper), 1.0)),
+                ),
+                Slot::Key(key, width) => keys.push((*key, *width)),
+            }
+        }
+        let mut x = (COLUMNS - keys.iter().map(|(_, width)| width).sum::<f64>()) / 2.0;
+        for (key, width) in keys {
+            rects.push(KeyRect { key, row, x, width });
+            x += width;
+        }
+    }
+    rects
+}
+
+/// The bitmaps of the key labels, made by `label_bitmap`.
+pub struct KeyboardBitmaps {
+    pub lower: BitmapHandle,
+    pub upper: BitmapHandle,
+}
+
+pub struct OnScreenKeyboard {
+    keys: Vec<KeyRect>,
+    open: bool,
+    shift: bool,
+    selected: usize,
+    /// The key each finger on the keyboard is on, by touch device and finger.
+    fingers: HashMap<(i64, i64), Option<usize>>,
+    /// The buttons pressed while the keyboard was open, whose release it takes too.
+    held: HashSet<GamepadButton>,
+}
+
+impl Default for OnScreenKeyboard {
+    fn default() -> Self {
+        let keys = key_rects();
+        // Start on the "q".
+        let selected = keys
+            .iter()
+            .position(|rect| rect.key == Key::Char('q', 'Q'))
+            .unwrap_or_default();
+        Self {
+            keys,
+            open: false,
+            shift: false,
+            selected,
+            fingers: HashMap::new(),
+            held: HashSet::new(),
+        }
+    }
+}
+
+impl OnScreenKeyboard {
+    pub fn is_open(&self) -> bool {
+        self.open
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.open = open;
+        self.fingers.clear();
+    }
+
+    /// Takes a controller button. Returns `None` if the keyboard leaves it to the player, or the
+    /// events it typed.
+    pub fn button(&mut self, button: GamepadButton, pressed: bool) -> Option<Vec<PlayerEvent>> {
+        if !pressed {
+            return self.held.remove(&button).then(Vec::new);
+        }
+        if !self.open {
+            return None;
+        }
+        self.held.insert(button);
+        let events = match button {
+            GamepadButton::DPadUp => self.select_row(-1),
+            GamepadButton::DPadDown => self.select_row(1),
+            GamepadButton::DPadLeft => self.select_column(-1),
+            GamepadButton::DPadRight => self.select_column(1),
+            GamepadButton::South => self.press(self.keys[self.selected].key),
+            GamepadButton::East => self.press(Key::Done),
+            GamepadButton::West => self.press(Key::Backspace),
+            GamepadButton::North => self.press(Key::Shift),
+            GamepadButton::LeftTrigger => self.press(Key::Left),
+            GamepadButton::RightTrigger => self.press(Key::Right),
+            GamepadButton::Start => self.press(Key::Enter),
+            _ => vec![],
+        };
+        Some(events)
+    }
+
+    /// Takes a finger going down at `position`, in screen pixels in the stage's orientation.
+    /// Returns whether it's on the keyboard.
+    pub fn finger_down(
+        &mut self,
+        touch_id: i64,
+        finger_id: i64,
+        position: (f64, f64),
+        layout: &StageLayout,
+    ) -> bool {
+        if !self.open || !self.panel(layout).contains(position) {
+            return false;
+        }
+        let key = self.key_at(position, layout);
+        if let Some(key) = key {
+            self.selected = key;
+        }
+        self.fingers.insert((touch_id, finger_id), key);
+        true
+    }
+
+    /// Takes a finger moving to `position`. Returns whether it's on the keyboard.
+    pub fn finger_motion(
+        &mut self,
+        touch_id: i64,
+        finger_id: i64,
+        position: (f64, f64),
+        layout: &StageLayout,
+    ) -> bool {
+        if !self.fingers.contains_key(&(touch_id, finger_id)) {
+            return false;
+        }
+        let key = self.key_at(position, layout);
+        if let Some(key) = key {
+            self.selected = key;
+        }
+        self.fingers.insert((touch_id, finger_id), key);
+        true
+    }
+
+    /// Takes a finger going up, typing the key it's on. Returns `None` if it wasn't on the
+    /// keyboard, or the events it typed.
+    pub fn finger_up(
+        &mut self,
+        touch_id: i64,
+        finger_id: i64,
+        position: (f64, f64),
+        layout: &StageLayout,
+    ) -> Option<Vec<PlayerEvent>> {
+        self.fingers.remove(&(touch_id, finger_id))?;
+        Some(match self.key_at(position, layout) {
+            Some(key) => self.press(self.keys[key].key),
+            None => vec![],
+        })
+    }
+
+    fn select_row(&mut self, step: isize) -> Vec<PlayerEvent> {
+        let current = self.keys[self.selected];
+        let row = (current.row as isize + step).rem_euclid(ROWS.len() as isize) as usize;
+        if let Some((index, _)) = self
+            .keys
+            .iter()
+            .enumerate()
+            .filter(|(_, rect)| rect.row == row)
+            .min_by(|(_, a), (_, b)| {
+                let a = (a.center() - current.center()).abs();
+                let b = (b.center() - current.center()).abs();
+                a.total_cmp(&b)
+            })
+        {
+            self.selected = index;
+        }
+        vec![]
+    }
+
+    fn select_column(&mut self, step: isize) -> Vec<PlayerEvent> {
+        let row = self.keys[self.selected].row;
+        let first = self.keys.iter().position(|rect| rect.row == row).unwrap();
+        let count = self.keys.iter().filter(|rect| rect.row == row).count();
+        let column = (self.selected - first) as isize + step;
+        self.selected = first + column.rem_euclid(count as isize) as usize;
+        vec![]
+    }
+
+    fn press(&mut self, key: Key) -> Vec<PlayerEvent> {
+        let text_control = |code| vec![PlayerEvent::TextControl { code }];
+        match key {
+            Key::Char(lower, upper) => {
+                let codepoint = if self.shift { upper } else { lower };
+                self.shift = false;
+                vec![PlayerEvent::TextInput { codepoint }]
+            }
+            Key::Shift => {
+                self.shift = !self.shift;
+                vec![]
+            }
+            Key::Backspace => text_control(TextControlCode::Backspace),
+            Key::Left => text_control(TextControlCode::MoveLeft),
+            Key::Right => text_control(TextControlCode::MoveRight),
+            Key::Space => vec![PlayerEvent::TextInput { codepoint: ' ' }],
+            Key::Enter => text_control(TextControlCode::Enter),
+            Key::Done => {
+                self.set_open(false);
+                vec![]
+            }
+        }
+    }
+
+    /// The size of a key, and the panel behind the keys, in screen pixels in the stage's
+    /// orientation.
+    fn panel(&self, layout: &StageLayout) -> Panel {
+        let (width, height) = layout.rotated_screen_size();
+        let (width, height) = (width as f64, height as f64);
+        let rows = ROWS.len() as f64;
+        let key =
+            (width / (COLUMNS + PADDING * 2.0)).min(height * MAX_HEIGHT / (rows + PADDING * 2.0));
+        let panel_width = key * (COLUMNS + PADDING * 2.0);
+        let panel_height = key * (rows + PADDING * 2.0);
+        Panel {
+            key,
+            x: (width - panel_width) / 2.0,
+            y: height - panel_height,
+            width: panel_width,
+            height: panel_height,
+        }
+    }
+
+    fn key_at(&self, (x, y): (f64, f64), layout: &StageLayout) -> Option<usize> {
+        let panel = self.panel(layout);
+        let column = (x - panel.x) / panel.key - PADDING;
+        let row = ((y - panel.y) / panel.key - PADDING).floor();
+        self.keys.iter().position(|rect| {
+            rect.row as f64 == row && column >= rect.x && column < rect.x + rect.width
+        })
+    }
+
+    /// The keyboard, drawn with `bitmaps`, or nothing if it's closed.
+    pub fn overlay(&self, bitmaps: &KeyboardBitmaps, layout: &StageLayout) -> Vec<OverlayQuad> {
+        if !self.open {
+            return vec![];
+        }
+        let panel = self.panel(layout);
+        let mut quads = vec![OverlayQuad {
+            x: panel.x as f32,
+            y: panel.y as f32,
+            width: panel.width as f32,
+            height: panel.height as f32,
+            color: PANEL_COLOR,
+            bitmap: None,
+        }];
+        let left = panel.x + PADDING * panel.key;
+        let top = panel.y + PADDING * panel.key;
+        // Leave a gap between the keys.
+        let inset = panel.key * 0.05;
+        for (index, rect) in self.keys.iter().enumerate() {
+            let color = if index == self.selected {
+                SELECTED_COLOR
+            } else if rect.key == Key::Shift && self.shift {
+                ACTIVE_COLOR
+            } else {
+                KEY_COLOR
+            };
+            quads.push(OverlayQuad {
+                x: (left + rect.x * panel.key + inset) as f32,
+                y: (top + rect.row as f64 * panel.key + inset) as f32,
+                width: (rect.width * panel.key - inset * 2.0) as f32,
+                height: (panel.key - inset * 2.0) as f32,
+                color,
+                bitmap: None,
+            });
+        }
+        let labels = if self.shift {
+            &bitmaps.upper
+        } else {
+            &bitmaps.lower
+        };
+        quads.push(OverlayQuad {
+            x: left as f32,
+            y: top as f32,
+            width: (COLUMNS * panel.key) as f32,
+            height: (ROWS.len() as f64 * panel.key) as f32,
+            color: [1.0; 4],
+            bitmap: Some(labels.clone()),
+        });
+        quads
+    }
+}
+
+struct Panel {
+    /// The size of a key.
+    key: f64,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+impl Panel {
+    fn contains(&self, (x, y): (f64, f64)) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+}
+
+/// Draws the labels of every key, white on transparent, to be stretched over the keys. The
+/// pixels are premultiplied, as the renderer expects.
+pub fn label_bitmap(shift: bool) -> Bitmap<'static> {
+    let width = COLUMNS as usize * CELL_SIZE;
+    let height = ROWS.len() * CELL_SIZE;
+    let mut alpha = vec![0u8; width * height];
+    for rect in key_rects() {
+        let label = rect.key.label(shift);
+        // Single characters are drawn larger than the names of the special keys.
+        let scale = if label.chars().count() == 1 { 3 } else { 2 };
+        let label_width = label.chars().count() * 6 * scale - scale;
+        let center_x = ((rect.x + rect.width / 2.0) * CELL_SIZE as f64) as usize;
+        let left = center_x.saturating_sub(label_width / 2);
+        let top = rect.row * CELL_SIZE + (CELL_SIZE - 7 * scale) / 2;
+        for (index, character) in label.chars().enumerate() {
+            let glyph = FONT
+                .get((character as usize).wrapping_sub(0x20))
+                .unwrap_or(&FONT[0]);
+            for (column, bits) in glyph.iter().enumerate() {
+                for row in 0..7 {
+                    if bits & (1 << row) == 0 {
+                        continue;
+                    }
+                    let x = left + (index * 6 + column) * scale;
+                    let y = top + row * scale;
+                    for dy in 0..scale {
+                        for dx in 0..scale {
+                            if x + dx < width && y + dy < height {
+                                alpha[(y + dy) * width + x + dx] = 255;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    let data = alpha
+        .into_iter()
+        .flat_map(|alpha| [alpha, alpha, alpha, alpha])
+        .collect();
+    Bitmap::new(width as u32, height as u32, BitmapFormat::Rgba, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn labels_are_premultiplied() {
+        for shift in [false, true] {
+            let bitmap = label_bitmap(shift);
+            let pixels = bitmap.data().chunks_exact(4);
+            assert!(pixels.clone().any(|pixel| pixel == [255, 255, 255, 255]));
+            for pixel in pixels {
+                assert!(
+                    pixel == [0, 0, 0, 0] || pixel == [255, 255, 255, 255],
+                    "{pixel:?}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Which player each controller is.
+//!
+//! Controllers take the first free player slot when they're connected and keep it until they're
+//! disconnected, so that each player's buttons press the keys mapped for them. Holding Back and
+//! pressing Start moves a controller to the next slot, swapping places with the controller there.
+//! Whatever the moved controllers held is released for the players they were, and Back is held
+//! back until it's clear it wasn't pressed for that.
+
+use ruffle_core::events::GamepadButton;
+use sdl2::controller::{Button, GameController};
+use sdl2::sensor::SensorType;
+
+use crate::gamepad::TriggerState;
+use crate::stick::AxisState;
+
+/// A connected controller and the state of its analog inputs.
+pub struct Pad {
+    controller: GameController,
+    /// The player slot, counting from 0.
+    pub player: usize,
+    pub axis_state: AxisState,
+    pub right_axis_state: AxisState,
+    pub trigger_state: TriggerState,
+    /// The buttons passed on as pressed and not released yet. Releases of other buttons are
+    /// swallowed.
+    held: Vec<Button>,
+    back_held: bool,
+    /// Whether Back + Start moved the controller since Back was pressed.
+    moved: bool,
+}
+
+impl Pad {
+    /// Releases everything the controller holds, for its current player.
+    fn release_all(&mut self) -> Vec<PadInput> {
+        let player = self.player;
+        let buttons = self.held.drain(..).map(|button| PadInput::Button {
+            player,
+            button,
+            pressed: false,
+        });
+        let analog = [
+            self.axis_state.release(),
+            self.right_axis_state.release(),
+            self.trigger_state.release(),
+        ]
+        .into_iter()
+        .flatten()
+        .map(|button| PadInput::ReleaseAnalog { player, button });
+        buttons.chain(analog).collect()
+    }
+}
+
+/// An input of a controller to pass on, for the player it belongs to.
+pub enum PadInput {
+    Button {
+        player: usize,
+        button: Button,
+        pressed: bool,
+    },
+    /// Releases a d-pad direction of a stick, or a trigger.
+    ReleaseAnalog {
+        player: usize,
+        button: GamepadButton,
+    },
+}
+
+pub struct Controllers {
+    pads: Vec<Pad>,
+    /// How many players have mappings of their own, which can be moved to even when there
+    /// aren't that many controllers.
+    mapped_players: usize,
+    /// The sensors turned on for motion controls.
+    sensors: Vec<SensorType>,
+}
+
+impl Controllers {
+    pub fn new(mapped_players: usize, sensors: Vec<SensorType>) -> Self {
+        Self {
+            pads: vec![],
+            mapped_players,
+            sensors,
+        }
+    }
+
+    /// Gives a newly connected controller the first free player slot.
+    pub fn add(&mut self, controller: GameController) {
+        // SDL also reports the controllers that were connected at startup as added.
+        if self.pad(controller.instance_id()).is_some() {
+            return;
+        }
+        for sensor in &self.sensors {
+            // Controllers without motion sensors just don't send their events.
+            if controller.has_sensor(*sensor) {
+                let _ = controller.sensor_set_enabled(*sensor, true);
+            }
+        }
+        let player = (0..)
+            .find(|player| self.pads.iter().all(|pad| pad.player != *player))
+            .unwrap();
+        let mut pad = Pad {
+            controller,
+            player,
+            axis_state: AxisState::default(),
+            right_axis_state: AxisState::default(),
+            trigger_state: TriggerState::default(),
+            held: vec![],
+            back_held: false,
+            moved: false,
+        };
+        show_player(&mut pad);
+        self.pads.push(pad);
+    }
+
+    /// Drops a disconnected controller, which has SDL close it. Returns the releases of
+    /// everything it held, since SDL doesn't send them.
+    pub fn remove(&mut self, instance_id: u32) -> Vec<PadInput> {
+        let Some(index) = self
+            .pads
+            .iter()
+            .position(|pad| pad.controller.instance_id() == instance_id)
+        else {
+            return vec![];
+        };
+        self.pads.remove(index).release_all()
+    }
+
+    pub fn pad(&self, instance_id: u32) -> Option<&Pad> {
+        self.pads
+            .iter()
+            .find(|pad| pad.controller.instance_id() == instance_id)
+    }
+
+    pub fn pad_mut(&mut self, instance_id: u32) -> Option<&mut Pad> {
+        self.pads
+            .iter_mut()
+            .find(|pad| pad.controller.instance_id() == instance_id)
+    }
+
+    /// The player slot of a controller. Events of controllers that aren't open yet go to the
+    /// first player.
+    pub fn player(&self, instance_id: u32) -> usize {
+        self.pad(instance_id).map_or(0, |pad| pad.player)
+    }
+
+    /// Watches for Back + Start on a controller, moving it to the next player slot. Returns the
+    /// inputs to pass on for the button, which are none when it was taken for that.
+    ///
+    /// Back is only passed on once another button is pressed with it, or as a quick press and
+    /// release when it's let go alone.
+    pub fn button(&mut self, instance_id: u32, button: Button, pressed: bool) -> Vec<PadInput> {
+        let slots = self.mapped_players.max(self.pads.len());
+        let Some(index) = self
+            .pads
+            .iter()
+            .position(|pad| pad.controller.instance_id() == instance_id)
+        else {
+            return vec![];
+        };
+        let pad = &mut self.pads[index];
+        let player = pad.player;
+        let press = |button| PadInput::Button {
+            player,
+            button,
+            pressed: true,
+        };
+        let release = |button| PadInput::Button {
+            player,
+            button,
+            pressed: false,
+        };
+        match (button, pressed) {
+            (Button::Back, true) => {
+                pad.back_held = true;
+                pad.moved = false;
+                vec![]
+            }
+            (Button::Start, true) if pad.back_held => {
+                let new = (player + 1) % slots.max(1);
+                let mut inputs = vec![];
+                for other in &mut self.pads {
+                    if other.player == new || other.player == player {
+                        inputs.extend(other.release_all());
+                    }
+                    if other.player == new {
+                        other.player = player;
+                        show_player(other);
+                    }
+                }
+                let pad = &mut self.pads[index];
+                pad.player = new;
+                pad.moved = true;
+                show_player(pad);
+                inputs
+            }
+            (_, true) => {
+                let mut inputs = vec![];
+                if pad.back_held && !pad.moved && !pad.held.contains(&Button::Back) {
+                    pad.held.push(Button::Back);
+                    inputs.push(press(Button::Back));
+                }
+                if !pad.held.contains(&button) {
+                    pad.held.push(button);
+                }
+                inputs.push(press(button));
+                inputs
+            }
+            (_, false) => {
+                if button == Button::Back {
+                    pad.back_held = false;
+                    if !pad.moved && !pad.held.contains(&Button::Back) {
+                        return vec![press(Button::Back), release(Button::Back)];
+                    }
+                }
+                match pad.held.iter().position(|held| *held == button) {
+                    Some(position) => {
+                        pad.held.remove(position);
+                        vec![release(button)]
+                    }
+                    // Pressed before the controller moved, and released for it already.
+                    None => vec![],
+                }
+            }
+        }
+    }
+}
+
+/// Lets the controller show its player number, on its lights for the ones that have any.
+fn show_player(pad: &mut Pad) {
+    println!("{} is player {}", pad.controller.name(), pad.player + 1);
+    // Not every controller can, which is fine.
+    let _ = pad.controller.set_player_index(Some(pad.player as u32));
+}
@@ -0,0 +1,219 @@
+//! A touchpad that isn't on the screen, like the Vita's rear touchpad.
+//!
+//! It's left unused by default. It can move a pointer like a trackpad, or be split into regions
+//! that press controller buttons or keys.
+
+use std::collections::HashMap;
+use std::time::Instant;
+
+use ruffle_core::PlayerEvent;
+use sdl2::sys::SDL_TouchDeviceType;
+use serde::Deserialize;
+
+use crate::input::{BindingConfig, Target};
+use crate::scaling::StageLayout;
+use crate::touch::{TouchConfig, TouchMode, TouchMouse, TouchPhase, Touches};
+
+/// Whether the touch device SDL reports a finger on is a touchpad, rather than the screen.
+pub fn is_touchpad(touch_id: i64) -> bool {
+    // SDL reports the Vita's rear touchpad as giving absolute positions without being a screen.
+    unsafe {
+        sdl2::sys::SDL_GetTouchDeviceType(touch_id)
+            == SDL_TouchDeviceType::SDL_TOUCH_DEVICE_INDIRECT_ABSOLUTE
+    }
+}
+
+/// The `rear_touch` section of `config.ron`.
+#[derive(Debug, Default, Deserialize)]
+pub struct RearTouchConfig {
+    /// "unused", "trackpad" or "regions".
+    mode: Option<String>,
+    /// Like the `touch` section, but always in trackpad mode.
+    trackpad: Option<TouchConfig>,
+    regions: Option<Vec<RegionConfig>>,
+}
+
+/// A region of the touchpad. The sizes are in percent of the touchpad.
+#[derive(Debug, Deserialize)]
+pub struct RegionConfig {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    /// A controller button, named like in `gamepad_config`, which does what it's mapped to.
+    button: Option<String>,
+    /// Keys or mouse buttons, like in `gamepad_config`.
+    key: Option<BindingConfig>,
+}
+
+#[derive(Debug)]
+pub struct Region {
+    /// All as fractions of the touchpad.
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    target: Target,
+}
+
+impl Region {
+    /// Reads a region, printing why it can't be used if so.
+    fn from_config(config: RegionConfig, number: usize) -> Option<Self> {
+        let target = Target::from_config(
+            config.button,
+            config.key,
+            &format!("rear_touch: region {}", number),
+        )?;
+        Some(Self {
+            x: config.x as f32 / 100.0,
+            y: config.y as f32 / 100.0,
+            width: config.width as f32 / 100.0,
+            height: config.height as f32 / 100.0,
+            target,
+        })
+    }
+
+    fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+}
+
+pub enum RearTouch {
+    Unused,
+    Trackpad {
+        touches: Touches,
+        mouse: TouchMouse,
+    },
+    Regions {
+        regions: Vec<Region>,
+        /// The region each finger is in, by touch device and finger.
+        fingers: HashMap<(i64, i64), usize>,
+        /// How many fingers are in each region.
+        held: Vec<u32>,
+    },
+}
+
+impl RearTouch {
+    /// Reads the `rear_touch` section of the config, printing what's wrong with it and leaving
+    /// out what can't be used.
+    pub fn from_config(config: RearTouchConfig) -> Self {
+        match config.mode.as_deref() {
+            None | Some("unused") => RearTouch::Unused,
+            Some("trackpad") => {
+                let mut mouse = TouchMouse::from_config(config.trackpad.unwrap_or_default());
+                mouse.set_mode(TouchMode::Trackpad);
+                RearTouch::Trackpad {
+                    touches: Touches::default(),
+                    mouse,
+                }
+            }
+            Some("regions") => {
+                let regions: Vec<_> = config
+                    .regions
+                    .unwrap_or_default()
+                    .into_iter()
+                    .enumerate()
+                    .filter_map(|(index, region)| Region::from_config(region, index + 1))
+                    .collect();
+                RearTouch::Regions {
+                    held: vec![0; regions.len()],
+                    regions,
+                    fingers: HashMap::new(),
+                }
+            }
+            Some(mode) => {
+                println!(
+                    "Unsupported rear touch mode {}, expected unused, trackpad or regions",
+                    mode
+                );
+                RearTouch::Unused
+            }
+        }
+    }
+
+    /// The trackpad's mouse, if the touchpad is one.
+    pub fn mouse(&mut self) -> Option<&mut TouchMouse> {
+        match self {
+            RearTouch::Trackpad { mouse, .. } => Some(mouse),
+            _ => None,
+        }
+    }
+
+    /// Takes a finger at `(x, y)`, normalized to the touchpad, returning the mouse events it
+    /// causes as a trackpad.
+    #[allow(clippy::too_many_arguments)]
+    pub fn trackpad_finger(
+        &mut self,
+        phase: TouchPhase,
+        touch_id: i64,
+        finger_id: i64,
+        x: f32,
+        y: f32,
+        now: Instant,
+        layout: &StageLayout,
+    ) -> Vec<PlayerEvent> {
+        let RearTouch::Trackpad { touches, mouse } = self else {
+            return vec![];
+        };
+        let touch = match phase {
+            TouchPhase::Begin => Some(touches.finger_down(touch_id, finger_id)),
+            TouchPhase::Move => touches.finger_motion(touch_id, finger_id),
+            TouchPhase::End => touches.finger_up(touch_id, finger_id),
+        };
+        match touch {
+            Some(touch) => mouse.touch(phase, touch, layout.finger_to_rotated(x, y), now, layout),
+            None => vec![],
+        }
+    }
+
+    /// Takes a finger at `(x, y)`, normalized to the touchpad, returning the regions it pressed
+    /// or released, and whether they are now pressed.
+    pub fn region_finger(
+        &mut self,
+        phase: TouchPhase,
+        touch_id: i64,
+        finger_id: i64,
+        x: f32,
+        y: f32,
+    ) -> Vec<(Target, bool)> {
+        let RearTouch::Regions {
+            regions,
+            fingers,
+            held,
+        } = self
+        else {
+            return vec![];
+        };
+        let old = match phase {
+            TouchPhase::Begin | TouchPhase::End => fingers.remove(&(touch_id, finger_id)),
+            TouchPhase::Move => fingers.get(&(touch_id, finger_id)).copied(),
+        };
+        let new = match phase {
+            TouchPhase::End => None,
+            _ => regions.iter().position(|region| region.contains(x, y)),
+        };
+        if old == new {
+            if let Some(new) = new {
+                fingers.insert((touch_id, finger_id), new);
+            }
+            return vec![];
+        }
+
+        let mut changes = vec![];
+        if let Some(old) = old {
+            fingers.remove(&(touch_id, finger_id));
+            held[old] -= 1;
+            if held[old] == 0 {
+                changes.push((regions[old].target.clone(), false));
+            }
+        }
+        if let Some(new) = new {
+            fingers.insert((touch_id, finger_id), new);
+            held[new] += 1;
+            if held[new] == 1 {
+                changes.push((regions[new].target.clone(), true));
+            }
+        }
+        changes
+    }
+}
@@ -0,0 +1,169 @@
+//! How the stage is placed on the screen.
+//!
+//! In the default `Fit` mode the player gets the whole screen and letterboxes the movie itself.
+//! The other modes give the player a viewport with the movie's aspect ratio, and have the
+//! renderer stretch it over an area of the screen.
+
+use std::str::FromStr;
+
+use ruffle_core::ViewportDimensions;
+use ruffle_render_glow::{ScreenRotation, StageOutput};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScalingMode {
+    /// Fill the screen, leaving the letterboxing to the player.
+    #[default]
+    Fit,
+
+    /// Show the movie at the largest whole multiple of its size that fits.
+    Integer,
+
+    /// Like `Integer`, but rendered at the movie's own size and upscaled without filtering.
+    PixelPerfect,
+
+    /// Stretch the movie over the whole screen, ignoring its aspect ratio.
+    Stretch,
+
+    /// Fill the screen, cropping at most the zoom margin of the movie on each axis.
+    Zoom,
+}
+
+impl FromStr for ScalingMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fit" => Ok(ScalingMode::Fit),
+            "integer" => Ok(ScalingMode::Integer),
+            "pixel_perfect" => Ok(ScalingMode::PixelPerfect),
+            "stretch" => Ok(ScalingMode::Stretch),
+            "zoom" => Ok(ScalingMode::Zoom),
+            _ => Err(()),
+        }
+    }
+}
+
+/// The player's viewport and where it ends up on the screen.
+#[derive(Debug, Clone, Copy)]
+pub struct StageLayout {
+    pub viewport: ViewportDimensions,
+    pub output: Option<StageOutput>,
+    screen: ViewportDimensions,
+    rotation: ScreenRotation,
+}
+
+impl StageLayout {
+    /// Lays out a `movie_width`x`movie_height` movie on `screen`. `zoom_margin` is the fraction
+    /// of the movie that `ScalingMode::Zoom` may crop.
+    pub fn new(
+        mode: ScalingMode,
+        zoom_margin: f64,
+        screen: ViewportDimensions,
+        rotation: ScreenRotation,
+        movie_width: f64,
+        movie_height: f64,
+    ) -> Self {
+        let (screen_width, screen_height) = rotation.stage_size(screen.width, screen.height);
+        let mut layout = Self {
+            viewport: ViewportDimensions {
+                width: screen_width,
+                height: screen_height,
+                scale_factor: screen.scale_factor,
+            },
+            output: None,
+            screen,
+            rotation,
+        };
+        if mode == ScalingMode::Fit || movie_width <= 0.0 || movie_height <= 0.0 {
+            return layout;
+        }
+
+        let (width, height) = (screen_width as f64, screen_height as f64);
+        let fit = (width / movie_width).min(height / movie_height);
+        let fill = (width / movie_width).max(height / movie_height);
+        // Movies larger than the screen are shrunk to fit instead.
+        let whole = if fit >= 1.0 { fit.floor() } else { fit };
+
+        // The scale the player renders at, and the scale of each axis on the screen.
+        let (render_scale, output_scale_x, output_scale_y) = match mode {
+            ScalingMode::Fit => unreachable!(),
+            ScalingMode::Integer => (whole, whole, whole),
+            ScalingMode::PixelPerfect => (whole.min(1.0), whole, whole),
+            ScalingMode::Stretch => (fit, width / movie_width, height / movie_height),
+            ScalingMode::Zoom => {
+                let zoom = if zoom_margin < 1.0 {
+                    fill.min(fit / (1.0 - zoom_margin))
+                } else {
+                    fill
+                };
+                (zoom, zoom, zoom)
+            }
+        };
+
+        layout.viewport.width = ((movie_width * render_scale).round() as u32).max(1);
+        layout.viewport.height = ((movie_height * render_scale).round() as u32).max(1);
+
+        let output_width = ((movie_width * output_scale_x).round() as u32).max(1);
+        let output_height = ((movie_height * output_scale_y).round() as u32).max(1);
+        layout.output = Some(StageOutput {
+            screen_width,
+            screen_height,
+            x: ((width - output_width as f64) / 2.0).round() as i32,
+            y: ((height - output_height as f64) / 2.0).round() as i32,
+            width: output_width,
+            height: output_height,
+            nearest: mode == ScalingMode::PixelPerfect,
+        });
+        layout
+    }
+
+    /// Maps a point on the screen to the stage.
+    pub fn screen_to_stage(&self, x: f64, y: f64) -> (f64, f64) {
+        let (x, y) = self.rotation.screen_to_stage(
+            x,
+            y,
+            self.screen.width as f64,
+            self.screen.height as f64,
+        );
+        self.rotated_to_stage(x, y)
+    }
+
+    /// The size of the screen in the stage's orientation.
+    pub fn rotated_screen_size(&self) -> (u32, u32) {
+        self.rotation
+            .stage_size(self.screen.width, self.screen.height)
+    }
+
+    /// Maps a point on the screen, in the stage's orientation, to the stage.
+    pub fn rotated_to_stage(&self, x: f64, y: f64) -> (f64, f64) {
+        match &self.output {
+            Some(output) => (
+                (x - output.x as f64) * self.viewport.width as f64 / output.width as f64,
+                (y - output.y as f64) * self.viewport.height as f64 / output.height as f64,
+            ),
+            None => (x, y),
+        }
+    }
+
+    /// Whether a point on the stage is inside the player's viewport, rather than on the border
+    /// around it.
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        x >= 0.0 && y >= 0.0 && x < self.viewport.width as f64 && y < self.viewport.height as f64
+    }
+
+    /// Maps a normalized finger position to the stage.
+    pub fn finger_to_stage(&self, x: f32, y: f32) -> (f64, f64) {
+        let (x, y) = self.finger_to_rotated(x, y);
+        self.rotated_to_stage(x, y)
+    }
+
+    /// Maps a normalized finger position to the screen, in the stage's orientation.
+    pub fn finger_to_rotated(&self, x: f32, y: f32) -> (f64, f64) {
+        self.rotation.screen_to_stage(
+            x as f64 * self.screen.width as f64,
+            y as f64 * self.screen.height as f64,
+            self.screen.width as f64,
+            self.screen.height as f64,
+        )
+    }
+}
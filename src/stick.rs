@@ -0,0 +1,450 @@
+//! Turning analog sticks into d-pad presses and pointer motion.
+
+use std::str::FromStr;
+
+use ruffle_core::events::GamepadButton;
+use serde::Deserialize;
+
+/// Default share of the stick's travel that is ignored around the center, in percent.
+const DEFAULT_DEADZONE: u32 = 25;
+
+/// Default share of the stick's travel past which it counts as fully deflected, in percent.
+const DEFAULT_OUTER_DEADZONE: u32 = 100;
+
+/// Default distance a pressed direction has to come back past its threshold before it's released,
+/// in percent of the stick's travel.
+const DEFAULT_HYSTERESIS: u32 = 5;
+
+/// How far a direction may be from the stick's angle and still be pressed with 8 directions:
+/// the sine of 22.5°, so that the diagonals get as much room as the other directions.
+const DIAGONAL_SECTOR: f64 = 0.382_683_432;
+
+/// A stick's deadzone in `config.ron`. The sizes are in percent of the stick's travel.
+#[derive(Debug, Default, Deserialize)]
+pub struct DeadzoneConfig {
+    /// "radial" or "axial".
+    shape: Option<String>,
+    inner: Option<u32>,
+    outer: Option<u32>,
+    hysteresis: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DeadzoneShape {
+    /// A circle, in which the stick's distance from the center is what counts.
+    #[default]
+    Radial,
+    /// A cross, in which each axis is on its own.
+    Axial,
+}
+
+impl FromStr for DeadzoneShape {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "radial" => Ok(DeadzoneShape::Radial),
+            "axial" => Ok(DeadzoneShape::Axial),
+            _ => Err(()),
+        }
+    }
+}
+
+/// How many directions a stick can press as a d-pad.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Directions {
+    /// Only one direction at once.
+    Four,
+    /// Diagonals press two directions.
+    #[default]
+    Eight,
+}
+
+impl Directions {
+    pub fn from_count(count: u32) -> Option<Self> {
+        match count {
+            4 => Some(Directions::Four),
+            8 => Some(Directions::Eight),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Deadzone {
+    shape: DeadzoneShape,
+    /// All as fractions of the stick's travel.
+    inner: f64,
+    outer: f64,
+    hysteresis: f64,
+}
+
+impl Default for Deadzone {
+    fn default() -> Self {
+        Self::from_config(DeadzoneConfig::default())
+    }
+}
+
+impl Deadzone {
+    /// Reads a stick's deadzone section of the config, printing what's wrong with it and falling
+    /// back to the defaults for those values.
+    pub fn from_config(config: DeadzoneConfig) -> Self {
+        let shape = match config.shape {
+            Some(shape) => DeadzoneShape::from_str(&shape).unwrap_or_else(|_| {
+                println!(
+                    "Unsupported deadzone shape {}, expected radial or axial",
+                    shape
+                );
+                DeadzoneShape::Radial
+            }),
+            None => DeadzoneShape::Radial,
+        };
+        let inner = config.inner.unwrap_or(DEFAULT_DEADZONE).min(99);
+        let outer = config
+            .outer
+            .unwrap_or(DEFAULT_OUTER_DEADZONE)
+            .clamp(inner + 1, 100);
+        let hysteresis = config.hysteresis.unwrap_or(DEFAULT_HYSTERESIS).min(inner);
+        Self {
+            shape,
+            inner: inner as f64 / 100.0,
+            outer: outer as f64 / 100.0,
+            hysteresis: hysteresis as f64 / 100.0,
+        }
+    }
+
+    /// Rescales a stick position so that the deadzone is at 0 and the outer deadzone at 1,
+    /// keeping its direction.
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let scale =
+            |value: f64| ((value.abs() - self.inner) / (self.outer - self.inner)).clamp(0.0, 1.0);
+        match self.shape {
+            DeadzoneShape::Radial => {
+                let magnitude = x.hypot(y);
+                if magnitude <= self.inner {
+                    return (0.0, 0.0);
+                }
+                let scaled = scale(magnitude) / magnitude;
+                (x * scaled, y * scaled)
+            }
+            DeadzoneShape::Axial => (scale(x).copysign(x), scale(y).copysign(y)),
+        }
+    }
+
+    /// The threshold a direction has to pass to be pressed, or to stay pressed.
+    fn threshold(&self, pressed: bool) -> f64 {
+        if pressed {
+            self.inner - self.hysteresis
+        } else {
+            self.inner
+        }
+    }
+}
+
+/// The d-pad directions pressed by a stick.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Pressed {
+    up: bool,
+    down: bool,
+    left: bool,
+    right: bool,
+}
+
+/// Turns a stick into d-pad presses, one axis motion at a time.
+#[derive(Debug, Default)]
+pub struct AxisState {
+    x: f64,
+    y: f64,
+    pressed: Pressed,
+}
+
+impl AxisState {
+    /// Takes the motion of the stick's horizontal or vertical axis, returning the d-pad buttons
+    /// that were pressed or released, and whether they are now pressed.
+    pub fn axis_motion(
+        &mut self,
+        horizontal: bool,
+        value: i16,
+        deadzone: &Deadzone,
+        directions: Directions,
+    ) -> Vec<(GamepadButton, bool)> {
+        let value = (value as f64 / i16::MAX as f64).clamp(-1.0, 1.0);
+        if horizontal {
+            self.x = value;
+        } else {
+            self.y = value;
+        }
+        self.update(deadzone, directions)
+    }
+
+    /// Like `axis_motion`, but takes both axes at once, from -1 to 1.
+    pub fn motion(
+        &mut self,
+        x: f64,
+        y: f64,
+        deadzone: &Deadzone,
+        directions: Directions,
+    ) -> Vec<(GamepadButton, bool)> {
+        self.x = x.clamp(-1.0, 1.0);
+        self.y = y.clamp(-1.0, 1.0);
+        self.update(deadzone, directions)
+    }
+
+    /// Whether up, down, left and right are pressed.
+    pub fn pressed(&self) -> [bool; 4] {
+        let pressed = self.pressed;
+        [pressed.up, pressed.down, pressed.left, pressed.right]
+    }
+
+    /// Releases the pressed directions, returning them. The stick presses them again once it
+    /// moves.
+    pub fn release(&mut self) -> Vec<GamepadButton> {
+        let pressed = std::mem::take(&mut self.pressed);
+        [
+            (pressed.up, GamepadButton::DPadUp),
+            (pressed.down, GamepadButton::DPadDown),
+            (pressed.left, GamepadButton::DPadLeft),
+            (pressed.right, GamepadButton::DPadRight),
+        ]
+        .into_iter()
+        .filter(|(pressed, _)| *pressed)
+        .map(|(_, button)| button)
+        .collect()
+    }
+
+    fn update(
+        &mut self,
+        deadzone: &Deadzone,
+        directions: Directions,
+    ) -> Vec<(GamepadButton, bool)> {
+        let old = self.pressed;
+        let new = self.directions(deadzone, directions);
+        self.pressed = new;
+
+        [
+            (old.up, new.up, GamepadButton::DPadUp),
+            (old.down, new.down, GamepadButton::DPadDown),
+            (old.left, new.left, GamepadButton::DPadLeft),
+            (old.right, new.right, GamepadButton::DPadRight),
+        ]
+        .into_iter()
+        .filter(|(old, new, _)| old != new)
+        .map(|(_, new, button)| (button, new))
+        .collect()
+    }
+
+    fn directions(&self, deadzone: &Deadzone, directions: Directions) -> Pressed {
+        let (x, y) = (self.x, self.y);
+        let old = self.pressed;
+        let mut new = match deadzone.shape {
+            DeadzoneShape::Radial => {
+                let was_pressed = old != Pressed::default();
+                let magnitude = x.hypot(y);
+                if magnitude <= deadzone.threshold(was_pressed) {
+                    return Pressed::default();
+                }
+                // Pick the directions by the stick's angle alone.
+                let (x, y) = (x / magnitude, y / magnitude);
+                let sector = |pressed: bool| {
+                    if pressed {
+                        DIAGONAL_SECTOR - deadzone.hysteresis
+                    } else {
+                        DIAGONAL_SECTOR
+                    }
+                };
+                Pressed {
+                    up: -y > sector(old.up),
+                    down: y > sector(old.down),
+                    left: -x > sector(old.left),
+                    right: x > sector(old.right),
+                }
+            }
+            DeadzoneShape::Axial => Pressed {
+                up: -y > deadzone.threshold(old.up),
+                down: y > deadzone.threshold(old.down),
+                left: -x > deadzone.threshold(old.left),
+                right: x > deadzone.threshold(old.right),
+            },
+        };
+
+        if directions == Directions::Four {
+            let horizontal = new.left || new.right;
+            let vertical = new.up || new.down;
+            if horizontal && vertical {
+                // Stay on the axis that was already pressed until the other one clearly wins.
+                let was_horizontal = old.left || old.right;
+                let was_vertical = old.up || old.down;
+                let keep_horizontal = if was_horizontal && !was_vertical {
+                    x.abs() + deadzone.hysteresis >= y.abs()
+                } else if was_vertical && !was_horizontal {
+                    x.abs() > y.abs() + deadzone.hysteresis
+                } else {
+                    x.abs() >= y.abs()
+                };
+                if keep_horizontal {
+                    new.up = false;
+                    new.down = false;
+                } else {
+                    new.left = false;
+                    new.right = false;
+                }
+            }
+        }
+        new
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn deadzone(shape: &str, inner: u32, outer: u32, hysteresis: u32) -> Deadzone {
+        Deadzone::from_config(DeadzoneConfig {
+            shape: Some(shape.to_string()),
+            inner: Some(inner),
+            outer: Some(outer),
+            hysteresis: Some(hysteresis),
+        })
+    }
+
+    fn assert_close((x, y): (f64, f64), (expected_x, expected_y): (f64, f64)) {
+        assert!(
+            (x - expected_x).abs() < 1e-9 && (y - expected_y).abs() < 1e-9,
+            "({}, {}) != ({}, {})",
+            x,
+            y,
+            expected_x,
+            expected_y
+        );
+    }
+
+    #[test]
+    fn radial_deadzone_counts_the_distance() {
+        let deadzone = deadzone("radial", 25, 100, 5);
+        let mut stick = AxisState::default();
+        assert!(
+            stick
+                .motion(0.2, 0.0, &deadzone, Directions::Eight)
+                .is_empty()
+        );
+        // Each axis is inside the deadzone, but the stick is outside it.
+        assert_eq!(
+            stick.motion(0.2, 0.2, &deadzone, Directions::Eight),
+            vec![
+                (GamepadButton::DPadDown, true),
+                (GamepadButton::DPadRight, true)
+            ]
+        );
+    }
+
+    #[test]
+    fn axial_deadzone_counts_each_axis() {
+        let deadzone = deadzone("axial", 25, 100, 5);
+        let mut stick = AxisState::default();
+        assert!(
+            stick
+                .motion(0.2, 0.2, &deadzone, Directions::Eight)
+                .is_empty()
+        );
+        assert_eq!(
+            stick.motion(0.2, -0.3, &deadzone, Directions::Eight),
+            vec![(GamepadButton::DPadUp, true)]
+        );
+    }
+
+    #[test]
+    fn hysteresis_keeps_a_direction_pressed_just_inside_the_threshold() {
+        for shape in ["radial", "axial"] {
+            let deadzone = deadzone(shape, 25, 100, 5);
+            let mut stick = AxisState::default();
+            assert_eq!(
+                stick.axis_motion(true, i16::MAX / 10 * 3, &deadzone, Directions::Eight),
+                vec![(GamepadButton::DPadRight, true)]
+            );
+            assert!(
+                stick
+                    .axis_motion(
+                        true,
+                        (i16::MAX as f64 * 0.22) as i16,
+                        &deadzone,
+                        Directions::Eight
+                    )
+                    .is_empty(),
+                "{}",
+                shape
+            );
+            assert_eq!(
+                stick.axis_motion(
+                    true,
+                    (i16::MAX as f64 * 0.18) as i16,
+                    &deadzone,
+                    Directions::Eight
+                ),
+                vec![(GamepadButton::DPadRight, false)]
+            );
+            // Coming back in, the full threshold has to be passed again.
+            assert!(
+                stick
+                    .axis_motion(
+                        true,
+                        (i16::MAX as f64 * 0.22) as i16,
+                        &deadzone,
+                        Directions::Eight
+                    )
+                    .is_empty()
+            );
+        }
+    }
+
+    #[test]
+    fn four_directions_keep_the_held_axis_on_diagonals() {
+        let deadzone = deadzone("radial", 25, 100, 5);
+
+        let mut stick = AxisState::default();
+        stick.motion(1.0, 0.0, &deadzone, Directions::Four);
+        assert!(
+            stick
+                .motion(0.7, 0.7, &deadzone, Directions::Four)
+                .is_empty()
+        );
+        assert_eq!(stick.pressed(), [false, false, false, true]);
+
+        let mut stick = AxisState::default();
+        stick.motion(0.0, 1.0, &deadzone, Directions::Four);
+        assert!(
+            stick
+                .motion(0.7, 0.7, &deadzone, Directions::Four)
+                .is_empty()
+        );
+        assert_eq!(stick.pressed(), [false, true, false, false]);
+
+        // Once the other axis clearly wins, it takes over.
+        assert_eq!(
+            stick.motion(0.8, 0.5, &deadzone, Directions::Four),
+            vec![
+                (GamepadButton::DPadDown, false),
+                (GamepadButton::DPadRight, true)
+            ]
+        );
+
+        // With eight directions, the diagonal presses both.
+        let mut stick = AxisState::default();
+        stick.motion(1.0, 0.0, &deadzone, Directions::Eight);
+        stick.motion(0.7, 0.7, &deadzone, Directions::Eight);
+        assert_eq!(stick.pressed(), [false, true, false, true]);
+    }
+
+    #[test]
+    fn apply_rescales_between_the_deadzones() {
+        let radial = deadzone("radial", 25, 75, 5);
+        assert_close(radial.apply(0.2, 0.0), (0.0, 0.0));
+        assert_close(radial.apply(0.5, 0.0), (0.5, 0.0));
+        assert_close(radial.apply(0.0, -0.9), (0.0, -1.0));
+        // The direction is kept.
+        assert_close(radial.apply(0.3, 0.4), (0.3, 0.4));
+
+        let axial = deadzone("axial", 25, 75, 5);
+        assert_close(axial.apply(-0.5, 0.1), (-0.5, 0.0));
+        assert_close(axial.apply(0.9, 0.75), (1.0, 1.0));
+    }
+}
@@ -0,0 +1,114 @@
+//! Bindings that press keys over time: turbo, while its button is held, and macros.
+//!
+//! They are advanced once a frame, so turbo rates above half the frame rate fall behind.
+
+use std::time::{Duration, Instant};
+
+use crate::input::{Action, Step};
+
+struct Turbo {
+    actions: Vec<Action>,
+    period: Duration,
+    started: Instant,
+    /// How many buttons with this binding are held.
+    held: u32,
+    down: bool,
+}
+
+struct Macro {
+    steps: Vec<Step>,
+    step: usize,
+    step_started: Instant,
+}
+
+#[derive(Default)]
+pub struct TimedInputs {
+    turbos: Vec<Turbo>,
+    macros: Vec<Macro>,
+}
+
+impl TimedInputs {
+    /// Starts or stops turbo on `actions`, returning the actions to press or release now.
+    pub fn turbo(
+        &mut self,
+        actions: &[Action],
+        period: Duration,
+        pressed: bool,
+        now: Instant,
+    ) -> Option<(Vec<Action>, bool)> {
+        let index = self
+            .turbos
+            .iter()
+            .position(|turbo| turbo.actions == actions && turbo.period == period);
+        match (index, pressed) {
+            (Some(index), true) => {
+                self.turbos[index].held += 1;
+                None
+            }
+            (None, true) => {
+                self.turbos.push(Turbo {
+                    actions: actions.to_vec(),
+                    period,
+                    started: now,
+                    held: 1,
+                    down: true,
+                });
+                Some((actions.to_vec(), true))
+            }
+            (Some(index), false) => {
+                let turbo = &mut self.turbos[index];
+                turbo.held -= 1;
+                if turbo.held > 0 {
+                    return None;
+                }
+                let turbo = self.turbos.remove(index);
+                turbo.down.then_some((turbo.actions, false))
+            }
+            (None, false) => None,
+        }
+    }
+
+    /// Starts playing a macro, returning the actions its first step presses.
+    pub fn play(&mut self, steps: &[Step], now: Instant) -> Vec<Action> {
+        let Some(first) = steps.first() else {
+            return vec![];
+        };
+        self.macros.push(Macro {
+            steps: steps.to_vec(),
+            step: 0,
+            step_started: now,
+        });
+        first.actions.clone()
+    }
+
+    /// Advances turbo and macros to `now`, returning the actions to press or release, in order.
+    pub fn update(&mut self, now: Instant) -> Vec<(Vec<Action>, bool)> {
+        let mut changes = vec![];
+        for turbo in &mut self.turbos {
+            // Down for the first half of each period.
+            let half = (turbo.period / 2).max(Duration::from_millis(1));
+            let halves = now.saturating_duration_since(turbo.started).as_nanos() / half.as_nanos();
+            let down = halves % 2 == 0;
+            if down != turbo.down {
+                turbo.down = down;
+                changes.push((turbo.actions.clone(), down));
+            }
+        }
+        self.macros.retain_mut(|playing| {
+            loop {
+                let step = &playing.steps[playing.step];
+                if now.saturating_duration_since(playing.step_started) < step.time {
+                    return true;
+                }
+                changes.push((step.actions.clone(), false));
+                playing.step_started += step.time;
+                playing.step += 1;
+                match playing.steps.get(playing.step) {
+                    Some(next) => changes.push((next.actions.clone(), true)),
+                    None => return false,
+                }
+            }
+        });
+        changes
+    }
+}
@@ -0,0 +1,839 @@
+//! Fingers on the touch screen.
+//!
+//! Every finger gets the lowest touch point index that isn't in use and keeps it until it's
+//! lifted, however the fingers before it come and go. Like on AIR, the first finger down is the
+//! primary touch point, which drives the mouse too, in the way set by the touch mode.
+
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use ruffle_core::PlayerEvent;
+use ruffle_core::events::MouseButton;
+use serde::Deserialize;
+
+use crate::scaling::StageLayout;
+
+/// Default time a finger has to be held still to right click, in milliseconds.
+const DEFAULT_LONG_PRESS_TIME: u32 = 500;
+
+/// How long a finger may be down for a tap.
+const TAP_TIME: Duration = Duration::from_millis(250);
+
+/// How soon after a tap a press drags instead.
+const DOUBLE_TAP_TIME: Duration = Duration::from_millis(300);
+
+/// How far a finger may move for a tap, in screen heights.
+const TAP_DISTANCE: f64 = 0.03;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TouchPhase {
+    Begin,
+    Move,
+    End,
+}
+
+/// A finger that is down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Touch {
+    /// The touch point index the player knows the finger by.
+    pub index: i32,
+    /// Whether the finger also drives the mouse.
+    pub primary: bool,
+}
+
+#[derive(Debug)]
+struct Finger {
+    touch_id: i64,
+    finger_id: i64,
+    touch: Touch,
+}
+
+#[derive(Debug, Default)]
+pub struct Touches {
+    fingers: Vec<Finger>,
+    /// Whether the primary finger was lifted while others were still down. No finger becomes
+    /// primary until they are all lifted.
+    primary_lifted: bool,
+}
+
+impl Touches {
+    /// Starts tracking a finger that went down.
+    pub fn finger_down(&mut self, touch_id: i64, finger_id: i64) -> Touch {
+        // SDL doesn't always report every finger going up, e.g. when the app loses focus.
+        self.fingers
+            .retain(|finger| (finger.touch_id, finger.finger_id) != (touch_id, finger_id));
+        let index = (0..)
+            .find(|index| {
+                self.fingers
+                    .iter()
+                    .all(|finger| finger.touch.index != *index)
+            })
+            .unwrap();
+        let touch = Touch {
+            index,
+            primary: self.fingers.is_empty() && !self.primary_lifted,
+        };
+        self.fingers.push(Finger {
+            touch_id,
+            finger_id,
+            touch,
+        });
+        touch
+    }
+
+    /// The finger that moved, if it's being tracked.
+    pub fn finger_motion(&self, touch_id: i64, finger_id: i64) -> Option<Touch> {
+        self.fingers
+            .iter()
+            .find(|finger| (finger.touch_id, finger.finger_id) == (touch_id, finger_id))
+            .map(|finger| finger.touch)
+    }
+
+    /// Stops tracking a finger that went up, returning it if it was tracked.
+    pub fn finger_up(&mut self, touch_id: i64, finger_id: i64) -> Option<Touch> {
+        let position = self
+            .fingers
+            .iter()
+            .position(|finger| (finger.touch_id, finger.finger_id) == (touch_id, finger_id))?;
+        let touch = self.fingers.remove(position).touch;
+        if touch.primary {
+            self.primary_lifted = true;
+        }
+        if self.fingers.is_empty() {
+            self.primary_lifted = false;
+        }
+        Some(touch)
+    }
+}
+
+/// The touch event for a finger at `(x, y)` on the stage.
+pub fn touch_event(phase: TouchPhase, touch: Touch, x: f64, y: f64) -> PlayerEvent {
+    let id = touch.index;
+    let primary = touch.primary;
+    match phase {
+        TouchPhase::Begin => PlayerEvent::TouchBegin { id, x, y, primary },
+        TouchPhase::Move => PlayerEvent::TouchMove { id, x, y, primary },
+        TouchPhase::End => PlayerEvent::TouchEnd { id, x, y, primary },
+    }
+}
+
+/// The `touch` section of `config.ron`.
+#[derive(Debug, Default, Deserialize)]
+pub struct TouchConfig {
+    /// "direct", "trackpad" or "tap".
+    mode: Option<String>,
+    /// Whether holding a finger still right clicks.
+    long_press: Option<bool>,
+    /// How long a finger has to be held for that, in milliseconds.
+    long_press_time: Option<u32>,
+    /// Whether tapping with two fingers right clicks.
+    two_finger_tap: Option<bool>,
+    /// How much faster than the finger the pointer moves in trackpad mode.
+    trackpad_speed: Option<f64>,
+}
+
+/// How the primary finger drives the mouse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TouchMode {
+    /// The mouse is pressed where the finger goes down.
+    #[default]
+    Direct,
+    /// The finger moves a pointer like on a laptop's trackpad, and tapping clicks at the pointer.
+    Trackpad,
+    /// The finger hovers the mouse where it is, and tapping clicks there.
+    Tap,
+}
+
+impl FromStr for TouchMode {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "direct" => Ok(TouchMode::Direct),
+            "trackpad" => Ok(TouchMode::Trackpad),
+            "tap" => Ok(TouchMode::Tap),
+            _ => Err(()),
+        }
+    }
+}
+
+/// A primary finger that is down.
+#[derive(Debug)]
+struct Gesture {
+    start: Instant,
+    /// Where the finger went down and where it is, in screen pixels in the stage's orientation.
+    origin: (f64, f64),
+    position: (f64, f64),
+    /// Whether the finger went further than a tap may.
+    moved: bool,
+    /// Whether the left mouse button is held down by the finger.
+    pressed: bool,
+    /// Whether the finger already did what it does, by a long press or a two finger tap.
+    done: bool,
+}
+
+/// Turns the primary finger, and a second finger tapping next to it, into mouse events.
+pub struct TouchMouse {
+    mode: TouchMode,
+    long_press: Option<Duration>,
+    two_finger_tap: bool,
+    trackpad_speed: f64,
+    gesture: Option<Gesture>,
+    /// When a second finger went down, while it could still be a tap.
+    second_finger: Option<Instant>,
+    /// When and where the last tap was, as a tap followed by a press drags.
+    last_tap: Option<(Instant, (f64, f64))>,
+    /// The mouse, in screen pixels in the stage's orientation. Starts at the center.
+    position: Option<(f64, f64)>,
+}
+
+impl TouchMouse {
+    /// Reads the `touch` section of the config, printing what's wrong with it and falling back
+    /// to the defaults for those values.
+    pub fn from_config(config: TouchConfig) -> Self {
+        let mode = match config.mode {
+            Some(mode) => TouchMode::from_str(&mode).unwrap_or_else(|_| {
+                println!(
+                    "Unsupported touch mode {}, expected direct, trackpad or tap",
+                    mode
+                );
+                TouchMode::Direct
+            }),
+            None => TouchMode::Direct,
+        };
+        let long_press_time = config.long_press_time.unwrap_or(DEFAULT_LONG_PRESS_TIME);
+        Self {
+            mode,
+            long_press: config
+                .long_press
+                .unwrap_or(false)
+                .then(|| Duration::from_millis(long_press_time.into())),
+            two_finger_tap: config.two_finger_tap.unwrap_or(false),
+            trackpad_speed: config.trackpad_speed.unwrap_or(1.5).max(0.0),
+            gesture: None,
+            second_finger: None,
+            last_tap: None,
+            position: None,
+        }
+    }
+
+    pub fn mode(&self) -> TouchMode {
+        self.mode
+    }
+
+    pub fn set_mode(&mut self, mode: TouchMode) {
+        self.mode = mode;
+    }
+
+    /// The trackpad's pointer, in screen pixels in the stage's orientation.
+    pub fn cursor(&self, layout: &StageLayout) -> Option<(f64, f64)> {
+        (self.mode == TouchMode::Trackpad).then(|| self.screen_position(layout))
+    }
+
+    /// Takes a finger at `position`, in screen pixels in the stage's orientation, returning the
+    /// mouse events it causes.
+    pub fn touch(
+        &mut self,
+        phase: TouchPhase,
+        touch: Touch,
+        position: (f64, f64),
+        now: Instant,
+        layout: &StageLayout,
+    ) -> Vec<PlayerEvent> {
+        if !touch.primary {
+            return self.second_touch(phase, now, layout);
+        }
+        match phase {
+            TouchPhase::Begin => self.begin(position, now, layout),
+            TouchPhase::Move => self.motion(position, layout),
+            TouchPhase::End => self.end(now, layout),
+        }
+    }
+
+    /// Right clicks if the primary finger has been held still long enough.
+    pub fn update(&mut self, now: Instant, layout: &StageLayout) -> Vec<PlayerEvent> {
+        let pointer = self.pointer(layout);
+        let (Some(long_press), Some(gesture)) = (self.long_press, &mut self.gesture) else {
+            return vec![];
+        };
+        if gesture.moved || gesture.pressed || gesture.done || now - gesture.start < long_press {
+            return vec![];
+        }
+        gesture.done = true;
+        click(pointer, MouseButton::Right)
+    }
+
+    fn begin(
+        &mut self,
+        position: (f64, f64),
+        now: Instant,
+        layout: &StageLayout,
+    ) -> Vec<PlayerEvent> {
+        let (_, height) = layout.rotated_screen_size();
+        let tap_distance = height as f64 * TAP_DISTANCE;
+        // A press right after a tap, close to it, drags.
+        let drags = self.last_tap.take().is_some_and(|(time, tap)| {
+            now - time <= DOUBLE_TAP_TIME
+                && (self.mode == TouchMode::Trackpad || distance(tap, position) <= tap_distance)
+        });
+        // Without a right click gesture to wait for, direct touch presses straight away.
+        let presses = drags
+            || (self.mode == TouchMode::Direct
+                && self.long_press.is_none()
+                && !self.two_finger_tap);
+        self.gesture = Some(Gesture {
+            start: now,
+            origin: position,
+            position,
+            moved: false,
+            pressed: presses,
+            done: false,
+        });
+        if self.mode != TouchMode::Trackpad {
+            self.position = Some(position);
+        }
+
+        let (x, y) = self.pointer(layout);
+        let mut events = vec![];
+        if self.mode != TouchMode::Trackpad {
+            events.push(PlayerEvent::MouseMove { x, y });
+        }
+        if presses {
+            events.push(PlayerEvent::MouseDown {
+                x,
+                y,
+                button: MouseButton::Left,
+                index: None,
+            });
+        }
+        events
+    }
+
+    fn motion(&mut self, position: (f64, f64), layout: &StageLayout) -> Vec<PlayerEvent> {
+        let (width, height) = layout.rotated_screen_size();
+        let Some(gesture) = &mut self.gesture else {
+            return vec![];
+        };
+        let last = gesture.position;
+        gesture.position = position;
+        if distance(gesture.origin, position) > height as f64 * TAP_DISTANCE {
+            gesture.moved = true;
+        }
+        if self.mode == TouchMode::Trackpad {
+            let (x, y) = self.screen_position(layout);
+            self.position = Some((
+                (x + (position.0 - last.0) * self.trackpad_speed).clamp(0.0, width as f64 - 1.0),
+                (y + (position.1 - last.1) * self.trackpad_speed).clamp(0.0, height as f64 - 1.0),
+            ));
+        } else {
+            self.position = Some(position);
+        }
+
+        let mut events = vec![];
+        let gesture = self.gesture.as_mut().unwrap();
+        // Direct touch that waited for a right click gesture presses once the finger moves, where
+        // it went down, so that it drags from there.
+        if self.mode == TouchMode::Direct && gesture.moved && !gesture.pressed && !gesture.done {
+            gesture.pressed = true;
+            let (x, y) = layout.rotated_to_stage(gesture.origin.0, gesture.origin.1);
+            events.push(PlayerEvent::MouseDown {
+                x,
+                y,
+                button: MouseButton::Left,
+                index: None,
+            });
+        }
+        let (x, y) = self.pointer(layout);
+        events.push(PlayerEvent::MouseMove { x, y });
+        events
+    }
+
+    fn end(&mut self, now: Instant, layout: &StageLayout) -> Vec<PlayerEvent> {
+        let pointer = self.pointer(layout);
+        let Some(gesture) = self.gesture.take() else {
+            return vec![];
+        };
+        if gesture.pressed {
+            return vec![PlayerEvent::MouseUp {
+                x: pointer.0,
+                y: pointer.1,
+                button: MouseButton::Left,
+            }];
+        }
+        if gesture.done || gesture.moved {
+            return vec![];
+        }
+        // Direct touch clicks however long the finger was held, as long as it didn't long press.
+        if self.mode == TouchMode::Direct || now - gesture.start <= TAP_TIME {
+            self.last_tap = Some((now, gesture.origin));
+            return click(pointer, MouseButton::Left);
+        }
+        vec![]
+    }
+
+    fn second_touch(
+        &mut self,
+        phase: TouchPhase,
+        now: Instant,
+        layout: &StageLayout,
+    ) -> Vec<PlayerEvent> {
+        if !self.two_finger_tap {
+            return vec![];
+        }
+        match phase {
+            TouchPhase::Begin => {
+                if let Some(gesture) = &mut self.gesture {
+                    if !gesture.moved && !gesture.pressed && !gesture.done {
+                        // The primary finger won't click now.
+                        gesture.done = true;
+                        self.second_finger = Some(now);
+                    }
+                }
+                vec![]
+            }
+            TouchPhase::Move => vec![],
+            TouchPhase::End => match self.second_finger.take() {
+                Some(start) if now - start <= TAP_TIME => {
+                    click(self.pointer(layout), MouseButton::Right)
+                }
+                _ => vec![],
+            },
+        }
+    }
+
+    /// Where the mouse is, in screen pixels in the stage's orientation.
+    fn screen_position(&self, layout: &StageLayout) -> (f64, f64) {
+        self.position.unwrap_or_else(|| {
+            let (width, height) = layout.rotated_screen_size();
+            (width as f64 / 2.0, height as f64 / 2.0)
+        })
+    }
+
+    /// Where the mouse is, on the stage.
+    fn pointer(&self, layout: &StageLayout) -> (f64, f64) {
+        let (x, y) = self.screen_position(layout);
+        layout.rotated_to_stage(x, y)
+    }
+}
+
+fn click((x, y): (f64, f64), button: MouseButton) -> Vec<PlayerEvent> {
+    vec![
+        PlayerEvent::MouseDown {
+            x,
+            y,
+            button,
+            index: None,
+        },
+        PlayerEvent::MouseUp { x, y, button },
+    ]
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use ruffle_core::ViewportDimensions;
+    use ruffle_render_glow::ScreenRotation;
+
+    use super::*;
+    use crate::scaling::ScalingMode;
+
+    const PRIMARY: Touch = Touch {
+        index: 0,
+        primary: true,
+    };
+    const SECOND: Touch = Touch {
+        index: 1,
+        primary: false,
+    };
+
+    #[derive(Debug, PartialEq)]
+    enum Mouse {
+        Move(f64, f64),
+        Down(MouseButton, f64, f64),
+        Up(MouseButton, f64, f64),
+    }
+
+    /// An 800x600 screen showing a 400x300 stage at twice its size, so the stage is at half
+    /// the screen's coordinates.
+    fn layout() -> StageLayout {
+        StageLayout::new(
+            ScalingMode::PixelPerfect,
+            0.0,
+            ViewportDimensions {
+                width: 800,
+                height: 600,
+                scale_factor: 1.0,
+            },
+            ScreenRotation::None,
+            400.0,
+            300.0,
+        )
+    }
+
+    fn touch_mouse(mode: &str, long_press: bool, two_finger_tap: bool) -> TouchMouse {
+        TouchMouse::from_config(TouchConfig {
+            mode: Some(mode.to_string()),
+            long_press: Some(long_press),
+            long_press_time: None,
+            two_finger_tap: Some(two_finger_tap),
+            trackpad_speed: None,
+        })
+    }
+
+    fn mouse(events: Vec<PlayerEvent>) -> Vec<Mouse> {
+        events
+            .into_iter()
+            .map(|event| match event {
+                PlayerEvent::MouseMove { x, y } => Mouse::Move(x, y),
+                PlayerEvent::MouseDown { x, y, button, .. } => Mouse::Down(button, x, y),
+                PlayerEvent::MouseUp { x, y, button } => Mouse::Up(button, x, y),
+                event => panic!("unexpected event {:?}", event),
+            })
+            .collect()
+    }
+
+    fn finger(
+        touch_mouse: &mut TouchMouse,
+        phase: TouchPhase,
+        touch: Touch,
+        position: (f64, f64),
+        now: Instant,
+    ) -> Vec<Mouse> {
+        mouse(touch_mouse.touch(phase, touch, position, now, &layout()))
+    }
+
+    fn ms(start: Instant, millis: u64) -> Instant {
+        start + Duration::from_millis(millis)
+    }
+
+    #[test]
+    fn direct_touch_presses_straight_away() {
+        let mut touch_mouse = touch_mouse("direct", false, false);
+        let start = Instant::now();
+        assert_eq!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::Begin,
+                PRIMARY,
+                (100.0, 80.0),
+                start
+            ),
+            vec![
+                Mouse::Move(50.0, 40.0),
+                Mouse::Down(MouseButton::Left, 50.0, 40.0)
+            ]
+        );
+        assert_eq!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::Move,
+                PRIMARY,
+                (300.0, 80.0),
+                ms(start, 50)
+            ),
+            vec![Mouse::Move(150.0, 40.0)]
+        );
+        assert_eq!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::End,
+                PRIMARY,
+                (300.0, 80.0),
+                ms(start, 100)
+            ),
+            vec![Mouse::Up(MouseButton::Left, 150.0, 40.0)]
+        );
+    }
+
+    #[test]
+    fn taps_click_where_the_finger_is() {
+        let mut touch_mouse = touch_mouse("tap", false, false);
+        let start = Instant::now();
+        assert_eq!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::Begin,
+                PRIMARY,
+                (100.0, 80.0),
+                start
+            ),
+            vec![Mouse::Move(50.0, 40.0)]
+        );
+        assert_eq!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::End,
+                PRIMARY,
+                (100.0, 80.0),
+                ms(start, 100)
+            ),
+            vec![
+                Mouse::Down(MouseButton::Left, 50.0, 40.0),
+                Mouse::Up(MouseButton::Left, 50.0, 40.0)
+            ]
+        );
+
+        // Held too long, it only hovers.
+        let start = ms(start, 1000);
+        finger(
+            &mut touch_mouse,
+            TouchPhase::Begin,
+            PRIMARY,
+            (100.0, 80.0),
+            start,
+        );
+        assert!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::End,
+                PRIMARY,
+                (100.0, 80.0),
+                ms(start, 400)
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn a_press_after_a_tap_drags() {
+        let mut touch_mouse = touch_mouse("tap", false, false);
+        let start = Instant::now();
+        finger(
+            &mut touch_mouse,
+            TouchPhase::Begin,
+            PRIMARY,
+            (100.0, 80.0),
+            start,
+        );
+        finger(
+            &mut touch_mouse,
+            TouchPhase::End,
+            PRIMARY,
+            (100.0, 80.0),
+            ms(start, 100),
+        );
+
+        assert_eq!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::Begin,
+                PRIMARY,
+                (104.0, 80.0),
+                ms(start, 200)
+            ),
+            vec![
+                Mouse::Move(52.0, 40.0),
+                Mouse::Down(MouseButton::Left, 52.0, 40.0)
+            ]
+        );
+        assert_eq!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::Move,
+                PRIMARY,
+                (200.0, 80.0),
+                ms(start, 250)
+            ),
+            vec![Mouse::Move(100.0, 40.0)]
+        );
+        assert_eq!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::End,
+                PRIMARY,
+                (200.0, 80.0),
+                ms(start, 300)
+            ),
+            vec![Mouse::Up(MouseButton::Left, 100.0, 40.0)]
+        );
+
+        // Too late after the tap, it's a tap of its own.
+        let start = ms(start, 1000);
+        finger(
+            &mut touch_mouse,
+            TouchPhase::Begin,
+            PRIMARY,
+            (100.0, 80.0),
+            start,
+        );
+        finger(
+            &mut touch_mouse,
+            TouchPhase::End,
+            PRIMARY,
+            (100.0, 80.0),
+            ms(start, 100),
+        );
+        assert_eq!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::Begin,
+                PRIMARY,
+                (100.0, 80.0),
+                ms(start, 500)
+            ),
+            vec![Mouse::Move(50.0, 40.0)]
+        );
+    }
+
+    #[test]
+    fn long_press_right_clicks() {
+        let mut touch_mouse = touch_mouse("direct", true, false);
+        let layout = layout();
+        let start = Instant::now();
+        assert_eq!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::Begin,
+                PRIMARY,
+                (100.0, 80.0),
+                start
+            ),
+            vec![Mouse::Move(50.0, 40.0)]
+        );
+        assert!(touch_mouse.update(ms(start, 400), &layout).is_empty());
+        assert_eq!(
+            mouse(touch_mouse.update(ms(start, 600), &layout)),
+            vec![
+                Mouse::Down(MouseButton::Right, 50.0, 40.0),
+                Mouse::Up(MouseButton::Right, 50.0, 40.0)
+            ]
+        );
+        assert!(touch_mouse.update(ms(start, 700), &layout).is_empty());
+        assert!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::End,
+                PRIMARY,
+                (100.0, 80.0),
+                ms(start, 800)
+            )
+            .is_empty()
+        );
+    }
+
+    #[test]
+    fn waiting_for_a_long_press_still_drags_from_where_the_finger_went_down() {
+        let mut touch_mouse = touch_mouse("direct", true, false);
+        let start = Instant::now();
+        finger(
+            &mut touch_mouse,
+            TouchPhase::Begin,
+            PRIMARY,
+            (100.0, 80.0),
+            start,
+        );
+        // Within the tap distance, it could still be a long press.
+        assert_eq!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::Move,
+                PRIMARY,
+                (104.0, 80.0),
+                ms(start, 50)
+            ),
+            vec![Mouse::Move(52.0, 40.0)]
+        );
+        assert_eq!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::Move,
+                PRIMARY,
+                (300.0, 80.0),
+                ms(start, 100)
+            ),
+            vec![
+                Mouse::Down(MouseButton::Left, 50.0, 40.0),
+                Mouse::Move(150.0, 40.0)
+            ]
+        );
+        assert_eq!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::End,
+                PRIMARY,
+                (300.0, 80.0),
+                ms(start, 150)
+            ),
+            vec![Mouse::Up(MouseButton::Left, 150.0, 40.0)]
+        );
+    }
+
+    #[test]
+    fn two_finger_tap_right_clicks() {
+        let mut touch_mouse = touch_mouse("direct", false, true);
+        let start = Instant::now();
+        assert_eq!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::Begin,
+                PRIMARY,
+                (100.0, 80.0),
+                start
+            ),
+            vec![Mouse::Move(50.0, 40.0)]
+        );
+        assert!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::Begin,
+                SECOND,
+                (160.0, 80.0),
+                ms(start, 50)
+            )
+            .is_empty()
+        );
+        assert_eq!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::End,
+                SECOND,
+                (160.0, 80.0),
+                ms(start, 150)
+            ),
+            vec![
+                Mouse::Down(MouseButton::Right, 50.0, 40.0),
+                Mouse::Up(MouseButton::Right, 50.0, 40.0)
+            ]
+        );
+        assert!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::End,
+                PRIMARY,
+                (100.0, 80.0),
+                ms(start, 200)
+            )
+            .is_empty()
+        );
+
+        // Held too long, the second finger does nothing.
+        let start = ms(start, 1000);
+        finger(
+            &mut touch_mouse,
+            TouchPhase::Begin,
+            PRIMARY,
+            (100.0, 80.0),
+            start,
+        );
+        finger(
+            &mut touch_mouse,
+            TouchPhase::Begin,
+            SECOND,
+            (160.0, 80.0),
+            ms(start, 50),
+        );
+        assert!(
+            finger(
+                &mut touch_mouse,
+                TouchPhase::End,
+                SECOND,
+                (160.0, 80.0),
+                ms(start, 500)
+            )
+            .is_empty()
+        );
+    }
+}
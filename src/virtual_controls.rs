@@ -0,0 +1,562 @@
+//! Controls drawn over the stage, for playing with the touch screen alone.
+//!
+//! Each control is placed by its center, in percent of the screen's width and height, and sized
+//! in percent of the screen's height. Buttons press a controller button or keys, and the d-pad and
+//! the stick press a direction each. When the controls are editable, a handle in the top right
+//! corner toggles moving them around, after which their places are saved next to the config.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use ruffle_core::events::GamepadButton;
+use ruffle_render::bitmap::{Bitmap, BitmapFormat, BitmapHandle};
+use ruffle_render_glow::OverlayQuad;
+use serde::Deserialize;
+
+use crate::input::{BindingConfig, Input, Target};
+use crate::scaling::StageLayout;
+use crate::stick::{AxisState, Deadzone, Directions};
+
+/// Default opacity of the controls, in percent.
+const DEFAULT_OPACITY: u32 = 50;
+
+/// The size of the edit handle, in screen heights.
+const HANDLE_SIZE: f64 = 0.06;
+
+/// The size of the disc and ring bitmaps, in pixels.
+const CIRCLE_SIZE: u32 = 64;
+
+/// The width of the d-pad's arms, as a fraction of its size.
+const DPAD_ARM_WIDTH: f64 = 0.34;
+
+/// The size of the stick's knob, as a fraction of the stick's size.
+const KNOB_SIZE: f64 = 0.45;
+
+const COLOR: [f32; 3] = [1.0, 1.0, 1.0];
+const EDIT_COLOR: [f32; 3] = [1.0, 0.8, 0.3];
+
+/// The `virtual_controls` section of `config.ron`.
+#[derive(Debug, Default, Deserialize)]
+pub struct VirtualControlsConfig {
+    /// How opaque the controls are, in percent.
+    opacity: Option<u32>,
+    /// Whether the controls can be moved around.
+    editable: Option<bool>,
+    controls: Vec<ControlConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ControlConfig {
+    /// "button", "dpad" or "stick".
+    kind: String,
+    x: u32,
+    y: u32,
+    size: u32,
+    /// A button's controller button, named like in `gamepad_config`.
+    button: Option<String>,
+    /// A button's keys or mouse buttons, like in `gamepad_config`.
+    key: Option<BindingConfig>,
+    /// The keys of the d-pad's or the stick's directions, which press the d-pad by default.
+    up: Option<BindingConfig>,
+    down: Option<BindingConfig>,
+    left: Option<BindingConfig>,
+    right: Option<BindingConfig>,
+}
+
+#[derive(Debug)]
+enum ControlKind {
+    Button {
+        target: Target,
+        /// How many fingers are on the button.
+        held: u32,
+    },
+    DPad {
+        /// Up, down, left and right.
+        targets: [Target; 4],
+        axis: AxisState,
+    },
+    Stick {
+        /// Up, down, left and right.
+        targets: [Target; 4],
+        axis: AxisState,
+        /// Where the knob is, from -1 to 1 on each axis.
+        knob: (f64, f64),
+    },
+}
+
+#[derive(Debug)]
+struct Control {
+    kind: ControlKind,
+    /// The center, as fractions of the screen's width and height.
+    x: f64,
+    y: f64,
+    /// As a fraction of the screen's height.
+    size: f64,
+}
+
+impl Control {
+    fn from_config(config: ControlConfig, number: usize) -> Option<Self> {
+        let ControlConfig {
+            kind,
+            x,
+            y,
+            size,
+            button,
+            key,
+            up,
+            down,
+            left,
+            right,
+        } = config;
+        let what = format!("virtual_controls: control {}", number);
+        let direction = |binding: Option<BindingConfig>, button| match binding {
+            Some(key) => Target::from_config(None, Some(key), &what),
+            None => Some(Target::Button(Input::Gamepad(button))),
+        };
+        let directions = move || {
+            Some([
+                direction(up, GamepadButton::DPadUp)?,
+                direction(down, GamepadButton::DPadDown)?,
+                direction(left, GamepadButton::DPadLeft)?,
+                direction(right, GamepadButton::DPadRight)?,
+            ])
+        };
+        let kind = match kind.as_str() {
+            "button" => ControlKind::Button {
+                target: Target::from_config(button, key, &what)?,
+                held: 0,
+            },
+            "dpad" => ControlKind::DPad {
+                targets: directions()?,
+                axis: AxisState::default(),
+            },
+            "stick" => ControlKind::Stick {
+                targets: directions()?,
+                axis: AxisState::default(),
+                knob: (0.0, 0.0),
+            },
+            kind => {
+                println!(
+                    "{} has unsupported kind {}, expected button, dpad or stick",
+                    what, kind
+                );
+                return None;
+            }
+        };
+        Some(Self {
+            kind,
+            x: x as f64 / 100.0,
+            y: y as f64 / 100.0,
+            size: size.max(1) as f64 / 100.0,
+        })
+    }
+
+    /// The center and size of the control, in screen pixels in the stage's orientation.
+    fn rect(&self, layout: &StageLayout) -> (f64, f64, f64) {
+        let (width, height) = layout.rotated_screen_size();
+        let (width, height) = (width as f64, height as f64);
+        (self.x * width, self.y * height, self.size * height)
+    }
+
+    fn contains(&self, (x, y): (f64, f64), layout: &StageLayout) -> bool {
+        let (center_x, center_y, size) = self.rect(layout);
+        match self.kind {
+            ControlKind::DPad { .. } => {
+                (x - center_x).abs() <= size / 2.0 && (y - center_y).abs() <= size / 2.0
+            }
+            _ => (x - center_x).hypot(y - center_y) <= size / 2.0,
+        }
+    }
+
+    /// Takes a finger on the control, or the finger going up if `position` is `None`, returning
+    /// what was pressed or released.
+    fn touch(
+        &mut self,
+        position: Option<(f64, f64)>,
+        layout: &StageLayout,
+        directions: Directions,
+    ) -> Vec<(Target, bool)> {
+        let (center_x, center_y, size) = self.rect(layout);
+        // Where the finger is, from -1 to 1 on each axis over the control.
+        let (x, y) = match position {
+            Some((x, y)) => ((x - center_x) / (size / 2.0), (y - center_y) / (size / 2.0)),
+            None => (0.0, 0.0),
+        };
+        let (targets, axis) = match &mut self.kind {
+            ControlKind::Button { target, held } => {
+                if position.is_some() || *held == 0 {
+                    return vec![];
+                }
+                *held -= 1;
+                return if *held == 0 {
+                    vec![(target.clone(), false)]
+                } else {
+                    vec![]
+                };
+            }
+            ControlKind::DPad { targets, axis } => (targets, axis),
+            ControlKind::Stick {
+                targets,
+                axis,
+                knob,
+            } => {
+                let distance = x.hypot(y);
+                *knob = if distance > 1.0 {
+                    (x / distance, y / distance)
+                } else {
+                    (x, y)
+                };
+                (targets, axis)
+            }
+        };
+        axis.motion(x, y, &Deadzone::default(), directions)
+            .into_iter()
+            .map(|(button, pressed)| {
+                let index = match button {
+                    GamepadButton::DPadUp => 0,
+                    GamepadButton::DPadDown => 1,
+                    GamepadButton::DPadLeft => 2,
+                    _ => 3,
+                };
+                (targets[index].clone(), pressed)
+            })
+            .collect()
+    }
+
+    /// Takes a finger going down on the control, returning what it pressed.
+    fn press(
+        &mut self,
+        position: (f64, f64),
+        layout: &StageLayout,
+        directions: Directions,
+    ) -> Vec<(Target, bool)> {
+        if let ControlKind::Button { target, held } = &mut self.kind {
+            *held += 1;
+            return if *held == 1 {
+                vec![(target.clone(), true)]
+            } else {
+                vec![]
+            };
+        }
+        self.touch(Some(position), layout, directions)
+    }
+
+    /// Lets go of everything the control is pressing.
+    fn release(&mut self, layout: &StageLayout, directions: Directions) -> Vec<(Target, bool)> {
+        if let ControlKind::Button { held, .. } = &mut self.kind {
+            // So that the button is released once.
+            *held = (*held).min(1);
+        }
+        self.touch(None, layout, directions)
+    }
+
+    fn overlay(
+        &self,
+        layout: &StageLayout,
+        bitmaps: &ControlBitmaps,
+        color: [f32; 3],
+        opacity: f32,
+    ) -> Vec<OverlayQuad> {
+        let (center_x, center_y, size) = self.rect(layout);
+        let quad =
+            |x: f64, y: f64, size: f64, alpha: f32, bitmap: Option<&BitmapHandle>| OverlayQuad {
+                x: (x - size / 2.0) as f32,
+                y: (y - size / 2.0) as f32,
+                width: size as f32,
+                height: size as f32,
+                color: [color[0], color[1], color[2], alpha * opacity],
+                bitmap: bitmap.cloned(),
+            };
+        let fill = |pressed: bool| if pressed { 0.8 } else { 0.35 };
+        match &self.kind {
+            ControlKind::Button { held, .. } => vec![
+                quad(
+                    center_x,
+                    center_y,
+                    size,
+                    fill(*held > 0),
+                    Some(&bitmaps.disc),
+                ),
+                quad(center_x, center_y, size, 1.0, Some(&bitmaps.ring)),
+            ],
+            ControlKind::DPad { axis, .. } => {
+                let arm = size * DPAD_ARM_WIDTH;
+                let offset = (size - arm) / 2.0;
+                let [up, down, left, right] = axis.pressed();
+                let mut quads = vec![quad(center_x, center_y, arm, fill(false), None)];
+                for (dx, dy, pressed) in [
+                    (0.0, -1.0, up),
+                    (0.0, 1.0, down),
+                    (-1.0, 0.0, left),
+                    (1.0, 0.0, right),
+                ] {
+                    quads.push(quad(
+                        center_x + dx * offset,
+                        center_y + dy * offset,
+                        arm,
+                        fill(pressed),
+                        None,
+                    ));
+                }
+                quads
+            }
+            ControlKind::Stick { knob, .. } => {
+                let reach = size * (1.0 - KNOB_SIZE) / 2.0;
+                let pressed = *knob != (0.0, 0.0);
+                vec![
+                    quad(center_x, center_y, size, 1.0, Some(&bitmaps.ring)),
+                    quad(
+                        center_x + knob.0 * reach,
+                        center_y + knob.1 * reach,
+                        size * KNOB_SIZE,
+                        fill(pressed),
+                        Some(&bitmaps.disc),
+                    ),
+                ]
+            }
+        }
+    }
+}
+
+/// The bitmaps the controls are drawn with, made by `disc_bitmap` and `ring_bitmap`.
+pub struct ControlBitmaps {
+    pub disc: BitmapHandle,
+    pub ring: BitmapHandle,
+}
+
+/// A finger on the controls.
+#[derive(Debug, Clone, Copy)]
+enum Grab {
+    Control(usize),
+    /// Moving a control around, from where the finger is relative to its center.
+    Move(usize, (f64, f64)),
+    Handle,
+    /// Off the controls while editing them.
+    Ignored,
+}
+
+pub struct VirtualControls {
+    controls: Vec<Control>,
+    opacity: f32,
+    editable: bool,
+    editing: bool,
+    directions: Directions,
+    /// What each finger is on, by touch device and finger.
+    fingers: HashMap<(i64, i64), Grab>,
+    /// Where the places of the controls are saved.
+    layout_path: PathBuf,
+}
+
+impl VirtualControls {
+    /// Reads the `virtual_controls` section of the config, printing what's wrong with it and
+    /// leaving out the controls that can't be used. The controls are moved to the places saved
+    /// at `layout_path`, if any.
+    pub fn from_config(
+        config: VirtualControlsConfig,
+        directions: Directions,
+        layout_path: PathBuf,
+    ) -> Self {
+        let mut controls: Vec<_> = config
+            .controls
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, control)| Control::from_config(control, index + 1))
+            .collect();
+        if let Ok(saved) = std::fs::read_to_string(&layout_path) {
+            match ron::from_str::<Vec<(f64, f64)>>(&saved) {
+                Ok(places) if places.len() == controls.len() => {
+                    for (control, (x, y)) in controls.iter_mut().zip(places) {
+                        control.x = x / 100.0;
+                        control.y = y / 100.0;
+                    }
+                }
+                _ => println!(
+                    "Ignoring {}, which doesn't match the virtual controls",
+                    layout_path.display()
+                ),
+            }
+        }
+        Self {
+            controls,
+            opacity: config.opacity.unwrap_or(DEFAULT_OPACITY).min(100) as f32 / 100.0,
+            editable: config.editable.unwrap_or(false),
+            editing: false,
+            directions,
+            fingers: HashMap::new(),
+            layout_path,
+        }
+    }
+
+    /// Takes a finger going down at `position`, in screen pixels in the stage's orientation.
+    /// Returns `None` if it isn't on the controls, or what it pressed.
+    pub fn finger_down(
+        &mut self,
+        touch_id: i64,
+        finger_id: i64,
+        position: (f64, f64),
+        layout: &StageLayout,
+    ) -> Option<Vec<(Target, bool)>> {
+        let mut changes = self
+            .finger_up(touch_id, finger_id, layout)
+            .unwrap_or_default();
+        let grab = if self.editable && handle_contains(position, layout) {
+            Grab::Handle
+        } else {
+            // The last control is drawn on top, so it's the one touched.
+            let index = self
+                .controls
+                .iter()
+                .rposition(|control| control.contains(position, layout));
+            match index {
+                Some(index) if self.editing => {
+                    let (x, y, _) = self.controls[index].rect(layout);
+                    Grab::Move(index, (position.0 - x, position.1 - y))
+                }
+                Some(index) => {
+                    changes.extend(self.controls[index].press(position, layout, self.directions));
+                    Grab::Control(index)
+                }
+                // Nothing on the stage can be touched while editing.
+                None if self.editing => Grab::Ignored,
+                None => return (!changes.is_empty()).then_some(changes),
+            }
+        };
+        self.fingers.insert((touch_id, finger_id), grab);
+        Some(changes)
+    }
+
+    /// Takes a finger moving to `position`. Returns `None` if it isn't on the controls, or what
+    /// it pressed and released.
+    pub fn finger_motion(
+        &mut self,
+        touch_id: i64,
+        finger_id: i64,
+        position: (f64, f64),
+        layout: &StageLayout,
+    ) -> Option<Vec<(Target, bool)>> {
+        match *self.fingers.get(&(touch_id, finger_id))? {
+            Grab::Control(index) => {
+                Some(self.controls[index].touch(Some(position), layout, self.directions))
+            }
+            Grab::Move(index, (offset_x, offset_y)) => {
+                let (width, height) = layout.rotated_screen_size();
+                let control = &mut self.controls[index];
+                control.x = ((position.0 - offset_x) / width as f64).clamp(0.0, 1.0);
+                control.y = ((position.1 - offset_y) / height as f64).clamp(0.0, 1.0);
+                Some(vec![])
+            }
+            Grab::Handle | Grab::Ignored => Some(vec![]),
+        }
+    }
+
+    /// Takes a finger going up. Returns `None` if it wasn't on the controls, or what it
+    /// released.
+    pub fn finger_up(
+        &mut self,
+        touch_id: i64,
+        finger_id: i64,
+        layout: &StageLayout,
+    ) -> Option<Vec<(Target, bool)>> {
+        match self.fingers.remove(&(touch_id, finger_id))? {
+            Grab::Control(index) => Some(self.controls[index].touch(None, layout, self.directions)),
+            Grab::Move(..) | Grab::Ignored => Some(vec![]),
+            Grab::Handle => {
+                let changes = if self.editing {
+                    vec![]
+                } else {
+                    self.release_all(layout)
+                };
+                self.set_editing(!self.editing);
+                Some(changes)
+            }
+        }
+    }
+
+    fn set_editing(&mut self, editing: bool) {
+        self.editing = editing;
+        if editing {
+            return;
+        }
+        let places: Vec<_> = self
+            .controls
+            .iter()
+            .map(|control| (control.x * 100.0, control.y * 100.0))
+            .collect();
+        let saved = ron::to_string(&places)
+            .map_err(|e| e.to_string())
+            .and_then(|places| {
+                std::fs::write(&self.layout_path, places).map_err(|e| e.to_string())
+            });
+        if let Err(e) = saved {
+            println!("Couldn't save the virtual controls: {}", e);
+        }
+    }
+
+    /// Lets go of every control, e.g. when starting to edit them.
+    pub fn release_all(&mut self, layout: &StageLayout) -> Vec<(Target, bool)> {
+        self.fingers.clear();
+        let directions = self.directions;
+        self.controls
+            .iter_mut()
+            .flat_map(|control| control.release(layout, directions))
+            .collect()
+    }
+
+    /// The controls, drawn with `bitmaps`.
+    pub fn overlay(&self, bitmaps: &ControlBitmaps, layout: &StageLayout) -> Vec<OverlayQuad> {
+        let color = if self.editing { EDIT_COLOR } else { COLOR };
+        let mut quads: Vec<_> = self
+            .controls
+            .iter()
+            .flat_map(|control| control.overlay(layout, bitmaps, color, self.opacity))
+            .collect();
+        if self.editable {
+            let (width, height) = layout.rotated_screen_size();
+            let size = HANDLE_SIZE * height as f64;
+            quads.push(OverlayQuad {
+                x: (width as f64 - size) as f32,
+                y: 0.0,
+                width: size as f32,
+                height: size as f32,
+                color: [color[0], color[1], color[2], self.opacity],
+                bitmap: Some(bitmaps.ring.clone()),
+            });
+        }
+        quads
+    }
+}
+
+fn handle_contains((x, y): (f64, f64), layout: &StageLayout) -> bool {
+    let (width, height) = layout.rotated_screen_size();
+    let size = HANDLE_SIZE * height as f64;
+    x >= width as f64 - size && y <= size
+}
+
+/// A white disc, for the renderer to tint.
+pub fn disc_bitmap() -> Bitmap<'static> {
+    circle_bitmap(|distance| 0.5 - distance)
+}
+
+/// A white circle's outline, for the renderer to tint.
+pub fn ring_bitmap() -> Bitmap<'static> {
+    // The outline is an eighth of the radius thick.
+    let thickness = CIRCLE_SIZE as f64 / 16.0;
+    circle_bitmap(|distance| thickness / 2.0 + 0.5 - (distance + thickness / 2.0).abs())
+}
+
+/// Draws a white circle, with the alpha of each pixel given by `coverage` from its signed
+/// distance to the edge, in pixels, negative inside. Like every bitmap the renderer draws, its
+/// pixels are premultiplied.
+fn circle_bitmap(coverage: impl Fn(f64) -> f64) -> Bitmap<'static> {
+    let radius = CIRCLE_SIZE as f64 / 2.0;
+    let data = (0..CIRCLE_SIZE * CIRCLE_SIZE)
+        .flat_map(|i| {
+            let x = (i % CIRCLE_SIZE) as f64 + 0.5 - radius;
+            let y = (i / CIRCLE_SIZE) as f64 + 0.5 - radius;
+            let distance = x.hypot(y) - radius;
+            let alpha = (coverage(distance).clamp(0.0, 1.0) * 255.0).round() as u8;
+            [alpha, alpha, alpha, alpha]
+        })
+        .collect();
+    Bitmap::new(CIRCLE_SIZE, CIRCLE_SIZE, BitmapFormat::Rgba, data)
+}
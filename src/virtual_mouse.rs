@@ -0,0 +1,256 @@
+//! A pointer moved with a controller stick, or by turning the controller, for games that are
+//! played with the mouse.
+
+use std::str::FromStr;
+
+use ruffle_core::events::{GamepadButton, MouseButton};
+use ruffle_render::bitmap::{Bitmap, BitmapFormat, BitmapHandle};
+use ruffle_render_glow::OverlayQuad;
+use sdl2::controller::Axis;
+use serde::Deserialize;
+
+use crate::scaling::StageLayout;
+use crate::stick::Deadzone;
+
+/// How long the stick has to be held before the pointer reaches its full acceleration, in seconds.
+const ACCELERATION_TIME: f64 = 1.0;
+
+/// The screen height, in pixels, at which the cursor is drawn at its own size.
+const CURSOR_SCALE_HEIGHT: f64 = 360.0;
+
+/// The cursor, with `X` for its outline and `O` for its fill. The tip is the top left pixel.
+const CURSOR: [&str; 19] = [
+    "X...........",
+    "XX..........",
+    "XOX.........",
+    "XOOX........",
+    "XOOOX.......",
+    "XOOOOX......",
+    "XOOOOOX.....",
+    "XOOOOOOX....",
+    "XOOOOOOOX...",
+    "XOOOOOOOOX..",
+    "XOOOOOOOOOX.",
+    "XOOOOOOXXXXX",
+    "XOOOXOOX....",
+    "XOOXXOOX....",
+    "XOX..XOOX...",
+    "XX...XOOX...",
+    "X.....XOOX..",
+    "......XOOX..",
+    ".......XX...",
+];
+
+#[derive(Debug, Default, Deserialize)]
+pub struct VirtualMouseConfig {
+    /// "left" or "right".
+    stick: Option<String>,
+    /// Pointer speed at full deflection, in screen heights per second.
+    speed: Option<f64>,
+    /// How many times faster the pointer gets while the stick is held.
+    acceleration: Option<f64>,
+    /// The gamepad buttons that click, named like in `gamepad_config`.
+    left_click: Option<String>,
+    right_click: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+impl FromStr for Stick {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(Stick::Left),
+            "right" => Ok(Stick::Right),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Stick {
+    /// The axes of the stick, horizontal first.
+    fn axes(self) -> (Axis, Axis) {
+        match self {
+            Stick::Left => (Axis::LeftX, Axis::LeftY),
+            Stick::Right => (Axis::RightX, Axis::RightY),
+        }
+    }
+}
+
+pub struct VirtualMouse {
+    stick: Stick,
+    speed: f64,
+    acceleration: f64,
+    left_button: GamepadButton,
+    right_button: GamepadButton,
+    deadzone: Deadzone,
+    /// The stick's position, from -1 to 1 on each axis.
+    deflection: (f64, f64),
+    /// How long the stick has been out of the deadzone, in seconds.
+    held: f64,
+    /// The pointer, in screen pixels in the stage's orientation. Starts at the center.
+    position: Option<(f64, f64)>,
+}
+
+impl VirtualMouse {
+    /// Reads the `virtual_mouse` section of the config, printing what's wrong with it and falling
+    /// back to the defaults for those values.
+    pub fn from_config(config: VirtualMouseConfig) -> Self {
+        let stick = match config.stick {
+            Some(stick) => Stick::from_str(&stick).unwrap_or_else(|_| {
+                println!(
+                    "Unsupported virtual mouse stick {}, expected left or right",
+                    stick
+                );
+                Stick::Left
+            }),
+            None => Stick::Left,
+        };
+        let button = |name: Option<String>, default| match name {
+            Some(name) => GamepadButton::from_str(&name).unwrap_or_else(|_| {
+                println!("Unsupported virtual mouse button {}", name);
+                default
+            }),
+            None => default,
+        };
+        Self {
+            stick,
+            speed: config.speed.unwrap_or(0.8).max(0.0),
+            acceleration: config.acceleration.unwrap_or(2.0).max(1.0),
+            left_button: button(config.left_click, GamepadButton::South),
+            right_button: button(config.right_click, GamepadButton::East),
+            deadzone: Deadzone::default(),
+            deflection: (0.0, 0.0),
+            held: 0.0,
+            position: None,
+        }
+    }
+
+    pub fn stick(&self) -> Stick {
+        self.stick
+    }
+
+    pub fn set_stick(&mut self, stick: Stick) {
+        self.stick = stick;
+    }
+
+    pub fn set_deadzone(&mut self, deadzone: Deadzone) {
+        self.deadzone = deadzone;
+    }
+
+    /// Takes the motion of an axis of the pointer's stick. Returns false for other axes.
+    pub fn axis_motion(&mut self, axis: Axis, value: i16) -> bool {
+        let (x_axis, y_axis) = self.stick.axes();
+        let value = (value as f64 / i16::MAX as f64).clamp(-1.0, 1.0);
+        if axis == x_axis {
+            self.deflection.0 = value;
+        } else if axis == y_axis {
+            self.deflection.1 = value;
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// The mouse button a gamepad button clicks, if any.
+    pub fn button(&self, button: GamepadButton) -> Option<MouseButton> {
+        if button == self.left_button {
+            Some(MouseButton::Left)
+        } else if button == self.right_button {
+            Some(MouseButton::Right)
+        } else {
+            None
+        }
+    }
+
+    /// Moves the pointer for `dt` seconds. Returns whether it moved.
+    pub fn update(&mut self, dt: f64, layout: &StageLayout) -> bool {
+        let (width, height) = layout.rotated_screen_size();
+        let (width, height) = (width as f64, height as f64);
+        let (x, y) = self.position.unwrap_or((width / 2.0, height / 2.0));
+
+        let (dx, dy) = self.deadzone.apply(self.deflection.0, self.deflection.1);
+        let magnitude = dx.hypot(dy);
+        if magnitude <= 0.0 {
+            self.held = 0.0;
+            let moved = self.position.is_none();
+            self.position = Some((x, y));
+            return moved;
+        }
+        self.held = (self.held + dt).min(ACCELERATION_TIME);
+
+        // Small deflections move the pointer slowly, for precise aiming.
+        let strength = magnitude.min(1.0).powi(2);
+        let boost = 1.0 + (self.acceleration - 1.0) * self.held / ACCELERATION_TIME;
+        let distance = strength * boost * self.speed * height * dt;
+        let x = (x + dx / magnitude * distance).clamp(0.0, width - 1.0);
+        let y = (y + dy / magnitude * distance).clamp(0.0, height - 1.0);
+        self.position = Some((x, y));
+        true
+    }
+
+    /// Moves the pointer by `(dx, dy)` screen pixels, as with motion controls. Returns whether it
+    /// moved.
+    pub fn nudge(&mut self, dx: f64, dy: f64, layout: &StageLayout) -> bool {
+        if dx == 0.0 && dy == 0.0 {
+            return false;
+        }
+        let (width, height) = layout.rotated_screen_size();
+        let (x, y) = self.screen_position(layout);
+        self.position = Some((
+            (x + dx).clamp(0.0, width as f64 - 1.0),
+            (y + dy).clamp(0.0, height as f64 - 1.0),
+        ));
+        true
+    }
+
+    /// The pointer, in screen pixels in the stage's orientation.
+    pub fn screen_position(&self, layout: &StageLayout) -> (f64, f64) {
+        self.position.unwrap_or_else(|| {
+            let (width, height) = layout.rotated_screen_size();
+            (width as f64 / 2.0, height as f64 / 2.0)
+        })
+    }
+
+    /// The pointer on the stage.
+    pub fn stage_position(&self, layout: &StageLayout) -> (f64, f64) {
+        let (x, y) = self.screen_position(layout);
+        layout.rotated_to_stage(x, y)
+    }
+}
+
+/// The cursor drawn at `(x, y)` in screen pixels in the stage's orientation, using a bitmap made
+/// by `cursor_bitmap`.
+pub fn cursor_overlay(cursor: &BitmapHandle, x: f64, y: f64, layout: &StageLayout) -> OverlayQuad {
+    let (_, height) = layout.rotated_screen_size();
+    let scale = (height as f64 / CURSOR_SCALE_HEIGHT).round().max(1.0) as f32;
+    OverlayQuad {
+        x: x as f32,
+        y: y as f32,
+        width: CURSOR[0].len() as f32 * scale,
+        height: CURSOR.len() as f32 * scale,
+        color: [1.0; 4],
+        bitmap: Some(cursor.clone()),
+    }
+}
+
+/// Draws the cursor into a bitmap for the renderer.
+pub fn cursor_bitmap() -> Bitmap<'static> {
+    let width = CURSOR[0].len() as u32;
+    let height = CURSOR.len() as u32;
+    let data = CURSOR
+        .iter()
+        .flat_map(|row| row.bytes())
+        .flat_map(|pixel| match pixel {
+            b'X' => [0, 0, 0, 255],
+            b'O' => [255, 255, 255, 255],
+            _ => [0, 0, 0, 0],
+        })
+        .collect();
+    Bitmap::new(width, height, BitmapFormat::Rgba, data)
+}